@@ -3,16 +3,21 @@
 //! All functions accept FEN strings and return simple types suitable for
 //! serialization / tool-call responses.
 
-use std::panic;
+use std::sync::{Arc, Mutex};
 
 use crate::{
-    constants::{INFINITY_SCORE, NUM_SIDES, NUM_SQUARES},
+    constants::INFINITY_SCORE,
+    engine::{Engine, SearchLine},
     position::Position,
-    types::{Board, Side, Square},
+    syzygy::{Tablebases, Wdl},
+    types::{Board, EvalTrace, Score, Side, Square},
 };
 
-/// Fixed search depth used for `get_top_moves`. Shallow enough to be fast
-/// across all root moves while still producing tactically meaningful scores.
+/// Tablebases probed by [`probe_tablebase`], set with [`set_syzygy_path`]
+static TABLEBASES: Mutex<Option<Arc<Tablebases>>> = Mutex::new(None);
+
+/// Search depth used for `get_top_moves`. Shallow enough to be fast while
+/// still producing tactically meaningful scores.
 const TOP_MOVES_DEPTH: u16 = 4;
 
 /// A scored move returned by [`get_top_moves`].
@@ -23,66 +28,48 @@ pub struct ScoredMove {
     /// Evaluation from the side-to-move's perspective, in pawns.
     /// Positive = good for side to move.
     pub score: f64,
+    /// Forced mate in this many moves, if found. Negative = side to move gets mated.
+    pub mate: Option<i32>,
+}
+
+/// Formats as e.g. `"e2e4 +0.35"` or `"h5f7 M1"`.
+impl std::fmt::Display for ScoredMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mate {
+            Some(mate) => write!(f, "{} {}", self.mv, Score::Mate(mate)),
+            None => write!(f, "{} {:+.2}", self.mv, self.score),
+        }
+    }
 }
 
 /// Return the top `n` moves for the position given by `fen`, ranked by engine
-/// evaluation (best first). Each move is searched to [`TOP_MOVES_DEPTH`] so
+/// evaluation (best first). Uses a MultiPV search to [`TOP_MOVES_DEPTH`], so
 /// scores are directly comparable across moves.
 pub fn get_top_moves(fen: &str, n: usize) -> Result<Vec<ScoredMove>, String> {
-    let mut pos = Position::from_fen(fen).map_err(|e| e.to_string())?;
-    pos.set_material_scores();
-
-    // Generate all pseudo-legal moves at the root
-    pos.ply = 0;
-    pos.first_move[0] = 0;
-    pos.generate_moves_and_captures(pos.side, |_, _, _| 0);
+    let position = Position::from_fen(fen).map_err(|e| e.to_string())?;
 
-    let mut scored: Vec<ScoredMove> = Vec::new();
-    let move_end = pos.first_move[1]; // first_move[ply+1] where ply=0
+    let mut engine = Engine::default();
+    engine.set_position(position);
+    engine.search_settings.max_depth = TOP_MOVES_DEPTH;
+    engine.search_settings.multi_pv = n.max(1);
 
-    for i in pos.first_move[0]..move_end {
-        let mv = match pos.move_list[i as usize] {
-            Some(m) => m,
-            None => continue,
-        };
+    let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
-        if !pos.make_move(mv.from, mv.to, mv.promote) {
-            continue; // Illegal (leaves king in check)
-        }
-
-        // Search from the opponent's perspective, then negate.
-        // Wrapped in catch_unwind because the engine uses panics as control
-        // flow for NodeLimitReached / TimeExhausted.
-        let mut history_table = [[[0isize; NUM_SQUARES]; NUM_SQUARES]; NUM_SIDES]; // TODO: Can `.search()` default this?
-        let search_result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
-            pos.search(
-                -INFINITY_SCORE,
-                INFINITY_SCORE,
-                TOP_MOVES_DEPTH - 1,
-                &mut history_table,
-                None,
-            )
-        }));
-
-        // Restore position to the pre-root-move state regardless of panic
-        while pos.ply > 0 {
-            pos.take_back_move();
-        }
-
-        if let Ok(score) = search_result {
-            scored.push(ScoredMove {
+    Ok(result
+        .lines
+        .iter()
+        .take(n)
+        .filter_map(|line| {
+            line.best_move().map(|mv| ScoredMove {
                 mv: Board::move_to_uci_string(mv.from, mv.to, mv.promote, false),
-                score: -score as f64 / 100.0, // Negate: score is from opponent's POV
-            });
-        }
-        // If search panicked (aborted early), skip this move
-    }
-
-    // Best first (highest score = best for side-to-move)
-    scored.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-    scored.truncate(n);
-
-    Ok(scored)
+                score: line.score.to_search_score() as f64 / 100.0,
+                mate: match line.score {
+                    Score::Mate(moves) => Some(moves),
+                    Score::Centipawns(_) => None,
+                },
+            })
+        })
+        .collect())
 }
 
 /// Apply a sequence of moves (in UCI notation, e.g. `["e2e4", "e7e5"]`) to the
@@ -108,12 +95,24 @@ pub fn evaluate_position(fen: &str) -> Result<f64, String> {
     let mut pos = Position::from_fen(fen).map_err(|e| e.to_string())?;
     pos.set_material_scores();
     // Resolves captures but doesn't do a full tree search
-    let raw = pos.quiescence_search(-INFINITY_SCORE, INFINITY_SCORE, 0, Some(100_000));
+    let raw = pos
+        .quiescence_search(-INFINITY_SCORE, INFINITY_SCORE, 0, Some(100_000))
+        .map_err(|e| e.to_string())?;
     let white_score = if pos.side == Side::White { raw } else { -raw };
 
     Ok(white_score as f64 / 100.0)
 }
 
+/// Return the static evaluation of the position broken down by term (material,
+/// piece-square tables, pawn structure, rook files, king shelter and safety, ...)
+/// for each side, in centipawns. Prints as a table.
+pub fn evaluate_with_trace(fen: &str) -> Result<EvalTrace, String> {
+    let mut pos = Position::from_fen(fen).map_err(|e| e.to_string())?;
+    pos.set_material_scores();
+
+    Ok(pos.evaluate_with_trace())
+}
+
 /// Return all legal moves in the position as UCI strings (e.g. `"e2e4"`).
 pub fn get_legal_moves(fen: &str) -> Result<Vec<String>, String> {
     let mut pos = Position::from_fen(fen).map_err(|e| e.to_string())?;
@@ -159,3 +158,88 @@ pub fn is_square_attacked(fen: &str, square: &str, by_color: &str) -> Result<boo
 
     Ok(pos.is_square_attacked_by_side(side, sq))
 }
+
+/// Return the Static Exchange Evaluation of `move_str` (UCI notation) in
+/// centipawns: the material the moving side wins if both sides keep
+/// recapturing on the destination square. Negative = the capture loses material.
+pub fn static_exchange_evaluation(fen: &str, move_str: &str) -> Result<i32, String> {
+    let mut pos = Position::from_fen(fen).map_err(|e| e.to_string())?;
+
+    if !pos.get_legal_moves().contains(&move_str.to_string()) {
+        return Err(format!("Illegal move: {}", move_str));
+    }
+
+    let mv = Board::move_from_uci_string(move_str)?;
+    Ok(pos.static_exchange_evaluation(mv.from, mv.to, mv.promote))
+}
+
+/// A position's result from the endgame tablebases, returned by [`probe_tablebase`].
+#[derive(Debug, Clone)]
+pub struct TablebaseProbe {
+    /// Win, draw or loss for the side to move, ignoring the fifty-move counter.
+    pub wdl: Wdl,
+    /// Plies until the next capture or pawn move on the way to the result, if the
+    /// DTZ table is available. Positive = side to move wins, 0 = draw.
+    pub dtz: Option<i32>,
+    /// The moves that keep the best result (UCI strings, sorted). With the DTZ
+    /// tables, only the fastest when the fifty-move rule is in sight.
+    pub best_moves: Vec<String>,
+}
+
+/// Load the Syzygy tablebases (`.rtbw`/`.rtbz` files) that [`probe_tablebase`]
+/// reads from `path`: directories separated by `:` (`;` on Windows). An empty
+/// path unloads them. Returns the number of table files found.
+pub fn set_syzygy_path(path: &str) -> Result<usize, String> {
+    let tablebases = if path.is_empty() {
+        None
+    } else {
+        Some(Arc::new(Tablebases::open(path).map_err(|e| e.to_string())?))
+    };
+    let num_tables = tablebases
+        .as_ref()
+        .map_or(0, |tablebases| tablebases.num_tables());
+
+    *TABLEBASES.lock().unwrap() = tablebases;
+
+    Ok(num_tables)
+}
+
+/// Look the position given by `fen` up in the tablebases loaded with
+/// [`set_syzygy_path`]. Fails if none are loaded or they don't cover the
+/// position (too many pieces, castling rights, missing table).
+pub fn probe_tablebase(fen: &str) -> Result<TablebaseProbe, String> {
+    let tablebases = TABLEBASES
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No tablebases loaded. Call set_syzygy_path first.")?;
+
+    let mut pos = Position::from_fen(fen).map_err(|e| e.to_string())?;
+    pos.set_tablebases(Some(Arc::clone(&tablebases)));
+
+    let wdl = pos.probe_wdl();
+    let dtz = pos.probe_dtz();
+    let ranked_moves = pos.tablebase_root_moves().unwrap_or_default();
+
+    // A table that fails to read isn't probed again, so this is the only report of it
+    let errors = tablebases.take_errors();
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    let wdl = wdl.ok_or("Position is not in the tablebases")?;
+
+    let best_rank = ranked_moves.iter().map(|&(_, rank)| rank).max();
+
+    let mut best_moves: Vec<String> = ranked_moves
+        .iter()
+        .filter(|&&(_, rank)| Some(rank) == best_rank)
+        .map(|(mv, _)| Board::move_to_uci_string(mv.from, mv.to, mv.promote, false))
+        .collect();
+    best_moves.sort();
+
+    Ok(TablebaseProbe {
+        wdl,
+        dtz,
+        best_moves,
+    })
+}
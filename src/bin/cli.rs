@@ -1,6 +1,6 @@
-use chess_engine::engine::{Engine, SearchResult};
+use chess_engine::engine::{Engine, SearchLine, SearchResult};
 use chess_engine::position::Position;
-use chess_engine::types::{Board, Difficulty, GameState, MoveData, Side};
+use chess_engine::types::{Board, Difficulty, GameState, MoveData, ScoreBound, Side};
 use rand::Rng;
 use std::io::{self, Write};
 
@@ -55,6 +55,7 @@ impl CommandLineInterface {
         println!("moves     - Displays of list of possible moves");
         println!("fen       - Displays a FEN string for the current position");
         println!("hash      - Displays hash key for the current position");
+        println!("eval      - Displays the evaluation broken down by term");
         println!("q or quit - Quits the program");
         println!("================= CONTROLLING THE ENGINE =================");
         println!("go        - Starts the engine from the current position");
@@ -71,6 +72,7 @@ impl CommandLineInterface {
         println!("sd <depth>   - Sets the maximum search depth");
         println!("st <seconds> - Sets the time limit per move in seconds");
         println!("sn <nodes>   - Sets the maximum search nodes");
+        println!("tb <path>    - Probes the Syzygy tablebases in <path>");
     }
 
     fn display_board(&self) {
@@ -210,6 +212,10 @@ impl CommandLineInterface {
                     println!("\n{}", self.engine.position.to_fen());
                     continue;
                 }
+                "eval" => {
+                    println!("\n{}", self.engine.evaluation_report());
+                    continue;
+                }
                 "hash" => {
                     println!(
                         "\nHash key: {:016X}",
@@ -269,7 +275,7 @@ impl CommandLineInterface {
                 println!();
                 match Position::from_fen(fen_str) {
                     Ok(position) => {
-                        self.engine.position = position;
+                        self.engine.set_position(position);
                         println!("FEN loaded successfully");
                         self.display_board();
                     }
@@ -303,6 +309,14 @@ impl CommandLineInterface {
                 continue;
             }
 
+            if let Some(path) = command.strip_prefix("tb ") {
+                match self.engine.set_syzygy_path(path.trim()) {
+                    Ok(num_tables) => println!("\nFound {} tablebase files", num_tables),
+                    Err(e) => println!("\n{}", e),
+                }
+                continue;
+            }
+
             // PARSE "FROM" AND THEN "TO" SQUARE
             if command.len() < 2 {
                 println!("\nINVALID COMMAND!");
@@ -447,8 +461,7 @@ impl CommandLineInterface {
             }
         };
 
-        self.engine.difficulty = Some(difficulty);
-        self.engine.search_settings.max_depth = difficulty.max_depth() as u16;
+        self.engine.set_difficulty(Some(difficulty));
 
         println!(
             "Difficulty set to {} (max depth: {})",
@@ -479,6 +492,22 @@ impl CommandLineInterface {
             0
         };
 
+        let hash_hits = self.engine.position.hash_hits;
+        let hash_stores = self.engine.position.hash_stores;
+        let hash_hit_rate = if total_nodes > 0 {
+            (hash_hits as f64 / total_nodes as f64 * 100.0) as u64
+        } else {
+            0
+        };
+
+        let pawn_hash_probes = self.engine.position.pawn_hash_probes.get();
+        let pawn_hash_hits = self.engine.position.pawn_hash_hits.get();
+        let pawn_hash_hit_rate = if pawn_hash_probes > 0 {
+            (pawn_hash_hits as f64 / pawn_hash_probes as f64 * 100.0) as u64
+        } else {
+            0
+        };
+
         if !result.from_book && !result.principal_variation.is_empty() {
             print!("  PV:  ");
             for (i, MoveData { from, to, promote }) in result.principal_variation.iter().enumerate()
@@ -521,6 +550,18 @@ impl CommandLineInterface {
                 format_with_commas(beta_cutoffs as u64),
                 cutoff_rate
             );
+            println!(
+                "│ Hash stores: {:>12}  │  Hash hits:    {:>12} ({}%)  │",
+                format_with_commas(hash_stores as u64),
+                format_with_commas(hash_hits as u64),
+                hash_hit_rate
+            );
+            println!(
+                "│ Pawn probes: {:>12}  │  Pawn hits:    {:>12} ({}%)  │",
+                format_with_commas(pawn_hash_probes as u64),
+                format_with_commas(pawn_hash_hits as u64),
+                pawn_hash_hit_rate
+            );
 
             println!("└─────────────────────────────────────────────────────────────────┘");
         }
@@ -531,22 +572,23 @@ impl CommandLineInterface {
         println!("│ DEPTH │    NODES     │  SCORE   │     BEST MOVE      │");
         println!("├───────┼──────────────┼──────────┼────────────────────┤");
 
-        let result = self
-            .engine
-            .think(Some(|depth, score, position: &mut Position| {
+        let result = self.engine.think(Some(
+            |depth, lines: &[SearchLine], position: &mut Position| {
+                // Only show completed depths, not failed aspiration windows
+                if lines.iter().any(|line| line.bound != ScoreBound::Exact) {
+                    return;
+                }
+
+                let best_line = lines.first();
+
                 print!(
                     "│ {:>5} │ {:>12} │ {:>8} │ ",
                     depth,
-                    format_with_commas(position.nodes as u64),
-                    score
+                    format_with_commas(position.total_nodes() as u64),
+                    best_line.map_or(String::new(), |line| line.score.to_string())
                 );
 
-                if let Some(move_) = position
-                    .pv_table
-                    .get(0)
-                    .and_then(|ply| ply.get(0))
-                    .and_then(|&m| m)
-                {
+                if let Some(move_) = best_line.and_then(|line| line.best_move()) {
                     print!(
                         "{:^18} ",
                         Board::move_to_uci_string(move_.from, move_.to, move_.promote, true)
@@ -557,10 +599,15 @@ impl CommandLineInterface {
 
                 println!("│");
                 std::io::Write::flush(&mut std::io::stdout()).unwrap();
-            }));
+            },
+        ));
 
         println!("└───────┴──────────────┴──────────┴────────────────────┘");
 
+        for error in self.engine.take_tablebase_errors() {
+            println!("{}", error);
+        }
+
         let (from, to, promote) =
             if let (Some(from), Some(to)) = (result.best_move_from, result.best_move_to) {
                 (from, to, result.best_move_promote)
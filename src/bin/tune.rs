@@ -0,0 +1,96 @@
+use chess_engine::params::EvalParams;
+use chess_engine::tuning::{Tuner, load_positions};
+use std::process;
+use std::thread;
+use std::time::Instant;
+
+const DEFAULT_MAX_PASSES: usize = 100;
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: chess-engine-tune <positions file> <output file> [--params <file>] [--threads <n>] [--passes <n>]"
+    );
+    eprintln!();
+    eprintln!("Each line of the positions file is a FEN or EPD followed by the game's result,");
+    eprintln!("e.g. `<fen> [0.5]` or `<epd> c9 \"1-0\";`.");
+    eprintln!("Tuning starts from the built-in parameters, or those in --params, and writes the");
+    eprintln!("best parameters found to the output file after each pass.");
+    process::exit(1);
+}
+
+fn parse_number(value: Option<String>) -> usize {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| usage())
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let (Some(positions_path), Some(output_path)) = (args.next(), args.next()) else {
+        usage();
+    };
+
+    let mut params = EvalParams::default();
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut max_passes = DEFAULT_MAX_PASSES;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--params" => {
+                let path = args.next().unwrap_or_else(|| usage());
+
+                params = EvalParams::load(&path).unwrap_or_else(|e| {
+                    eprintln!("Failed to load parameters from {}: {}", path, e);
+                    process::exit(1);
+                });
+            }
+            "--threads" => threads = parse_number(args.next()),
+            "--passes" => max_passes = parse_number(args.next()),
+            _ => usage(),
+        }
+    }
+
+    let positions = load_positions(&positions_path).unwrap_or_else(|e| {
+        eprintln!("Failed to load positions from {}: {}", positions_path, e);
+        process::exit(1);
+    });
+
+    let mut tuner = Tuner::new(positions, threads);
+    println!(
+        "Loaded {} positions, tuning {} values on {} threads",
+        tuner.num_positions(),
+        params.values().len(),
+        threads
+    );
+
+    let k = tuner.fit_k(&params);
+    let mut error = tuner.error(&params);
+    println!("K = {:.3}, error = {:.8}", k, error);
+
+    let start = Instant::now();
+
+    for pass in 1..=max_passes {
+        let new_error = tuner.local_search_pass(&mut params, error, 1);
+
+        if let Err(e) = params.save(&output_path) {
+            eprintln!("Failed to write {}: {}", output_path, e);
+            process::exit(1);
+        }
+
+        println!(
+            "Pass {}: error = {:.8} ({:.1}s)",
+            pass,
+            new_error,
+            start.elapsed().as_secs_f64()
+        );
+
+        if new_error >= error {
+            break;
+        }
+
+        error = new_error;
+    }
+
+    println!("Parameters written to {}", output_path);
+}
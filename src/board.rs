@@ -1,11 +1,14 @@
+use std::sync::Arc;
+
 use crate::{
     constants::{
-        BISHOP_SCORE, COLUMN, FLIPPED_BOARD_SQUARE, INIT_BOARD, INIT_COLOR, KING_ENDGAME_SCORE,
-        KING_SCORE, KNIGHT_SCORE, NORTH_EAST_DIAGONAL, NORTH_WEST_DIAGONAL, NUM_PIECE_TYPES,
-        NUM_SIDES, NUM_SQUARES, PASSED_SCORE, PAWN_SCORE, QUEEN_SCORE, ROOK_SCORE, ROW,
+        COLUMN, FLIPPED_BOARD_SQUARE, INIT_BOARD, INIT_COLOR, NORTH_EAST_DIAGONAL,
+        NORTH_WEST_DIAGONAL, NUM_PIECE_TYPES, NUM_SIDES, NUM_SQUARES, ROW,
     },
     hash::Hash,
-    types::{BitBoard, Board, MoveData, Piece, Side, Square},
+    nnue::{Accumulator, Network},
+    params::EvalParams,
+    types::{BitBoard, Board, MoveData, Piece, Side, Square, TaperedScore},
 };
 
 impl Board {
@@ -41,6 +44,7 @@ impl Board {
             bit_units,
             bit_all,
             hash,
+            nnue: None,
         }
     }
 
@@ -51,6 +55,7 @@ impl Board {
             bit_units: [BitBoard(0); NUM_SIDES],
             bit_all: BitBoard(0),
             hash: Hash::new(),
+            nnue: None,
         }
     }
 
@@ -62,6 +67,11 @@ impl Board {
 
         self.value[square as usize] = piece;
         self.hash.toggle_piece(side, piece, square);
+
+        if let Some(nnue) = &mut self.nnue {
+            nnue.add(side, piece, square);
+        }
+
         self.bit_pieces[side as usize][piece as usize].set_bit(square);
         self.bit_units[side as usize].set_bit(square);
         self.bit_all.set_bit(square);
@@ -73,6 +83,11 @@ impl Board {
         }
 
         self.hash.toggle_piece(side, piece, square);
+
+        if let Some(nnue) = &mut self.nnue {
+            nnue.remove(side, piece, square);
+        }
+
         self.value[square as usize] = Piece::Empty;
         self.bit_pieces[side as usize][piece as usize].clear_bit(square);
         self.bit_units[side as usize].clear_bit(square);
@@ -93,6 +108,10 @@ impl Board {
         self.hash.toggle_piece(side, piece, from);
         self.hash.toggle_piece(side, piece, to);
 
+        if let Some(nnue) = &mut self.nnue {
+            nnue.move_piece(side, piece, from, to);
+        }
+
         self.value[from as usize] = Piece::Empty;
         self.value[to as usize] = piece;
 
@@ -100,6 +119,16 @@ impl Board {
         self.bit_pieces[side as usize][piece as usize].set_bit(to);
     }
 
+    /// Evaluate with `network` from now on, or with the handcrafted evaluation if `None`
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(|network| Accumulator::new(network, self));
+    }
+
+    /// The network evaluating this board, if any
+    pub fn network(&self) -> Option<Arc<Network>> {
+        self.nnue.as_ref().map(|nnue| Arc::clone(nnue.network()))
+    }
+
     /// Parse a UCI move string (e.g. "e2e4", "e7e8q") and return the from/to squares and promotion piece
     pub fn move_from_uci_string(move_str: &str) -> Result<MoveData, String> {
         if move_str.len() < 4 || move_str.len() > 5 {
@@ -413,143 +442,6 @@ impl Board {
         bit_between
     }
 
-    /// Return the square at the border of the board when going
-    /// in the direction of `plus` from the given square.
-    fn get_edge(square: Square, plus: i32) -> Square {
-        let mut square_index = square as i32;
-
-        loop {
-            square_index += plus;
-
-            if square_index < 0 || square_index >= 64 {
-                square_index -= plus; // Step back to last valid square
-                break;
-            }
-
-            if COLUMN[square_index as usize] == 0
-                || COLUMN[square_index as usize] == 7
-                || ROW[square_index as usize] == 0
-                || ROW[square_index as usize] == 7
-            {
-                break;
-            }
-        }
-
-        square_index
-            .try_into()
-            .expect("Failed to convert square index to Square")
-    }
-
-    pub fn get_bit_after() -> [[BitBoard; NUM_SQUARES]; NUM_SQUARES] {
-        let mut bit_after = [[BitBoard(0); NUM_SQUARES]; NUM_SQUARES];
-
-        fn compute_after_and_set_bitboard(
-            bitboard: &mut BitBoard,
-            square: Square,
-            square_2: Square,
-            edge_squares: (Square, Square),
-            increments: (i32, i32),
-        ) {
-            let mut current_index = square_2 as i32;
-
-            if (square as u8) < (square_2 as u8) {
-                while current_index <= (edge_squares.1 as i32) {
-                    bitboard.set_bit(
-                        current_index
-                            .try_into()
-                            .expect("Failed to convert square index to Square"),
-                    );
-
-                    current_index += increments.1;
-                }
-            } else {
-                while current_index >= (edge_squares.0 as i32) {
-                    bitboard.set_bit(
-                        current_index
-                            .try_into()
-                            .expect("Failed to convert square index to Square"),
-                    );
-
-                    current_index += increments.0;
-                }
-            }
-        }
-
-        for square in Square::iter() {
-            for square_2 in Square::iter() {
-                let bitboard = &mut bit_after[square as usize][square_2 as usize];
-
-                // Same rank
-                if ROW[square as usize] == ROW[square_2 as usize] {
-                    compute_after_and_set_bitboard(
-                        bitboard,
-                        square,
-                        square_2,
-                        (
-                            (ROW[square_2 as usize] * 8)
-                                .try_into()
-                                .expect("Failed to convert square index to Square"),
-                            (ROW[square_2 as usize] * 8 + 7)
-                                .try_into()
-                                .expect("Failed to convert square index to Square"),
-                        ),
-                        (-1, 1),
-                    );
-                }
-
-                // Same file
-                if COLUMN[square as usize] == COLUMN[square_2 as usize] {
-                    compute_after_and_set_bitboard(
-                        bitboard,
-                        square,
-                        square_2,
-                        (
-                            COLUMN[square_2 as usize]
-                                .try_into()
-                                .expect("Failed to convert square index to Square"),
-                            (COLUMN[square_2 as usize] + 56)
-                                .try_into()
-                                .expect("Failed to convert square index to Square"),
-                        ),
-                        (-8, 8),
-                    );
-                }
-
-                // Northwest diagonal
-                if NORTH_WEST_DIAGONAL[square as usize] == NORTH_WEST_DIAGONAL[square_2 as usize] {
-                    compute_after_and_set_bitboard(
-                        bitboard,
-                        square,
-                        square_2,
-                        (Board::get_edge(square, -7), Board::get_edge(square, 7)),
-                        (-7, 7),
-                    );
-                }
-
-                // Northeast diagonal
-                if NORTH_EAST_DIAGONAL[square as usize] == NORTH_EAST_DIAGONAL[square_2 as usize] {
-                    compute_after_and_set_bitboard(
-                        bitboard,
-                        square,
-                        square_2,
-                        (Board::get_edge(square, -9), Board::get_edge(square, 9)),
-                        (-9, 9),
-                    );
-                }
-            }
-        }
-
-        // Zeros will represent the square and those after it
-        for square in Square::iter() {
-            for square_2 in Square::iter() {
-                bit_after[square as usize][square_2 as usize].0 =
-                    !bit_after[square as usize][square_2 as usize].0
-            }
-        }
-
-        bit_after
-    }
-
     pub fn get_knight_moves() -> [BitBoard; NUM_SQUARES] {
         let mut bit_knight_moves = [BitBoard(0); NUM_SQUARES];
 
@@ -635,87 +527,34 @@ impl Board {
         (mask_queenside, mask_kingside)
     }
 
-    pub fn get_queen_rook_bishop_moves() -> (
-        [BitBoard; NUM_SQUARES],
-        [BitBoard; NUM_SQUARES],
-        [BitBoard; NUM_SQUARES],
-    ) {
-        let mut bit_queen_moves = [BitBoard(0); NUM_SQUARES];
-        let mut bit_rook_moves = [BitBoard(0); NUM_SQUARES];
-        let mut bit_bishop_moves = [BitBoard(0); NUM_SQUARES];
-
-        for square in Square::iter() {
-            let square_ = square as usize;
-
-            for square_2 in Square::iter() {
-                let square_2_ = square_2 as usize;
-
-                if square != square_2 {
-                    if NORTH_WEST_DIAGONAL[square_] == NORTH_WEST_DIAGONAL[square_2_]
-                        || NORTH_EAST_DIAGONAL[square_] == NORTH_EAST_DIAGONAL[square_2_]
-                    {
-                        bit_queen_moves[square_].set_bit(square_2);
-                        bit_bishop_moves[square_].set_bit(square_2);
-                    }
-
-                    if ROW[square_] == ROW[square_2_] || COLUMN[square_] == COLUMN[square_2_] {
-                        bit_queen_moves[square_].set_bit(square_2);
-                        bit_rook_moves[square_].set_bit(square_2);
-                    }
-                }
-            }
-        }
-
-        (bit_queen_moves, bit_rook_moves, bit_bishop_moves)
-    }
-
-    pub fn get_score_tables() -> (
-        [[[i32; NUM_SQUARES]; NUM_PIECE_TYPES]; NUM_SIDES],
-        [[i32; NUM_SQUARES]; NUM_SIDES],
-        [[i32; NUM_SQUARES]; NUM_SIDES],
+    /// Piece-square tables and passed pawn bonuses for each side
+    pub fn get_score_tables(
+        params: &EvalParams,
+    ) -> (
+        [[[TaperedScore; NUM_SQUARES]; NUM_PIECE_TYPES]; NUM_SIDES],
+        [[TaperedScore; NUM_SQUARES]; NUM_SIDES],
     ) {
-        let mut square_score = [[[0; NUM_SQUARES]; NUM_PIECE_TYPES]; NUM_SIDES];
-        let mut king_endgame_score = [[0; NUM_SQUARES]; NUM_SIDES];
-        let mut passed_pawns_score = [[0; NUM_SQUARES]; NUM_SIDES];
-
-        let (white, black) = (Side::White as usize, Side::Black as usize);
-        let (pawn, knight, bishop, rook, queen, king) = (
-            Piece::Pawn as usize,
-            Piece::Knight as usize,
-            Piece::Bishop as usize,
-            Piece::Rook as usize,
-            Piece::Queen as usize,
-            Piece::King as usize,
-        );
+        let mut square_score =
+            [[[TaperedScore::default(); NUM_SQUARES]; NUM_PIECE_TYPES]; NUM_SIDES];
+        let mut passed_pawns_score = [[TaperedScore::default(); NUM_SQUARES]; NUM_SIDES];
 
         for square in Square::iter() {
-            // Square score
             let sq = square as usize;
             let flipped_sq = FLIPPED_BOARD_SQUARE[sq] as usize;
 
-            square_score[white][pawn][sq] = PAWN_SCORE[sq] + Piece::Pawn.value();
-            square_score[white][knight][sq] = KNIGHT_SCORE[sq] + Piece::Knight.value();
-            square_score[white][bishop][sq] = BISHOP_SCORE[sq] + Piece::Bishop.value();
-            square_score[white][rook][sq] = ROOK_SCORE[sq] + Piece::Rook.value();
-            square_score[white][queen][sq] = QUEEN_SCORE[sq] + Piece::Queen.value();
-            square_score[white][king][sq] = KING_SCORE[sq];
-
-            square_score[black][pawn][sq] = PAWN_SCORE[flipped_sq] + Piece::Pawn.value();
-            square_score[black][knight][sq] = KNIGHT_SCORE[flipped_sq] + Piece::Knight.value();
-            square_score[black][bishop][sq] = BISHOP_SCORE[flipped_sq] + Piece::Bishop.value();
-            square_score[black][rook][sq] = ROOK_SCORE[flipped_sq] + Piece::Rook.value();
-            square_score[black][queen][sq] = QUEEN_SCORE[flipped_sq] + Piece::Queen.value();
-            square_score[black][king][sq] = KING_SCORE[flipped_sq];
-
-            // King endgame score
-            king_endgame_score[white][sq] = KING_ENDGAME_SCORE[sq] - square_score[white][king][sq];
-            king_endgame_score[black][sq] = KING_ENDGAME_SCORE[sq] - square_score[black][king][sq];
-
-            // Passed pawns score
-            passed_pawns_score[white][sq] = PASSED_SCORE[sq];
-            passed_pawns_score[black][sq] = PASSED_SCORE[flipped_sq];
+            // Black's tables are White's, mirrored top to bottom
+            for (side, table_sq) in [(Side::White, sq), (Side::Black, flipped_sq)] {
+                for (scores, table) in square_score[side as usize]
+                    .iter_mut()
+                    .zip(&params.piece_square)
+                {
+                    scores[sq] = table[table_sq];
+                }
+
+                passed_pawns_score[side as usize][sq] = params.passed_pawn[table_sq];
+            }
         }
 
-        (square_score, king_endgame_score, passed_pawns_score)
+        (square_score, passed_pawns_score)
     }
 }
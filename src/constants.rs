@@ -1,10 +1,22 @@
+use crate::types::TaperedScore;
+
 /// Depth
 pub const DEFAULT_MAX_DEPTH: u16 = 9;
 pub const DEFAULT_MAX_QUIESCENCE_DEPTH: u16 = 6;
+pub const DEFAULT_BENCH_DEPTH: u16 = 9;
+
+/// Quiescence search also tries quiet checks in this many plies at its start (0 to disable)
+pub const QUIESCENCE_CHECK_PLIES: u16 = 1;
+
+/// Delta pruning: quiescence skips a capture when the standing pat plus the captured piece and
+/// this margin still can't raise alpha
+pub const DELTA_PRUNING_MARGIN: i32 = 200;
 
 /// Time
 pub const DEFAULT_PLAYER_TIME_REMAINING_MS: u64 = 120_000; // 2 minutes
 pub const DEFAULT_PLAYER_INCREMENT_MS: u64 = 0;
+pub const DEFAULT_MOVE_OVERHEAD_MS: u64 = 10; // Reserved per move for communication delays
+pub const DEFAULT_MOVES_TO_GO: u64 = 30; // Moves assumed left when the time control doesn't say
 
 pub const SOFT_TO_HARD_LIMIT_RATIO: f64 = 0.75; // Hard limit is 1/30 of time remaining, plus increment
 
@@ -14,6 +26,56 @@ pub const MAX_HISTORY_SCORE: isize = 10_000;
 pub const MATE_SCORE: i32 = 10_000;
 pub const MATE_THRESHOLD: i32 = 9000;
 pub const INFINITY_SCORE: i32 = 10_000;
+pub const TB_WIN_SCORE: i32 = MATE_THRESHOLD - MAX_PLY as i32; // Tablebase win, less the ply; below mate scores
+
+/// Stack size for search threads: the recursive search needs more than the 2 MB default
+/// (the Makefile gives tests the same with `RUST_MIN_STACK`)
+pub const SEARCH_THREAD_STACK_SIZE: usize = 32 * 1024 * 1024;
+
+/// Aspiration windows: from `ASPIRATION_MIN_DEPTH`, each iteration searches a window of
+/// `ASPIRATION_WINDOW` either side of the previous score, doubling it on each fail-low or fail-high
+pub const ASPIRATION_MIN_DEPTH: u16 = 4;
+pub const ASPIRATION_WINDOW: i32 = 25;
+
+/// Null-move pruning: only at this depth or more, reducing the null search by
+/// `NULL_MOVE_REDUCTION` plus one ply for every `NULL_MOVE_REDUCTION_DEPTH_DIVISOR` of depth
+pub const NULL_MOVE_MIN_DEPTH: u16 = 3;
+pub const NULL_MOVE_REDUCTION: u16 = 2;
+pub const NULL_MOVE_REDUCTION_DEPTH_DIVISOR: u16 = 6;
+
+/// Late move reductions: from `LMR_MIN_DEPTH`, quiet moves after the first `LMR_FULL_DEPTH_MOVES`
+/// are searched `LMR_BASE + ln(depth) * ln(move number) / LMR_DIVISOR` plies shallower
+pub const LMR_MIN_DEPTH: u16 = 3;
+pub const LMR_FULL_DEPTH_MOVES: usize = 3;
+pub const LMR_BASE: f64 = 0.75;
+pub const LMR_DIVISOR: f64 = 2.25;
+
+/// Late move pruning: up to `LMP_MAX_DEPTH`, quiet moves are skipped once
+/// `LMP_BASE_MOVES + depth * depth` of them have been searched
+pub const LMP_MAX_DEPTH: u16 = 3;
+pub const LMP_BASE_MOVES: usize = 3;
+
+/// Frontier pruning margins, indexed by remaining depth; deeper nodes aren't pruned.
+/// Reverse futility returns the static evaluation when it beats beta by the margin, razoring
+/// drops into quiescence when it trails alpha by the margin, and futility pruning skips quiet
+/// moves when even the margin wouldn't lift it above alpha.
+pub const REVERSE_FUTILITY_MARGIN: [i32; 7] = [0, 90, 180, 270, 360, 450, 540];
+pub const RAZORING_MARGIN: [i32; 3] = [0, 300, 550];
+pub const FUTILITY_MARGIN: [i32; 4] = [0, 150, 300, 450];
+
+/// Positions searched by `bench`: openings, middlegames and endgames, some of them tactical
+pub const BENCH_POSITIONS: [&str; 10] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bq1rk1/pp2ppbp/2np1np1/8/3NP3/2N1BP2/PPPQ2PP/R3KB1R w KQ - 0 9",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "2r3k1/pp3ppp/2n1b3/3p4/3P4/2PB1N2/P4PPP/R5K1 w - - 0 20",
+    "r2q1rk1/ppp2ppp/2n1bn2/2bpp3/4P3/2PP1N2/PP1NBPPP/R1BQ1RK1 w - - 0 8",
+    "8/5pk1/6p1/7p/1P5P/6P1/5PK1/8 w - - 0 40",
+];
 
 /// Used to size the move list. We can explore extra plies due to quiescence search and check extensions.
 pub const MAX_PLY: usize = 64;
@@ -27,18 +89,25 @@ pub const MOVE_STACK: usize = 4000;
 pub const GAME_STACK: usize = 2000;
 
 /// Added to move score so that the move from the hash table is searched first.
-#[allow(dead_code)]
 pub const HASH_SCORE: i32 = 100_000_000;
 
 /// Added to move score so that captures are search right after the hash table.
 pub const CAPTURE_SCORE: i32 = 10_000_000;
 
+/// Added to quiet moves so killers (two per ply), then the countermove to the previous move, are
+/// searched after captures but before moves ordered only by the history table.
+pub const KILLER_SCORE: [i32; 2] = [9_000_000, 8_000_000];
+pub const COUNTERMOVE_SCORE: i32 = 7_000_000;
+
 /// Hash table configuration
 pub const NUM_HASH_SLOTS: usize = 5_000_000;
 // You could use a power-of-2 size for faster modulo (bitwise AND instead of %)
 // const NUM_HASH_SLOTS: usize = 1 << 22; // 4,194,304 (2^22)
 // let index = (self.current_key as usize) & (NUM_HASH_SLOTS - 1); // Faster than %
 
+/// Pawn hash table configuration. Pawn structures repeat far more often than positions.
+pub const NUM_PAWN_HASH_SLOTS: usize = 16_384;
+
 pub const NUM_SQUARES: usize = 64;
 pub const NUM_PIECE_TYPES: usize = 6;
 pub const NUM_SIDES: usize = 2;
@@ -129,7 +198,9 @@ pub const FLIPPED_BOARD_SQUARE: [u8; NUM_SQUARES] = [
      0,  1,  2,  3,  4,  5,  6,  7
 ];
 
-/// A1 - H8
+/// Piece-square tables, A1 - H8. Each piece has a midgame table and an `_ENDGAME` one;
+/// `Position::evaluate` blends the two by the game phase.
+/// These and the evaluation weights below are the defaults for `EvalParams`.
 #[rustfmt::skip]
 pub const PAWN_SCORE: [i32; NUM_SQUARES] = [
 	    0,   0,   0,   0,   0,   0,   0,   0,
@@ -142,6 +213,18 @@ pub const PAWN_SCORE: [i32; NUM_SQUARES] = [
 	    0,   0,   0,   0,   0,   0,   0,   0
 ];
 
+#[rustfmt::skip]
+pub const PAWN_ENDGAME_SCORE: [i32; NUM_SQUARES] = [
+	    0,   0,   0,   0,   0,   0,   0,   0,
+	   -5,   0,   0,   0,   0,   0,   0,  -5,
+	    0,   5,   5,   5,   5,   5,   5,   0,
+	    5,  10,  10,  10,  10,  10,  10,   5,
+	   15,  20,  20,  20,  20,  20,  20,  15,
+	   30,  35,  35,  35,  35,  35,  35,  30,
+	  120, 120, 120, 120, 120, 120, 120, 120,
+	    0,   0,   0,   0,   0,   0,   0,   0
+];
+
 #[rustfmt::skip]
 pub const KNIGHT_SCORE: [i32; NUM_SQUARES] = [
 	   -30, -20, -10, -8, -8, -10, -20,  -30,
@@ -154,6 +237,18 @@ pub const KNIGHT_SCORE: [i32; NUM_SQUARES] = [
 	  -150, -20, -10, -5, -5, -10, -20, -150
 ];
 
+#[rustfmt::skip]
+pub const KNIGHT_ENDGAME_SCORE: [i32; NUM_SQUARES] = [
+	  -20, -12,  -8,  -6,  -6,  -8, -12, -20,
+	  -12,  -4,   0,   2,   2,   0,  -4, -12,
+	   -8,   0,   4,   6,   6,   4,   0,  -8,
+	   -6,   2,   6,   8,   8,   6,   2,  -6,
+	   -6,   2,   6,   8,   8,   6,   2,  -6,
+	   -8,   0,   4,   6,   6,   4,   0,  -8,
+	  -12,  -4,   0,   2,   2,   0,  -4, -12,
+	  -20, -12,  -8,  -6,  -6,  -8, -12, -20
+];
+
 #[rustfmt::skip]
 pub const BISHOP_SCORE: [i32; NUM_SQUARES] = [
 	  -10, -10, -12, -10, -10, -12, -10, -10,
@@ -166,6 +261,18 @@ pub const BISHOP_SCORE: [i32; NUM_SQUARES] = [
 	  -10, -10, -10, -10, -10, -10, -10, -10
 ];
 
+#[rustfmt::skip]
+pub const BISHOP_ENDGAME_SCORE: [i32; NUM_SQUARES] = [
+	   -8,  -6,  -4,  -4,  -4,  -4,  -6,  -8,
+	   -6,   0,   2,   2,   2,   2,   0,  -6,
+	   -4,   2,   4,   4,   4,   4,   2,  -4,
+	   -4,   2,   4,   6,   6,   4,   2,  -4,
+	   -4,   2,   4,   6,   6,   4,   2,  -4,
+	   -4,   2,   4,   4,   4,   4,   2,  -4,
+	   -6,   0,   2,   2,   2,   2,   0,  -6,
+	   -8,  -6,  -4,  -4,  -4,  -4,  -6,  -8
+];
+
 #[rustfmt::skip]
 pub const ROOK_SCORE: [i32; NUM_SQUARES] = [
 	   4,  4,  4,  6,  6,  4,  4,  4,
@@ -178,6 +285,18 @@ pub const ROOK_SCORE: [i32; NUM_SQUARES] = [
 	  10, 10, 10, 10, 10, 10, 10, 10
 ];
 
+#[rustfmt::skip]
+pub const ROOK_ENDGAME_SCORE: [i32; NUM_SQUARES] = [
+	   0,  0,  2,  4,  4,  2,  0,  0,
+	   0,  0,  2,  4,  4,  2,  0,  0,
+	   0,  0,  2,  4,  4,  2,  0,  0,
+	   0,  0,  2,  4,  4,  2,  0,  0,
+	   0,  0,  2,  4,  4,  2,  0,  0,
+	   0,  0,  2,  4,  4,  2,  0,  0,
+	  10, 10, 10, 10, 10, 10, 10, 10,
+	   4,  4,  4,  4,  4,  4,  4,  4
+];
+
 #[rustfmt::skip]
 pub const QUEEN_SCORE: [i32; NUM_SQUARES] = [
 	  -10, -10, -6, -4, -4, -6, -10, -10,
@@ -190,6 +309,18 @@ pub const QUEEN_SCORE: [i32; NUM_SQUARES] = [
 	  -10, -10,  2,  2,  2,  2, -10, -10
 ];
 
+#[rustfmt::skip]
+pub const QUEEN_ENDGAME_SCORE: [i32; NUM_SQUARES] = [
+	  -10,  -6,  -4,  -2,  -2,  -4,  -6, -10,
+	   -6,  -2,   0,   2,   2,   0,  -2,  -6,
+	   -4,   0,   4,   6,   6,   4,   0,  -4,
+	   -2,   2,   6,   8,   8,   6,   2,  -2,
+	   -2,   2,   6,   8,   8,   6,   2,  -2,
+	   -4,   0,   4,   6,   6,   4,   0,  -4,
+	   -6,  -2,   0,   2,   2,   0,  -2,  -6,
+	  -10,  -6,  -4,  -2,  -2,  -4,  -6, -10
+];
+
 #[rustfmt::skip]
 pub const KING_SCORE: [i32; NUM_SQUARES] = [
 	   20,  20,  20, -40,  10, -60,  20,  20,     
@@ -226,6 +357,18 @@ pub const PASSED_SCORE: [i32; NUM_SQUARES] = [
      0,  0,  0,  0,  0,  0,  0,  0
 ];
 
+#[rustfmt::skip]
+pub const PASSED_ENDGAME_SCORE: [i32; NUM_SQUARES] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    15,  15,  15,  15,  15,  15,  15,  15,
+    15,  15,  15,  15,  15,  15,  15,  15,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    60,  60,  60,  60,  60,  60,  60,  60,
+   100, 100, 100, 100, 100, 100, 100, 100,
+     0,   0,   0,   0,   0,   0,   0,   0, // 7th rank; always passed; handled by `pawn_score`
+     0,   0,   0,   0,   0,   0,   0,   0
+];
+
 /// Used to determine the castling permissions after a move.
 /// We logical-AND the castle bits with the CASTLE_MASK bits for
 /// both of the move's squares.
@@ -313,4 +456,207 @@ pub const KING_CAPTURE_SCORE: [i32; 6] = [0, 10, 20, 30, 40, 0];
 
 pub const REVERSE_SQUARE: [i32; NUM_SIDES] = [-8, 8];
 
-pub const ISOLATED_PAWN_SCORE: i32 = -20;
+pub const ISOLATED_PAWN_SCORE: TaperedScore = TaperedScore::new(-20, -25);
+
+/// Each pawn with another of its own pawns in front of it on the same file
+pub const DOUBLED_PAWN_SCORE: TaperedScore = TaperedScore::new(-10, -20);
+
+/// A pawn whose neighbours have all advanced past it, with its stop square covered by an
+/// enemy pawn. It can't move up safely or be defended by another pawn.
+pub const BACKWARD_PAWN_SCORE: TaperedScore = TaperedScore::new(-10, -10);
+
+/// Per square a piece attacks that isn't occupied by its own units or covered by enemy pawns
+pub const MOBILITY_SCORE: [TaperedScore; NUM_PIECE_TYPES] = [
+    TaperedScore::new(0, 0),
+    TaperedScore::new(4, 4), // Knight
+    TaperedScore::new(4, 5), // Bishop
+    TaperedScore::new(2, 4), // Rook
+    TaperedScore::new(1, 2), // Queen
+    TaperedScore::new(0, 0),
+];
+
+/// Two or more bishops
+pub const BISHOP_PAIR_SCORE: TaperedScore = TaperedScore::new(30, 50);
+
+/// A knight on the 4th to 6th rank, defended by a pawn, that no enemy pawn can ever attack
+pub const KNIGHT_OUTPOST_SCORE: TaperedScore = TaperedScore::new(20, 10);
+
+/// A rook on the 7th rank, with enemy pawns on it or the enemy king stuck on the 8th
+pub const ROOK_ON_SEVENTH_SCORE: TaperedScore = TaperedScore::new(20, 30);
+
+/// Rooks that defend each other along a rank or file
+pub const CONNECTED_ROOKS_SCORE: TaperedScore = TaperedScore::new(10, 5);
+
+/// King safety: attack units per square a piece attacks in the enemy king zone (the king's
+/// square and its neighbours)
+pub const KING_ATTACK_WEIGHTS: [i32; NUM_PIECE_TYPES] = [0, 2, 2, 3, 5, 0];
+/// Pieces that must hit the king zone before their attack units count
+pub const KING_ZONE_MIN_ATTACKERS: i32 = 2;
+/// Units for each piece type that can give check on a square the defender doesn't cover
+pub const SAFE_CHECK_UNITS: [i32; NUM_PIECE_TYPES] = [0, 3, 2, 3, 4, 0];
+/// Units for each file on or beside the king with no pawns, or none of the defender's
+pub const KING_OPEN_FILE_UNITS: i32 = 4;
+pub const KING_SEMI_OPEN_FILE_UNITS: i32 = 2;
+/// Units for each enemy pawn on or beside the king's file, by its rank counted from the
+/// defender's back rank
+pub const PAWN_STORM_UNITS: [i32; 8] = [0, 0, 4, 3, 1, 0, 0, 0];
+
+/// Midgame penalty for the king by the attacker's total units. Grows faster than the units, so
+/// several weak threats together count for more than any one of them.
+#[rustfmt::skip]
+pub const KING_SAFETY_TABLE: [i32; 100] = [
+      0,   0,   1,   2,   3,   5,   7,   9,  12,  15,
+     18,  22,  26,  30,  35,  39,  44,  50,  56,  62,
+     68,  75,  82,  85,  89,  97, 105, 113, 122, 131,
+    140, 150, 169, 180, 191, 202, 213, 225, 237, 248,
+    260, 272, 283, 295, 307, 319, 330, 342, 354, 366,
+    377, 389, 401, 412, 424, 436, 448, 459, 471, 483,
+    494, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+];
+
+/// Rooks on a file with no pawns, or none of their own
+pub const ROOK_OPEN_FILE_SCORE: TaperedScore = TaperedScore::new(20, 10);
+pub const ROOK_HALF_OPEN_FILE_SCORE: TaperedScore = TaperedScore::new(10, 5);
+
+/// Game phase: each knight, bishop, rook and queen on the board adds its weight, up to
+/// `MAX_PHASE` for the opening (pawns and kings don't count). 0 is a pawn endgame.
+pub const PHASE_WEIGHTS: [i32; NUM_PIECE_TYPES] = [0, 1, 1, 2, 4, 0];
+pub const MAX_PHASE: i32 = 24;
+
+/// Magic bitboard multipliers for sliding piece attacks, indexed by square (A1 - H8).
+/// Each maps every blocker arrangement on the square's rays to a unique table slot.
+pub const ROOK_MAGICS: [u64; NUM_SQUARES] = [
+    0x1080004008801020,
+    0x0840092002C03000,
+    0x1900200010400900,
+    0x0880100008000480,
+    0x4200100420080200,
+    0x8100020100080400,
+    0x0200040110886200,
+    0x0200008040220411,
+    0x0404800084400220,
+    0x0000401000402000,
+    0x0086001081220440,
+    0x0408800800100280,
+    0x000A001201040820,
+    0x8848800200840080,
+    0x4001000100040200,
+    0x0442000102105084,
+    0x9080010020804100,
+    0x0040404000201009,
+    0x0000808010002009,
+    0x2200090021D00100,
+    0x0008008008040080,
+    0x0004004002010040,
+    0x0011040008015042,
+    0x00000A0001768104,
+    0x0000800080204009,
+    0x2010004140002001,
+    0x9800200280100080,
+    0x1000100080080080,
+    0x0442000A00049020,
+    0x2100040080020080,
+    0x0800120400900148,
+    0x0010040A00128541,
+    0x2800804000800030,
+    0x1010002000400041,
+    0x4000200011004100,
+    0x0610008410800800,
+    0x0400802402800800,
+    0xC100020080800400,
+    0x0002000802000401,
+    0x0182085882000401,
+    0x0220204000808000,
+    0x2860100040024022,
+    0x0001002004110040,
+    0x99101042000A0020,
+    0x0004080004008080,
+    0x0010040002008080,
+    0x2012004881020004,
+    0x8300842444820011,
+    0x0088403882010200,
+    0x0820400080210100,
+    0x0110910040A00300,
+    0x0801100280080480,
+    0x0242009008200600,
+    0x1002000489500200,
+    0x0040800200010080,
+    0x0091800041000080,
+    0x0000209300488001,
+    0x04C1002414824001,
+    0x020020000B001041,
+    0x7000100004200901,
+    0x8002002004100802,
+    0x30010002084C0007,
+    0x0888221800813004,
+    0x4000002840840112,
+];
+pub const BISHOP_MAGICS: [u64; NUM_SQUARES] = [
+    0xA010041108003100,
+    0x006082020A002900,
+    0x6810010619200000,
+    0x08281A0520000408,
+    0x0001104001000400,
+    0x0018901008048400,
+    0x00040A0210245280,
+    0x000200210808A402,
+    0x9140048410821200,
+    0x0800091010820041,
+    0x20504804832202C0,
+    0x0100091401081000,
+    0x8021011140000012,
+    0x0810020804450400,
+    0x208B0542109008A2,
+    0x0080084A08040204,
+    0x0040E2A80811244C,
+    0x2505022008008108,
+    0x0430220100420040,
+    0x010A040420220040,
+    0x1105000290400000,
+    0x0093001200822120,
+    0x4000A62048043004,
+    0x280120048A015004,
+    0x006090002A020814,
+    0x44042000240800D0,
+    0x01102800040A4400,
+    0x1004080080220040,
+    0x0001001011004024,
+    0x0010044000805040,
+    0x0914041200820100,
+    0x0004821012821480,
+    0x0024040500C05021,
+    0x0088611002080200,
+    0x0116080A00040020,
+    0x4000020080080080,
+    0x2450450140840040,
+    0x0000880201484100,
+    0x0222020404020092,
+    0x8081110600002E00,
+    0x2842101105000801,
+    0x1100809008001025,
+    0x00020202221C0400,
+    0x0422014022009020,
+    0x0210046102100C00,
+    0xC004008082029102,
+    0x00AA461801101200,
+    0x0404080080201108,
+    0x020542108C205002,
+    0x0410544804100100,
+    0x0040910841100000,
+    0x0400200042021100,
+    0x00004204850400C0,
+    0x0200100410A42102,
+    0x1040020801210102,
+    0x0805040410420000,
+    0x2884804130100200,
+    0x800C262201242000,
+    0x1058000194108800,
+    0x0014221054420204,
+    0x0104000012A02200,
+    0x0200881003300100,
+    0x0140400202840100,
+    0x0402020801010201,
+];
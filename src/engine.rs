@@ -1,14 +1,24 @@
-use std::{panic, sync::Arc};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+};
 
 use crate::{
     constants::{
-        DEFAULT_MAX_DEPTH, DEFAULT_PLAYER_INCREMENT_MS, DEFAULT_PLAYER_TIME_REMAINING_MS,
-        INFINITY_SCORE, MATE_THRESHOLD, MAX_PLY, NUM_SIDES, NUM_SQUARES,
+        ASPIRATION_MIN_DEPTH, ASPIRATION_WINDOW, BENCH_POSITIONS, DEFAULT_MAX_DEPTH,
+        DEFAULT_MOVE_OVERHEAD_MS, DEFAULT_PLAYER_INCREMENT_MS, DEFAULT_PLAYER_TIME_REMAINING_MS,
+        INFINITY_SCORE, NUM_SIDES, NUM_SQUARES, SEARCH_THREAD_STACK_SIZE,
     },
+    nnue::Network,
+    params::EvalParams,
     polyglot::PolyglotBook,
     position::Position,
+    syzygy::Tablebases,
     time::TimeManager,
-    types::{Difficulty, MoveData, Piece, Side, Square},
+    types::{Board, Difficulty, MoveData, Piece, Score, ScoreBound, SearchAbort, Side, Square},
 };
 
 pub struct Engine {
@@ -18,6 +28,9 @@ pub struct Engine {
     pub computer_side: Option<Side>,
     history_table: [[[isize; NUM_SQUARES]; NUM_SQUARES]; NUM_SIDES], // [color][from][to] = score
     pub book: Option<PolyglotBook>,
+    pub own_book: bool, // Whether to play moves from `book` when one is loaded
+    stop_signal: Arc<AtomicBool>, // Shared with the search's `TimeManager`
+    ponder_signal: Arc<AtomicBool>, // Shared with the search's `TimeManager`
 }
 
 pub struct SearchSettings {
@@ -26,23 +39,54 @@ pub struct SearchSettings {
     pub winc: u64,
     pub binc: u64,
     pub movetime: Option<u64>,
+    pub movestogo: Option<u64>, // Moves until the next time control
     pub max_depth: u16,
-    pub max_nodes: Option<usize>,
+    pub max_nodes: Option<usize>, // Searched on one thread, whatever `threads` is
+    pub mate: Option<u16>,        // Search for a mate in this many moves
+    pub infinite: bool,           // No time limit; search until stopped or `max_depth`
+    pub search_moves: Vec<MoveData>, // Only search these root moves, if any
+    pub threads: usize,           // Search threads; helpers beyond the first use Lazy SMP
+    pub multi_pv: usize,          // Number of principal variations to report
+    pub move_overhead: u64,       // Subtracted from the time available for each move (ms)
 }
 
 pub struct SearchResult {
     pub best_move_from: Option<Square>,
     pub best_move_to: Option<Square>,
     pub best_move_promote: Option<Piece>,
-    pub evaluation: i32,
+    pub evaluation: Score,
     pub depth: u16,
     pub nodes: usize,
     pub qnodes: usize,
     pub time_ms: u64,
     pub principal_variation: Vec<MoveData>, // Principal variation: list of (from, to, promote)
+    pub ponder_move: Option<MoveData>,      // Expected reply to the best move
+    pub lines: Vec<SearchLine>,             // Best first; more than one with `multi_pv` > 1
     pub from_book: bool,
 }
 
+/// One principal variation found by the search, e.g. for MultiPV analysis
+#[derive(Clone, Debug)]
+pub struct SearchLine {
+    pub score: Score,
+    pub depth: u16,
+    pub principal_variation: Vec<MoveData>,
+    pub bound: ScoreBound, // Not exact while the search is widening a failed aspiration window
+}
+
+/// Totals from `Engine::bench`
+pub struct BenchResult {
+    pub nodes: usize,
+    pub time_ms: u64,
+}
+
+impl SearchLine {
+    /// The first move of the line
+    pub fn best_move(&self) -> Option<MoveData> {
+        self.principal_variation.first().copied()
+    }
+}
+
 impl Default for Engine {
     fn default() -> Self {
         Engine::new(None, None, None, None, None, None, None, None, None)
@@ -72,20 +116,32 @@ impl Engine {
             .unwrap_or(DEFAULT_MAX_DEPTH);
 
         let mut engine = Engine {
-            position: Position::new(TimeManager::new(wtime, btime, winc, binc, movetime, true)),
+            position: Position::new(TimeManager::new(
+                wtime, btime, winc, binc, None, movetime, true,
+            )),
             search_settings: SearchSettings {
                 wtime,
                 btime,
                 winc,
                 binc,
                 movetime,
+                movestogo: None,
                 max_depth,
                 max_nodes,
+                mate: None,
+                infinite: false,
+                search_moves: Vec::new(),
+                threads: 1,
+                multi_pv: 1,
+                move_overhead: DEFAULT_MOVE_OVERHEAD_MS,
             },
             computer_side: None,
             history_table: [[[0; NUM_SQUARES]; NUM_SQUARES]; NUM_SIDES],
             book: None,
+            own_book: true,
             difficulty,
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            ponder_signal: Arc::new(AtomicBool::new(false)),
         };
 
         if let Some(book_path) = book_path
@@ -98,12 +154,53 @@ impl Engine {
     }
 
     pub fn from_fen(fen: &str) -> Self {
-        let mut engine = Engine::default();
-        engine.position = Position::from_fen(fen).expect("Failed to load FEN");
+        let mut engine = Engine {
+            position: Position::from_fen(fen).expect("Failed to load FEN"),
+            ..Default::default()
+        };
+
         engine.position.set_material_scores();
         engine
     }
 
+    /// Search each of `BENCH_POSITIONS` to `depth` on one thread, with an empty transposition table.
+    /// The node count changes only when the search does, so it fingerprints a version of it.
+    pub fn bench(depth: u16) -> BenchResult {
+        let mut result = BenchResult {
+            nodes: 0,
+            time_ms: 0,
+        };
+
+        for fen in BENCH_POSITIONS {
+            let mut engine = Engine::from_fen(fen);
+            engine.search_settings.max_depth = depth;
+            engine.search_settings.infinite = true;
+
+            let search = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+            result.nodes += search.nodes;
+            result.time_ms += search.time_ms;
+        }
+
+        result
+    }
+
+    /// Returns a handle that can be used from another thread to stop a running search.
+    /// `think()` returns with the best move from the last completed iteration.
+    pub fn stop_signal(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_signal)
+    }
+
+    /// Stop the running search, if any
+    pub fn stop(&self) {
+        self.stop_signal.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns a handle to the pondering signal. Raise it before `think()` to search without
+    /// time limits (on the opponent's time). Lowering it ("ponderhit") starts the clock.
+    pub fn ponder_signal(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.ponder_signal)
+    }
+
     pub fn load_opening_book(&mut self, book_path: &str) -> Result<(), String> {
         match PolyglotBook::load(book_path) {
             Ok(book) => {
@@ -114,184 +211,569 @@ impl Engine {
         }
     }
 
+    /// Evaluate with the network in `eval_path` instead of the handcrafted evaluation
+    pub fn load_eval_file(&mut self, eval_path: &str) -> Result<(), String> {
+        match Network::load(eval_path) {
+            Ok(network) => {
+                self.position.board.set_network(Some(Arc::new(network)));
+                Ok(())
+            }
+            Err(e) => Err(format!("Failed to load network: {}", e)),
+        }
+    }
+
+    /// Use the handcrafted evaluation weights in `params_path`, e.g. from the tuner
+    pub fn load_eval_params(&mut self, params_path: &str) -> Result<(), String> {
+        match EvalParams::load(params_path) {
+            Ok(params) => {
+                self.position.set_eval_params(Arc::new(params));
+                Ok(())
+            }
+            Err(e) => Err(format!("Failed to load evaluation parameters: {}", e)),
+        }
+    }
+
+    /// Go back to the built-in handcrafted evaluation weights
+    pub fn use_default_eval_params(&mut self) {
+        self.position
+            .set_eval_params(Arc::new(EvalParams::default()));
+    }
+
+    /// Probe the Syzygy tablebases in `path` (directories separated by `:`, or `;` on Windows)
+    /// during the search. An empty path stops probing. Returns the number of table files found.
+    pub fn set_syzygy_path(&mut self, path: &str) -> Result<usize, String> {
+        if path.is_empty() {
+            self.position.set_tablebases(None);
+            return Ok(0);
+        }
+
+        match Tablebases::open(path) {
+            Ok(tablebases) => {
+                let num_tables = tablebases.num_tables();
+                self.position.set_tablebases(Some(Arc::new(tablebases)));
+                Ok(num_tables)
+            }
+            Err(e) => Err(format!("Failed to open tablebases: {}", e)),
+        }
+    }
+
+    /// Tablebase files that failed to load or read since the last call. The search stops
+    /// probing them, so each failure is reported once.
+    pub fn take_tablebase_errors(&self) -> Vec<String> {
+        self.position
+            .tablebases()
+            .map_or_else(Vec::new, |tablebases| tablebases.take_errors())
+    }
+
+    /// Go back to the handcrafted evaluation
+    pub fn use_handcrafted_eval(&mut self) {
+        self.position.board.set_network(None);
+    }
+
+    pub fn uses_network_eval(&self) -> bool {
+        self.position.board.nnue.is_some()
+    }
+
+    /// The handcrafted evaluation broken down by term, and the network's evaluation if one is
+    /// loaded. Printed by the `eval` commands.
+    pub fn evaluation_report(&self) -> String {
+        let mut report = self.position.evaluate_with_trace().to_string();
+
+        if self.uses_network_eval() {
+            let evaluation = match self.position.side {
+                Side::White => self.position.evaluate(),
+                Side::Black => -self.position.evaluate(),
+            };
+
+            report += &format!(
+                "\nNetwork evaluation: {:+.2} (White's view)",
+                evaluation as f64 / 100.0
+            );
+        }
+
+        report
+    }
+
     pub fn new_game(&mut self) {
-        self.position = Position::new(TimeManager::new(
+        self.set_position(Position::new(TimeManager::new(
             self.search_settings.wtime,
             self.search_settings.btime,
             self.search_settings.winc,
             self.search_settings.binc,
+            self.search_settings.movestogo,
             self.search_settings.movetime,
             true,
-        ));
+        )));
 
+        self.clear_hash();
         self.computer_side = None;
     }
 
+    /// Replace the current position, keeping the transposition table and evaluation settings
+    pub fn set_position(&mut self, mut position: Position) {
+        position.set_material_scores();
+        position.board.set_network(self.position.board.network());
+        position.set_eval_params(Arc::clone(self.position.eval_params()));
+        position.set_tablebases(self.position.tablebases());
+        position
+            .board
+            .hash
+            .swap_table(&mut self.position.board.hash);
+        self.position = position;
+    }
+
+    /// Resize the transposition table to (at most) `size_mb` megabytes. Clears the table.
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.position.board.hash.resize_table(size_mb);
+    }
+
+    pub fn clear_hash(&mut self) {
+        self.position.board.hash.clear_table();
+        self.position.board.hash.clear_pawn_table();
+    }
+
+    /// Set the difficulty, limiting the search depth accordingly. `None` is full strength.
+    pub fn set_difficulty(&mut self, difficulty: Option<Difficulty>) {
+        self.difficulty = difficulty;
+        self.search_settings.max_depth = self.default_max_depth();
+    }
+
+    /// The search depth used when a search doesn't specify one
+    pub fn default_max_depth(&self) -> u16 {
+        self.difficulty
+            .map(|d| d.max_depth() as u16)
+            .unwrap_or(DEFAULT_MAX_DEPTH)
+    }
+
     /// Core iterative deepening search logic. Returns SearchResult with best move and evaluation.
+    /// `on_depth_complete` is called after each depth with the lines found, best first. It is also
+    /// called when a line fails its aspiration window, with that line's score as a bound.
     pub fn think<F>(&mut self, mut on_depth_complete: Option<F>) -> SearchResult
     where
-        F: FnMut(u16, i32, &mut Position),
+        F: FnMut(u16, &[SearchLine], &mut Position),
     {
-        // Don't print anything when known panics occur
-        let default_hook: Arc<dyn Fn(&panic::PanicHookInfo<'_>) + Send + Sync + 'static> =
-            Arc::from(panic::take_hook());
-
-        panic::set_hook({
-            let hook = Arc::clone(&default_hook);
-            Box::new(move |panic_info: &panic::PanicHookInfo<'_>| {
-                if let Some(msg) = panic_info.payload().downcast_ref::<&str>()
-                    && matches!(*msg, "TimeExhausted" | "NodeLimitReached")
-                {
-                    return;
-                }
-
-                hook(panic_info);
-            }) as Box<dyn Fn(&panic::PanicHookInfo<'_>) + Send + Sync + 'static>
-        });
-
-        if let Some(book) = &self.book
+        if self.own_book
+            && let Some(book) = &self.book
             && let Some(book_entry) = book.get_move_from_book(self.position.board.hash.current_key)
         {
             let book_move = book_entry.decode_move();
 
+            let lines = vec![SearchLine {
+                score: Score::Centipawns(0),
+                depth: 0,
+                principal_variation: vec![book_move],
+                bound: ScoreBound::Exact,
+            }];
+
             if let Some(ref mut callback) = on_depth_complete {
                 self.position
                     .make_move(book_move.from, book_move.to, book_move.promote);
-                let score = -self.position.evaluate();
+                let score = Score::from_search_score(-self.position.evaluate());
                 self.position.take_back_move();
-                callback(0, score, &mut self.position);
+
+                let scored_lines = [SearchLine {
+                    score,
+                    ..lines[0].clone()
+                }];
+
+                callback(0, &scored_lines, &mut self.position);
             }
 
             return SearchResult {
                 best_move_from: Some(book_move.from),
                 best_move_to: Some(book_move.to),
                 best_move_promote: book_move.promote,
-                evaluation: 0,
+                evaluation: Score::Centipawns(0),
                 depth: 0,
                 nodes: 0,
                 qnodes: 0,
                 time_ms: 0,
                 principal_variation: vec![book_move],
+                ponder_move: None,
+                lines,
                 from_book: true,
             };
         }
 
-        self.position.time_manager = TimeManager::new(
-            self.search_settings.wtime,
-            self.search_settings.btime,
-            self.search_settings.winc,
-            self.search_settings.binc,
-            self.search_settings.movetime,
-            self.position.side == Side::White,
-        );
+        let move_overhead = self.search_settings.move_overhead;
+
+        self.position.time_manager = match self.search_settings.infinite {
+            true => TimeManager::unlimited(),
+            false => TimeManager::new(
+                self.search_settings.wtime.saturating_sub(move_overhead),
+                self.search_settings.btime.saturating_sub(move_overhead),
+                self.search_settings.winc,
+                self.search_settings.binc,
+                self.search_settings.movestogo,
+                self.search_settings
+                    .movetime
+                    .map(|movetime| movetime.saturating_sub(move_overhead)),
+                self.position.side == Side::White,
+            ),
+        };
+
+        self.position.time_manager.stop_signal = Arc::clone(&self.stop_signal);
+        self.position
+            .time_manager
+            .set_ponder_signal(Arc::clone(&self.ponder_signal));
 
         // Reset all search statistics
         self.position.nodes = 0;
         self.position.qnodes = 0;
+        self.position.helper_nodes = 0;
         self.position.max_depth_reached = 0;
         self.position.hash_hits = 0;
         self.position.hash_stores = 0;
         self.position.beta_cutoffs = 0;
+        self.position.pawn_hash_probes.set(0);
+        self.position.pawn_hash_hits.set(0);
+        self.position.tb_hits = 0;
 
-        // Reset history table at the start of the search
+        // Reset move ordering heuristics at the start of the search
         self.history_table = [[[0; NUM_SQUARES]; NUM_SQUARES]; NUM_SIDES];
+        self.position.clear_killers_and_countermoves();
 
-        let mut final_depth = 0;
-        let mut final_score = 0;
+        let root_moves = self.root_moves();
 
-        // Save the principal variation from the last completed iteration
-        let mut saved_pv_length = 0;
-        let mut saved_pv = [None; MAX_PLY];
+        // Lines from the last completed iteration
+        let mut lines: Vec<SearchLine> = Vec::new();
 
-        // Iterative deepening: search depth 1, 2, 3, ... maximum
-        for depth in 1..=self.search_settings.max_depth {
-            // Soft time limit (avoid starting a depth that won't finish)
-            if self.search_settings.max_depth > 1
-                && depth > 1
-                && self.position.time_manager.is_soft_limit_reached()
-            {
-                break;
-            }
+        // A mate in N moves is 2N - 1 plies, plus one to find the mated side has no moves
+        let max_depth = match self.search_settings.mate {
+            Some(moves) => self.search_settings.max_depth.min(2 * moves.max(1)),
+            None => self.search_settings.max_depth,
+        };
 
-            self.position.ply = 0;
-            self.position.first_move[0] = 0;
+        let helper_nodes = AtomicUsize::new(0);
+        let mut helper_qnodes = 0;
 
-            // Perform the search at this depth
-            let score = match panic::catch_unwind(panic::AssertUnwindSafe(|| {
-                self.position.search(
-                    -INFINITY_SCORE,
-                    INFINITY_SCORE,
-                    depth,
-                    &mut self.history_table,
-                    self.search_settings.max_nodes,
-                )
-            })) {
-                Ok(score) => score,
-                Err(panic_payload) => {
-                    if let Some(panic_message) = panic_payload.downcast_ref::<&str>()
-                        && matches!(*panic_message, "TimeExhausted" | "NodeLimitReached")
-                    {
-                        while self.position.ply > 0 {
-                            self.position.take_back_move();
-                        }
-
-                        // Restore PV from the last completed iteration
-                        self.position.pv_length[0] = saved_pv_length;
-                        self.position.pv_table[0][..saved_pv_length]
-                            .copy_from_slice(&saved_pv[..saved_pv_length]);
-
-                        break;
-                    }
-
-                    panic::resume_unwind(panic_payload);
-                }
+        thread::scope(|scope| {
+            // Lazy SMP: helpers search the same root on their own copy of the position,
+            // sharing the transposition table. Only this thread's lines are reported.
+            // A node limit is only checked on this thread, so it then searches alone.
+            let threads = match self.search_settings.max_nodes {
+                Some(_) => 1,
+                None => self.search_settings.threads.max(1),
             };
 
-            while self.position.ply > 0 {
-                self.position.take_back_move();
-            }
+            let helpers: Vec<_> = (1..threads)
+                .map(|helper_index| {
+                    let mut position = Box::new(self.position.clone());
+                    position.time_manager = TimeManager::unlimited();
+                    position.time_manager.stop_signal = Arc::clone(&self.stop_signal);
+                    position.root_search_moves.clone_from(&root_moves);
+
+                    let helper_nodes = &helper_nodes;
+                    thread::Builder::new()
+                        .stack_size(SEARCH_THREAD_STACK_SIZE)
+                        .spawn_scoped(scope, move || {
+                            Self::helper_search(position, helper_index, max_depth, helper_nodes)
+                        })
+                        .expect("Failed to spawn helper search thread")
+                })
+                .collect();
+
+            // Iterative deepening: search depth 1, 2, 3, ... maximum
+            for depth in 1..=max_depth {
+                // Soft time limit (avoid starting a depth that won't finish)
+                if max_depth > 1 && depth > 1 && self.position.time_manager.is_soft_limit_reached()
+                {
+                    break;
+                }
 
-            final_depth = depth;
-            final_score = score;
+                let Some(depth_lines) =
+                    self.search_lines(depth, &root_moves, &lines, &mut on_depth_complete)
+                else {
+                    break; // Aborted: keep the lines from the last completed iteration
+                };
 
-            // Save the PV from this completed iteration
-            saved_pv_length = self.position.pv_length[0];
-            saved_pv[..saved_pv_length]
-                .copy_from_slice(&self.position.pv_table[0][..saved_pv_length]);
+                lines = depth_lines;
+                self.position.helper_nodes = helper_nodes.load(Ordering::Relaxed);
 
-            if let Some(ref mut callback) = on_depth_complete {
-                callback(depth, score, &mut self.position);
-            }
+                if let Some(ref mut callback) = on_depth_complete {
+                    callback(depth, &lines, &mut self.position);
+                }
 
-            if score > MATE_THRESHOLD || score < -MATE_THRESHOLD {
-                break;
+                if lines.first().is_none_or(|line| line.score.is_mate()) {
+                    break;
+                }
             }
-        }
 
-        // Collect principal variation from position
-        let mut principal_variation = Vec::new();
+            // Helpers search until this thread is done
+            if !helpers.is_empty() {
+                self.stop_signal.store(true, Ordering::Relaxed);
+            }
 
-        for i in 0..self.position.pv_length[0] {
-            if let Some(move_) = self.position.pv_table[0][i] {
-                principal_variation.push(MoveData {
-                    from: move_.from,
-                    to: move_.to,
-                    promote: move_.promote,
-                });
+            for helper in helpers {
+                helper_qnodes += helper.join().expect("Helper search thread panicked");
             }
-        }
+        });
+
+        self.position.helper_nodes = helper_nodes.load(Ordering::Relaxed);
+
+        // Leave the signal clear for the next search
+        self.stop_signal.store(false, Ordering::Relaxed);
+
+        let principal_variation = match lines.first() {
+            Some(line) => line.principal_variation.clone(),
+            None => self.fallback_move(&root_moves).into_iter().collect(),
+        };
+
+        let ponder_move = match principal_variation.get(1) {
+            Some(&ponder_move) => Some(ponder_move),
+            None => principal_variation
+                .first()
+                .and_then(|&best_move| self.ponder_move_from_hash(best_move)),
+        };
 
         SearchResult {
             best_move_from: principal_variation.first().map(|m| m.from),
             best_move_to: principal_variation.first().map(|m| m.to),
             best_move_promote: principal_variation.first().and_then(|m| m.promote),
-            evaluation: final_score,
-            depth: final_depth,
-            nodes: self.position.nodes,
-            qnodes: self.position.qnodes,
+            evaluation: lines
+                .first()
+                .map_or(Score::Centipawns(0), |line| line.score),
+            depth: lines.first().map_or(0, |line| line.depth),
+            nodes: self.position.total_nodes(),
+            qnodes: self.position.qnodes + helper_qnodes,
             time_ms: self.position.time_manager.elapsed().as_millis() as u64,
             principal_variation,
+            ponder_move,
+            lines,
             from_book: false,
         }
     }
+
+    /// The root moves to search (all of them if empty): `search_moves`, narrowed to those that
+    /// keep the best result when the tablebases cover the position
+    fn root_moves(&mut self) -> Vec<MoveData> {
+        let search_moves = self.search_settings.search_moves.clone();
+
+        let Some(ranked_moves) = self.position.tablebase_root_moves() else {
+            return search_moves;
+        };
+
+        let ranked_moves: Vec<_> = ranked_moves
+            .into_iter()
+            .filter(|(move_, _)| search_moves.is_empty() || search_moves.contains(move_))
+            .collect();
+
+        let Some(best_rank) = ranked_moves.iter().map(|&(_, rank)| rank).max() else {
+            return search_moves;
+        };
+
+        ranked_moves
+            .into_iter()
+            .filter(|&(_, rank)| rank == best_rank)
+            .map(|(move_, _)| move_)
+            .collect()
+    }
+
+    /// A legal move to play when the search stopped before depth 1 was finished: the first
+    /// of `root_moves`, or of all legal moves if it's empty. `None` if there are no legal moves.
+    fn fallback_move(&mut self, root_moves: &[MoveData]) -> Option<MoveData> {
+        root_moves.first().copied().or_else(|| {
+            self.position
+                .get_legal_moves()
+                .first()
+                .and_then(|uci| Board::move_from_uci_string(uci).ok())
+        })
+    }
+
+    /// Search the root to `depth` once per principal variation, only trying `root_moves` (all
+    /// moves if empty) and excluding the first moves of the lines already found. Each line starts
+    /// with an aspiration window around its score in `previous_lines`; when the score falls
+    /// outside, `on_window_fail` gets the line found so far with a bound and the window is widened.
+    /// Returns `None` if the search was aborted.
+    fn search_lines<F>(
+        &mut self,
+        depth: u16,
+        root_moves: &[MoveData],
+        previous_lines: &[SearchLine],
+        on_window_fail: &mut Option<F>,
+    ) -> Option<Vec<SearchLine>>
+    where
+        F: FnMut(u16, &[SearchLine], &mut Position),
+    {
+        let mut lines = Vec::new();
+        let mut aborted = false;
+
+        self.position.root_search_moves = root_moves.to_vec();
+
+        'lines: while lines.len() < self.search_settings.multi_pv.max(1) {
+            let mut delta = ASPIRATION_WINDOW;
+
+            let (mut alpha, mut beta) = match previous_lines.get(lines.len()) {
+                Some(previous) if depth >= ASPIRATION_MIN_DEPTH && !previous.score.is_mate() => {
+                    let score = previous.score.to_search_score();
+                    (score - delta, score + delta)
+                }
+                _ => (-INFINITY_SCORE, INFINITY_SCORE),
+            };
+
+            let line = loop {
+                let Ok((score, principal_variation)) = self.search_root(alpha, beta, depth) else {
+                    aborted = true;
+                    break 'lines;
+                };
+
+                let bound = if score <= alpha && alpha > -INFINITY_SCORE {
+                    ScoreBound::Upper
+                } else if score >= beta && beta < INFINITY_SCORE {
+                    ScoreBound::Lower
+                } else {
+                    ScoreBound::Exact
+                };
+
+                let line = SearchLine {
+                    score: Score::from_search_score(score),
+                    depth,
+                    principal_variation,
+                    bound,
+                };
+
+                if bound == ScoreBound::Exact {
+                    break line;
+                }
+
+                if let Some(callback) = on_window_fail
+                    && !line.principal_variation.is_empty()
+                {
+                    let mut reported_lines = lines.clone();
+                    reported_lines.push(line);
+                    callback(depth, &reported_lines, &mut self.position);
+                }
+
+                delta *= 2;
+
+                match bound {
+                    ScoreBound::Upper => alpha = (score - delta).max(-INFINITY_SCORE),
+                    _ => beta = (score + delta).min(INFINITY_SCORE),
+                }
+            };
+
+            // No moves left to search
+            let Some(best_move) = line.best_move() else {
+                break;
+            };
+
+            self.position.excluded_root_moves.push(best_move);
+            lines.push(line);
+        }
+
+        self.position.excluded_root_moves.clear();
+        self.position.root_search_moves.clear();
+
+        if aborted {
+            return None;
+        }
+
+        // Later lines can occasionally score higher than earlier ones
+        lines.sort_by_key(|line| std::cmp::Reverse(line.score));
+
+        Some(lines)
+    }
+
+    /// One search of the root within `alpha` and `beta`, returning the score and principal variation
+    fn search_root(
+        &mut self,
+        alpha: i32,
+        beta: i32,
+        depth: u16,
+    ) -> Result<(i32, Vec<MoveData>), SearchAbort> {
+        self.position.ply = 0;
+        self.position.first_move[0] = 0;
+
+        let search_result = self.position.search(
+            alpha,
+            beta,
+            depth,
+            &mut self.history_table,
+            self.search_settings.max_nodes,
+        );
+
+        // An aborted search leaves its line on the board
+        while self.position.ply > 0 {
+            self.position.take_back_move();
+        }
+
+        let score = search_result?;
+
+        let principal_variation = self.position.pv_table[0][..self.position.pv_length[0]]
+            .iter()
+            .flatten()
+            .map(|move_| MoveData {
+                from: move_.from,
+                to: move_.to,
+                promote: move_.promote,
+            })
+            .collect();
+
+        Ok((score, principal_variation))
+    }
+
+    /// Iterative deepening on a helper thread until the stop signal is raised or `max_depth`
+    /// is searched. Odd helpers start a ply deeper so the threads spread over neighbouring
+    /// depths. Adds its nodes to `helper_nodes` after each iteration and returns its qnodes.
+    fn helper_search(
+        mut position: Box<Position>,
+        helper_index: usize,
+        max_depth: u16,
+        helper_nodes: &AtomicUsize,
+    ) -> usize {
+        let mut history_table = Box::new([[[0; NUM_SQUARES]; NUM_SQUARES]; NUM_SIDES]);
+        let mut reported_nodes = 0;
+
+        for depth in (1 + (helper_index % 2) as u16)..=max_depth {
+            position.ply = 0;
+            position.first_move[0] = 0;
+
+            let search_result = position.search(
+                -INFINITY_SCORE,
+                INFINITY_SCORE,
+                depth,
+                &mut history_table,
+                None,
+            );
+
+            helper_nodes.fetch_add(position.nodes - reported_nodes, Ordering::Relaxed);
+            reported_nodes = position.nodes;
+
+            if search_result.is_err() {
+                break;
+            }
+        }
+
+        position.qnodes
+    }
+
+    /// The hash move after `best_move`, if legal. Used when the PV was cut short.
+    fn ponder_move_from_hash(&mut self, best_move: MoveData) -> Option<MoveData> {
+        if !self
+            .position
+            .make_move(best_move.from, best_move.to, best_move.promote)
+        {
+            return None;
+        }
+
+        let ponder_move = self
+            .position
+            .board
+            .hash
+            .probe()
+            .and_then(|entry| entry.best_move)
+            .filter(|move_| {
+                let uci = Board::move_to_uci_string(move_.from, move_.to, move_.promote, false);
+                self.position.get_legal_moves().contains(&uci)
+            })
+            .map(|move_| MoveData {
+                from: move_.from,
+                to: move_.to,
+                promote: move_.promote,
+            });
+
+        self.position.take_back_move();
+
+        ponder_move
+    }
 }
@@ -1,9 +1,26 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
 use crate::{
-    constants::NUM_HASH_SLOTS,
+    constants::{MATE_THRESHOLD, NUM_HASH_SLOTS, NUM_PAWN_HASH_SLOTS, NUM_SIDES},
     polyglot::POLYGLOT,
-    types::{Move, Piece, Side, Square},
+    types::{Move, Piece, Side, Square, TaperedScore},
 };
 
+/// How the stored score relates to the true score of the position
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashFlag {
+    /// The score is exact (a PV node: alpha < score < beta)
+    #[default]
+    Exact,
+    /// The score is a lower bound (the search failed high: score >= beta)
+    LowerBound,
+    /// The score is an upper bound (the search failed low: score <= alpha)
+    UpperBound,
+}
+
 /// An entry in the transposition table storing the best move for a position
 #[derive(Clone, Copy, Default)]
 pub struct HashEntry {
@@ -15,73 +32,384 @@ pub struct HashEntry {
     pub depth: u8,
     /// Score/evaluation for this position
     pub score: i32,
+    /// Whether `score` is exact or a bound
+    pub flag: HashFlag,
+}
+
+impl HashEntry {
+    /// Returns the stored score if it is usable within the (alpha, beta) window,
+    /// given that the entry was searched to at least the required depth.
+    pub fn cutoff_score(&self, depth: u8, alpha: i32, beta: i32) -> Option<i32> {
+        if self.depth < depth {
+            return None;
+        }
+
+        match self.flag {
+            HashFlag::Exact => Some(self.score),
+            HashFlag::LowerBound if self.score >= beta => Some(self.score),
+            HashFlag::UpperBound if self.score <= alpha => Some(self.score),
+            _ => None,
+        }
+    }
+}
+
+/// Mate scores are stored relative to the position being stored rather than the root,
+/// so that the same mate found via different path lengths is stored consistently.
+pub fn score_to_hash(score: i32, ply: usize) -> i32 {
+    if score > MATE_THRESHOLD {
+        score + ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
 }
 
-/// Transposition table for storing positions and their best moves
+/// Converts a stored mate score back to be relative to the root of the current search
+pub fn score_from_hash(score: i32, ply: usize) -> i32 {
+    if score > MATE_THRESHOLD {
+        score - ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Size of the transposition table created by `HashTable::new()`
+pub const DEFAULT_HASH_SIZE_MB: usize = NUM_HASH_SLOTS * size_of::<HashSlot>() / (1024 * 1024);
+
+/// A table slot, packed so it can be shared between search threads without locking.
+/// `key` holds the hash key XOR'ed with `data`, so a slot torn by a concurrent write
+/// fails the key check on probe instead of returning another position's entry.
+#[derive(Default)]
+struct HashSlot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl HashSlot {
+    fn load(&self) -> (u64, u64) {
+        let data = self.data.load(Ordering::Relaxed);
+        (self.key.load(Ordering::Relaxed) ^ data, data)
+    }
+
+    fn store(&self, key: u64, data: u64) {
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+/// Packs an entry (minus its key) into 64 bits: score (32) | depth (8) | flag (2) | move (16)
+fn pack_entry(entry: &HashEntry) -> u64 {
+    let flag = match entry.flag {
+        HashFlag::Exact => 0,
+        HashFlag::LowerBound => 1,
+        HashFlag::UpperBound => 2,
+    };
+
+    (entry.score as u32 as u64) << 32
+        | (entry.depth as u64) << 24
+        | flag << 16
+        | entry.best_move.map_or(0, pack_move)
+}
+
+fn unpack_entry(hash_key: u64, data: u64) -> HashEntry {
+    let flag = match (data >> 16) & 0b11 {
+        1 => HashFlag::LowerBound,
+        2 => HashFlag::UpperBound,
+        _ => HashFlag::Exact,
+    };
+
+    HashEntry {
+        hash_key,
+        best_move: unpack_move(data as u16),
+        depth: (data >> 24) as u8,
+        score: (data >> 32) as u32 as i32,
+        flag,
+    }
+}
+
+/// Bit 15 marks a stored move; then promotion piece + 1 (3 bits), to (6) and from (6)
+fn pack_move(move_: Move) -> u64 {
+    let promote = move_.promote.map_or(0, |piece| piece as u64 + 1);
+    1 << 15 | promote << 12 | (move_.to as u64) << 6 | move_.from as u64
+}
+
+fn unpack_move(bits: u16) -> Option<Move> {
+    if bits & 1 << 15 == 0 {
+        return None;
+    }
+
+    Some(Move {
+        from: Square::try_from((bits & 0x3F) as u8).ok()?,
+        to: Square::try_from((bits >> 6 & 0x3F) as u8).ok()?,
+        promote: match (bits >> 12 & 0b111) as u8 {
+            0 => None,
+            piece => Some(Piece::try_from(piece - 1).ok()?),
+        },
+        score: 0,
+    })
+}
+
+/// Transposition table for storing positions and their best moves.
+/// Lock-free, so one table can be shared by all search threads.
 #[derive(Default)]
 pub struct HashTable {
-    entries: Vec<HashEntry>,
+    entries: Vec<HashSlot>,
 }
 
 impl HashTable {
     pub fn new() -> Self {
+        Self::with_slots(NUM_HASH_SLOTS)
+    }
+
+    /// Create a table using (at most) `size_mb` megabytes
+    pub fn with_size_mb(size_mb: usize) -> Self {
+        Self::with_slots(size_mb * 1024 * 1024 / size_of::<HashSlot>())
+    }
+
+    fn with_slots(num_slots: usize) -> Self {
         Self {
-            entries: vec![HashEntry::default(); NUM_HASH_SLOTS],
+            entries: (0..num_slots.max(1)).map(|_| HashSlot::default()).collect(),
         }
     }
 
-    /// Get an entry at the given index
-    fn get(&self, index: usize) -> &HashEntry {
-        &self.entries[index]
+    /// Index of the slot for a hash key
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
     }
 
-    /// Get a mutable entry at the given index
-    fn get_mut(&mut self, index: usize) -> &mut HashEntry {
-        &mut self.entries[index]
+    /// Get the slot for a hash key
+    fn get(&self, key: u64) -> &HashSlot {
+        &self.entries[self.index(key)]
+    }
+
+    /// Empty every slot, keeping the current size
+    fn clear(&self) {
+        for slot in &self.entries {
+            slot.store(0, 0);
+        }
     }
 }
 
-/// Zobrist hash manager for incremental position hashing
+/// The pawn structure terms of the handcrafted evaluation, which depend only on where the pawns
+/// are. See `Position::evaluate_pawn_structure`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PawnEntry {
+    /// Passed, doubled, isolated and backward pawn scores for each side
+    pub score: [TaperedScore; NUM_SIDES],
+    /// Midgame pawn shield for each side's king, if castled kingside
+    pub kingside_shield: [i32; NUM_SIDES],
+    /// Midgame pawn shield for each side's king, if castled queenside
+    pub queenside_shield: [i32; NUM_SIDES],
+}
+
+/// Packs four values into 64 bits, 16 each. `None` if one doesn't fit.
+fn pack_i16s(values: [i32; 4]) -> Option<u64> {
+    values.iter().rev().try_fold(0, |packed, &value| {
+        i16::try_from(value)
+            .ok()
+            .map(|value| packed << 16 | value as u16 as u64)
+    })
+}
+
+fn unpack_i16s(data: u64) -> [i32; 4] {
+    std::array::from_fn(|i| (data >> (16 * i)) as u16 as i16 as i32)
+}
+
+/// A pawn hash table slot. Like `HashSlot`, `key` is XOR'ed with the data.
 #[derive(Default)]
+struct PawnHashSlot {
+    key: AtomicU64,
+    scores: AtomicU64, // White midgame | White endgame | Black midgame | Black endgame
+    shields: AtomicU64, // White kingside | White queenside | Black kingside | Black queenside
+}
+
+/// Pawn structure scores by pawn key. Lock-free like `HashTable`.
+/// An empty slot matches positions without pawns, whose entry is all zeroes anyway.
+pub struct PawnHashTable {
+    entries: Vec<PawnHashSlot>,
+}
+
+impl Default for PawnHashTable {
+    fn default() -> Self {
+        Self::with_slots(NUM_PAWN_HASH_SLOTS)
+    }
+}
+
+impl PawnHashTable {
+    fn with_slots(num_slots: usize) -> Self {
+        Self {
+            entries: (0..num_slots.max(1))
+                .map(|_| PawnHashSlot::default())
+                .collect(),
+        }
+    }
+
+    fn get(&self, key: u64) -> &PawnHashSlot {
+        &self.entries[(key % self.entries.len() as u64) as usize]
+    }
+
+    fn probe(&self, key: u64) -> Option<PawnEntry> {
+        let slot = self.get(key);
+        let scores = slot.scores.load(Ordering::Relaxed);
+        let shields = slot.shields.load(Ordering::Relaxed);
+
+        if slot.key.load(Ordering::Relaxed) ^ scores ^ shields != key {
+            return None;
+        }
+
+        let [white_midgame, white_endgame, black_midgame, black_endgame] = unpack_i16s(scores);
+        let [
+            white_kingside,
+            white_queenside,
+            black_kingside,
+            black_queenside,
+        ] = unpack_i16s(shields);
+
+        Some(PawnEntry {
+            score: [
+                TaperedScore::new(white_midgame, white_endgame),
+                TaperedScore::new(black_midgame, black_endgame),
+            ],
+            kingside_shield: [white_kingside, black_kingside],
+            queenside_shield: [white_queenside, black_queenside],
+        })
+    }
+
+    /// Entries with a value outside the `i16` range (only possible with extreme parameters)
+    /// aren't stored
+    fn store(&self, key: u64, entry: &PawnEntry) {
+        let [white, black] = [Side::White as usize, Side::Black as usize];
+
+        let (Some(scores), Some(shields)) = (
+            pack_i16s([
+                entry.score[white].midgame,
+                entry.score[white].endgame,
+                entry.score[black].midgame,
+                entry.score[black].endgame,
+            ]),
+            pack_i16s([
+                entry.kingside_shield[white],
+                entry.queenside_shield[white],
+                entry.kingside_shield[black],
+                entry.queenside_shield[black],
+            ]),
+        ) else {
+            return;
+        };
+
+        let slot = self.get(key);
+        slot.key.store(key ^ scores ^ shields, Ordering::Relaxed);
+        slot.scores.store(scores, Ordering::Relaxed);
+        slot.shields.store(shields, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        for slot in &self.entries {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.scores.store(0, Ordering::Relaxed);
+            slot.shields.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Zobrist hash manager for incremental position hashing.
+/// Clones share the transposition and pawn hash tables, e.g. between search threads.
+#[derive(Clone, Default)]
 pub struct Hash {
     /// Current Zobrist hash key for the position
     pub current_key: u64,
+    /// Zobrist hash key of the pawns alone, for the pawn hash table
+    pub pawn_key: u64,
     /// Transposition table
-    hash_table: HashTable,
+    hash_table: Arc<HashTable>,
+    /// Pawn structure evaluations
+    pawn_table: Arc<PawnHashTable>,
 }
 
 impl Hash {
     pub fn new() -> Self {
         Self {
             current_key: 0,
-            hash_table: HashTable::new(),
+            pawn_key: 0,
+            hash_table: Arc::new(HashTable::new()),
+            pawn_table: Arc::new(PawnHashTable::default()),
         }
     }
 
-    /// Store a move in the hash table for the current position
-    pub fn store_move(&mut self, move_: Move, depth: u8, score: i32) {
-        let index = (self.current_key as usize) % NUM_HASH_SLOTS;
-        let entry = self.hash_table.get_mut(index);
+    /// Number of entries in the transposition table
+    pub fn num_slots(&self) -> usize {
+        self.hash_table.entries.len()
+    }
+
+    /// Replace the transposition table with an empty one of (at most) `size_mb` megabytes
+    pub fn resize_table(&mut self, size_mb: usize) {
+        self.hash_table = Arc::new(HashTable::with_size_mb(size_mb));
+    }
+
+    /// Empty the transposition table
+    pub fn clear_table(&mut self) {
+        self.hash_table.clear();
+    }
+
+    /// Swap transposition tables with `other`. Used to keep the table (and its size)
+    /// when a new position replaces the current one.
+    pub fn swap_table(&mut self, other: &mut Hash) {
+        std::mem::swap(&mut self.hash_table, &mut other.hash_table);
+    }
+
+    /// Empty the pawn hash table, e.g. when the evaluation parameters change
+    pub fn clear_pawn_table(&mut self) {
+        self.pawn_table.clear();
+    }
+
+    /// Look up the pawn structure evaluation for the current pawns, if available
+    pub fn probe_pawns(&self) -> Option<PawnEntry> {
+        self.pawn_table.probe(self.pawn_key)
+    }
+
+    /// Store the pawn structure evaluation for the current pawns
+    pub fn store_pawns(&self, entry: &PawnEntry) {
+        self.pawn_table.store(self.pawn_key, entry);
+    }
+
+    /// Whether `other` uses the same transposition table
+    pub fn shares_table_with(&self, other: &Hash) -> bool {
+        Arc::ptr_eq(&self.hash_table, &other.hash_table)
+    }
+
+    /// Store a move in the hash table for the current position.
+    /// `score` should already be adjusted with `score_to_hash()`.
+    pub fn store_move(&mut self, move_: Move, depth: u8, score: i32, flag: HashFlag) {
+        let slot = self.hash_table.get(self.current_key);
+        let (hash_key, data) = slot.load();
+        let entry = unpack_entry(hash_key, data);
 
         if entry.hash_key != self.current_key || depth >= entry.depth {
-            entry.hash_key = self.current_key;
-            entry.best_move = Some(move_);
-            entry.depth = depth;
-            entry.score = score;
+            let entry = HashEntry {
+                hash_key: self.current_key,
+                best_move: Some(move_),
+                depth,
+                score,
+                flag,
+            };
+
+            slot.store(self.current_key, pack_entry(&entry));
         }
     }
 
     /// Look up the hash entry for the current position, if available
-    pub fn probe(&self) -> Option<&HashEntry> {
-        let index = (self.current_key as usize) % NUM_HASH_SLOTS;
-        let entry = self.hash_table.get(index);
+    pub fn probe(&self) -> Option<HashEntry> {
+        let (hash_key, data) = self.hash_table.get(self.current_key).load();
 
         // Verify this is the same position (collision detection) and has a move stored
-        if entry.hash_key == self.current_key && entry.best_move.is_some() {
-            Some(entry)
-        } else {
-            None
+        if hash_key != self.current_key {
+            return None;
         }
+
+        Some(unpack_entry(hash_key, data)).filter(|entry| entry.best_move.is_some())
     }
 
     /// Update hash for a piece on a square. Call this twice per move (from/to).
@@ -106,8 +434,13 @@ impl Hash {
             _ => return,
         };
 
-        self.current_key ^=
-            POLYGLOT[64 * piece_index + 8 * square.rank() as usize + square.file() as usize];
+        let key = POLYGLOT[64 * piece_index + 8 * square.rank() as usize + square.file() as usize];
+
+        self.current_key ^= key;
+
+        if piece == Piece::Pawn {
+            self.pawn_key ^= key;
+        }
     }
 
     /// Toggle side-to-move in the hash
@@ -4,10 +4,15 @@ mod board;
 mod constants;
 pub mod engine;
 pub mod hash;
+mod magic;
+pub mod nnue;
+pub mod params;
 mod piece;
 mod polyglot;
 pub mod position;
 mod square;
+pub mod syzygy;
 pub mod time;
+pub mod tuning;
 pub mod types;
 pub mod uci;
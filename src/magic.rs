@@ -0,0 +1,210 @@
+use std::sync::LazyLock;
+
+use crate::{
+    constants::{BISHOP_MAGICS, NUM_SQUARES, ROOK_MAGICS},
+    types::{BitBoard, Piece},
+};
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)]; // (rank, file)
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Attack tables for every square, built once on first use
+static SLIDING_ATTACKS: LazyLock<SlidingAttacks> = LazyLock::new(SlidingAttacks::new);
+
+/// The shared attack tables. Keep the reference to skip the initialization check on each lookup.
+pub fn sliding_attacks() -> &'static SlidingAttacks {
+    &SLIDING_ATTACKS
+}
+
+/// How to find a square's attacks in the shared table.
+/// https://www.chessprogramming.org/Magic_Bitboards ("fancy" magics)
+#[derive(Clone, Copy, Default)]
+struct Magic {
+    mask: u64,  // Squares whose occupancy affects the attacks (rays without their edge squares)
+    magic: u64, // Multiplier that maps each blocker arrangement to a unique index
+    shift: u32, // 64 minus the number of bits in `mask`
+    offset: usize, // Start of this square's attacks in `SlidingAttacks::table`
+    rays: u64,  // Attacks on an empty board
+}
+
+impl Magic {
+    fn index(&self, occupied: BitBoard) -> usize {
+        self.offset + ((occupied.0 & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+/// Magic bitboard lookups for bishop, rook and queen attacks
+pub struct SlidingAttacks {
+    bishop: [Magic; NUM_SQUARES],
+    rook: [Magic; NUM_SQUARES],
+    table: Vec<u64>,
+}
+
+impl SlidingAttacks {
+    fn new() -> Self {
+        let mut table = Vec::new();
+        let bishop = Self::init_magics(&BISHOP_DIRECTIONS, &BISHOP_MAGICS, &mut table);
+        let rook = Self::init_magics(&ROOK_DIRECTIONS, &ROOK_MAGICS, &mut table);
+
+        Self {
+            bishop,
+            rook,
+            table,
+        }
+    }
+
+    /// Attacks for a bishop, rook or queen on `square`
+    #[inline]
+    pub fn slider(&self, piece: Piece, square: u8, occupied: BitBoard) -> BitBoard {
+        match piece {
+            Piece::Bishop => self.bishop(square, occupied),
+            Piece::Rook => self.rook(square, occupied),
+            _ => self.queen(square, occupied),
+        }
+    }
+
+    /// Squares attacked by a bishop on `square`, up to and including the first piece on each ray
+    #[inline]
+    pub fn bishop(&self, square: u8, occupied: BitBoard) -> BitBoard {
+        BitBoard(self.table[self.bishop[square as usize].index(occupied)])
+    }
+
+    /// Squares attacked by a rook on `square`, up to and including the first piece on each ray
+    #[inline]
+    pub fn rook(&self, square: u8, occupied: BitBoard) -> BitBoard {
+        BitBoard(self.table[self.rook[square as usize].index(occupied)])
+    }
+
+    /// Bishop and rook attacks combined
+    #[inline]
+    pub fn queen(&self, square: u8, occupied: BitBoard) -> BitBoard {
+        BitBoard(self.bishop(square, occupied).0 | self.rook(square, occupied).0)
+    }
+
+    /// Squares a bishop on `square` would attack on an empty board. A cheap check before a lookup.
+    #[inline]
+    pub fn bishop_rays(&self, square: u8) -> BitBoard {
+        BitBoard(self.bishop[square as usize].rays)
+    }
+
+    /// Squares a rook on `square` would attack on an empty board. A cheap check before a lookup.
+    #[inline]
+    pub fn rook_rays(&self, square: u8) -> BitBoard {
+        BitBoard(self.rook[square as usize].rays)
+    }
+
+    /// Fill `table` with the attacks for every blocker arrangement on every square
+    fn init_magics(
+        directions: &[(i8, i8)],
+        magics: &[u64; NUM_SQUARES],
+        table: &mut Vec<u64>,
+    ) -> [Magic; NUM_SQUARES] {
+        let mut square_magics = [Magic::default(); NUM_SQUARES];
+
+        for (square, square_magic) in square_magics.iter_mut().enumerate() {
+            let mask = relevant_blockers(square, directions);
+
+            *square_magic = Magic {
+                mask,
+                magic: magics[square],
+                shift: 64 - mask.count_ones(),
+                offset: table.len(),
+                rays: slow_attacks(square, 0, directions),
+            };
+
+            table.resize(table.len() + (1 << mask.count_ones()), 0);
+
+            // Visit every subset of `mask` (Carry-Rippler)
+            let mut blockers = 0u64;
+            loop {
+                let attacks = slow_attacks(square, blockers, directions);
+                let slot = &mut table[square_magic.index(BitBoard(blockers))];
+
+                assert!(
+                    *slot == 0 || *slot == attacks,
+                    "Magic for square {} maps different attacks to the same index",
+                    square
+                );
+                *slot = attacks;
+
+                blockers = blockers.wrapping_sub(mask) & mask;
+                if blockers == 0 {
+                    break;
+                }
+            }
+        }
+
+        square_magics
+    }
+}
+
+/// Walk each ray from `square` until the edge of the board or a blocker
+fn slow_attacks(square: usize, blockers: u64, directions: &[(i8, i8)]) -> u64 {
+    let mut attacks = 0;
+
+    for &(rank_step, file_step) in directions {
+        let mut rank = (square / 8) as i8 + rank_step;
+        let mut file = (square % 8) as i8 + file_step;
+
+        while (0..8).contains(&rank) && (0..8).contains(&file) {
+            let bit = 1u64 << (rank * 8 + file);
+            attacks |= bit;
+
+            if blockers & bit != 0 {
+                break;
+            }
+
+            rank += rank_step;
+            file += file_step;
+        }
+    }
+
+    attacks
+}
+
+/// A piece on the last square of a ray can't block anything, so edges are left out
+fn relevant_blockers(square: usize, directions: &[(i8, i8)]) -> u64 {
+    let mut mask = 0;
+
+    for &(rank_step, file_step) in directions {
+        let mut rank = (square / 8) as i8 + rank_step;
+        let mut file = (square % 8) as i8 + file_step;
+
+        while (0..8).contains(&(rank + rank_step)) && (0..8).contains(&(file + file_step)) {
+            mask |= 1u64 << (rank * 8 + file);
+            rank += rank_step;
+            file += file_step;
+        }
+    }
+
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_magic_attacks_match_ray_walks() {
+        let attacks = sliding_attacks();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..1000 {
+            // Sparse boards, like real positions
+            let occupied = rng.r#gen::<u64>() & rng.r#gen::<u64>();
+
+            for square in 0..NUM_SQUARES {
+                let bishop = slow_attacks(square, occupied, &BISHOP_DIRECTIONS);
+                let rook = slow_attacks(square, occupied, &ROOK_DIRECTIONS);
+
+                let occupied = BitBoard(occupied);
+                let square = square as u8;
+
+                assert_eq!(attacks.bishop(square, occupied).0, bishop);
+                assert_eq!(attacks.rook(square, occupied).0, rook);
+                assert_eq!(attacks.queen(square, occupied).0, bishop | rook);
+            }
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    constants::{MATE_THRESHOLD, NUM_PIECE_TYPES, NUM_SIDES, NUM_SQUARES},
+    types::{Board, Piece, Side, Square},
+};
+
+/// One input per (own or opponent's) piece type on each square, from one side's point of view
+pub const NUM_FEATURES: usize = 2 * NUM_PIECE_TYPES * NUM_SQUARES;
+
+/// Network files start with these bytes, then the format version and the hidden layer size
+const NETWORK_MAGIC: &[u8; 4] = b"CENN";
+const NETWORK_VERSION: u32 = 1;
+
+/// The hidden layer is processed 16 values at a time, and is capped so sums fit in 32-bit lanes
+const HIDDEN_SIZE_MULTIPLE: usize = 16;
+const MAX_HIDDEN_SIZE: usize = 2048;
+
+/// Quantization: hidden activations are clipped to [0, QA], output weights are scaled by QB
+const QA: i32 = 255;
+const QB: i32 = 64;
+/// Converts the network's output to centipawns
+const EVAL_SCALE: i64 = 400;
+
+/// A quantized "768 -> hidden x 2 -> 1" network (https://www.chessprogramming.org/NNUE).
+/// Each side has its own accumulator of hidden values. The side to move's comes first when they
+/// are joined for the output layer.
+///
+/// File format, all little-endian:
+/// - `CENN`, version (u32), hidden size (u32)
+/// - Feature weights (i16), `NUM_FEATURES` rows of hidden size
+/// - Feature biases (i16), hidden size
+/// - Output weights (i16), twice the hidden size: side to move's half first
+/// - Output bias (i32)
+pub struct Network {
+    hidden_size: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+    use_avx2: bool, // Detected once at load time
+}
+
+impl Network {
+    /// Load a network file written in the format above
+    pub fn load(path: &str) -> io::Result<Self> {
+        let file = File::open(Path::new(path))?;
+        let mut reader = BufReader::new(file);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+
+        Self::from_bytes(&buf)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        if bytes.len() < 12 || &bytes[0..4] != NETWORK_MAGIC {
+            return Err(invalid("Not a network file".to_string()));
+        }
+
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+
+        if version != NETWORK_VERSION {
+            return Err(invalid(format!("Unsupported network version {}", version)));
+        }
+
+        let hidden_size = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+
+        if hidden_size == 0
+            || !hidden_size.is_multiple_of(HIDDEN_SIZE_MULTIPLE)
+            || hidden_size > MAX_HIDDEN_SIZE
+        {
+            return Err(invalid(format!(
+                "Hidden layer size must be a multiple of {} up to {}, got {}",
+                HIDDEN_SIZE_MULTIPLE, MAX_HIDDEN_SIZE, hidden_size
+            )));
+        }
+
+        let expected_len = 12 + 2 * (NUM_FEATURES * hidden_size + 3 * hidden_size) + 4;
+
+        if bytes.len() != expected_len {
+            return Err(invalid(format!(
+                "Expected {} bytes for hidden layer size {}, got {}",
+                expected_len,
+                hidden_size,
+                bytes.len()
+            )));
+        }
+
+        let mut rest = &bytes[12..];
+        let mut read_i16s = |count: usize| {
+            let (values, remaining) = rest.split_at(2 * count);
+            rest = remaining;
+
+            values
+                .chunks_exact(2)
+                .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>()
+        };
+
+        let feature_weights = read_i16s(NUM_FEATURES * hidden_size);
+        let feature_biases = read_i16s(hidden_size);
+        let output_weights = read_i16s(2 * hidden_size);
+        let output_bias = i32::from_le_bytes(rest.try_into().unwrap());
+
+        Ok(Self {
+            hidden_size,
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias,
+            use_avx2: has_avx2(),
+        })
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    /// Feature weights for one input
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden_size..(feature + 1) * self.hidden_size]
+    }
+}
+
+/// The input for a `side` `piece` on `square`, seen from `perspective`. Black sees the board
+/// mirrored top to bottom, so both sides see their own pieces the same way.
+fn feature_index(perspective: Side, side: Side, piece: Piece, square: Square) -> usize {
+    let (owner, square) = match perspective {
+        Side::White => (side as usize, square as usize),
+        Side::Black => (side.opponent() as usize, square as usize ^ 56),
+    };
+
+    (owner * NUM_PIECE_TYPES + piece as usize) * NUM_SQUARES + square
+}
+
+/// The network's hidden values for the current board, one set per side. `Board` updates it
+/// whenever a piece is added, removed or moved, so it never needs rebuilding during a search.
+#[derive(Clone)]
+pub struct Accumulator {
+    network: Arc<Network>,
+    values: [Vec<i16>; NUM_SIDES], // [perspective][hidden]
+}
+
+impl Accumulator {
+    /// Build the accumulator from scratch for the pieces on `board`
+    pub fn new(network: Arc<Network>, board: &Board) -> Self {
+        let mut accumulator = Self {
+            values: [
+                network.feature_biases.clone(),
+                network.feature_biases.clone(),
+            ],
+            network,
+        };
+
+        for side in Side::iter() {
+            for piece in Piece::iter().filter(|&piece| piece != Piece::Empty) {
+                let mut squares = board.bit_pieces[side as usize][piece as usize];
+
+                while squares.0 != 0 {
+                    let square = squares
+                        .next_bit_mut()
+                        .try_into()
+                        .expect("Failed to convert u8 to Square");
+
+                    accumulator.add(side, piece, square);
+                }
+            }
+        }
+
+        accumulator
+    }
+
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    pub fn add(&mut self, side: Side, piece: Piece, square: Square) {
+        for perspective in Side::iter() {
+            let weights = self
+                .network
+                .weights(feature_index(perspective, side, piece, square));
+
+            for (value, weight) in self.values[perspective as usize].iter_mut().zip(weights) {
+                *value = value.wrapping_add(*weight);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, side: Side, piece: Piece, square: Square) {
+        for perspective in Side::iter() {
+            let weights = self
+                .network
+                .weights(feature_index(perspective, side, piece, square));
+
+            for (value, weight) in self.values[perspective as usize].iter_mut().zip(weights) {
+                *value = value.wrapping_sub(*weight);
+            }
+        }
+    }
+
+    /// `remove` and `add` in one pass
+    pub fn move_piece(&mut self, side: Side, piece: Piece, from: Square, to: Square) {
+        for perspective in Side::iter() {
+            let from_weights = self
+                .network
+                .weights(feature_index(perspective, side, piece, from));
+            let to_weights = self
+                .network
+                .weights(feature_index(perspective, side, piece, to));
+
+            for ((value, from_weight), to_weight) in self.values[perspective as usize]
+                .iter_mut()
+                .zip(from_weights)
+                .zip(to_weights)
+            {
+                *value = value.wrapping_sub(*from_weight).wrapping_add(*to_weight);
+            }
+        }
+    }
+
+    /// Centipawns for `side_to_move`, kept clear of mate scores
+    pub fn evaluate(&self, side_to_move: Side) -> i32 {
+        let network = &self.network;
+        let (own_weights, opponent_weights) = network.output_weights.split_at(network.hidden_size);
+
+        let output = clipped_dot(
+            &self.values[side_to_move as usize],
+            own_weights,
+            network.use_avx2,
+        ) + clipped_dot(
+            &self.values[side_to_move.opponent() as usize],
+            opponent_weights,
+            network.use_avx2,
+        ) + network.output_bias as i64;
+
+        let score = output * EVAL_SCALE / (QA * QB) as i64;
+        let limit = (MATE_THRESHOLD - 1) as i64;
+
+        score.clamp(-limit, limit) as i32
+    }
+}
+
+fn has_avx2() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        is_x86_feature_detected!("avx2")
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        false
+    }
+}
+
+/// Sum of each value, clipped to [0, QA], times its weight
+fn clipped_dot(values: &[i16], weights: &[i16], use_avx2: bool) -> i64 {
+    #[cfg(target_arch = "x86_64")]
+    if use_avx2 {
+        // SAFETY: `use_avx2` is only set when the CPU supports AVX2
+        return unsafe { clipped_dot_avx2(values, weights) };
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = use_avx2;
+
+    clipped_dot_scalar(values, weights)
+}
+
+fn clipped_dot_scalar(values: &[i16], weights: &[i16]) -> i64 {
+    values
+        .iter()
+        .zip(weights)
+        .map(|(&value, &weight)| (value as i32).clamp(0, QA) as i64 * weight as i64)
+        .sum()
+}
+
+/// 16 values at a time. Each pair of products is added into a 32-bit lane, which
+/// `MAX_HIDDEN_SIZE` keeps from overflowing.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn clipped_dot_avx2(values: &[i16], weights: &[i16]) -> i64 {
+    use std::arch::x86_64::{
+        __m256i, _mm256_add_epi32, _mm256_loadu_si256, _mm256_madd_epi16, _mm256_max_epi16,
+        _mm256_min_epi16, _mm256_set1_epi16, _mm256_setzero_si256, _mm256_storeu_si256,
+    };
+
+    let zero = _mm256_setzero_si256();
+    let max = _mm256_set1_epi16(QA as i16);
+    let mut sum = _mm256_setzero_si256();
+
+    for (values, weights) in values.chunks_exact(16).zip(weights.chunks_exact(16)) {
+        // SAFETY: Each chunk holds exactly 16 i16s (256 bits); unaligned loads are allowed
+        let (values, weights) = unsafe {
+            (
+                _mm256_loadu_si256(values.as_ptr().cast::<__m256i>()),
+                _mm256_loadu_si256(weights.as_ptr().cast::<__m256i>()),
+            )
+        };
+
+        let clipped = _mm256_min_epi16(_mm256_max_epi16(values, zero), max);
+        sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, weights));
+    }
+
+    let mut lanes = [0i32; 8];
+    // SAFETY: `lanes` is 256 bits
+    unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast::<__m256i>(), sum) };
+
+    lanes.iter().map(|&lane| lane as i64).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_avx2_matches_scalar() {
+        if !has_avx2() {
+            return;
+        }
+
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..100 {
+            let values: Vec<i16> = (0..MAX_HIDDEN_SIZE).map(|_| rng.r#gen()).collect();
+            let weights: Vec<i16> = (0..MAX_HIDDEN_SIZE).map(|_| rng.r#gen()).collect();
+
+            assert_eq!(
+                clipped_dot(&values, &weights, true),
+                clipped_dot_scalar(&values, &weights)
+            );
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::{fmt, fs, io};
+
+use crate::{
+    constants::{
+        BACKWARD_PAWN_SCORE, BISHOP_ENDGAME_SCORE, BISHOP_PAIR_SCORE, BISHOP_SCORE,
+        CONNECTED_ROOKS_SCORE, DOUBLED_PAWN_SCORE, ISOLATED_PAWN_SCORE, KING_ATTACK_WEIGHTS,
+        KING_ENDGAME_SCORE, KING_OPEN_FILE_UNITS, KING_SAFETY_TABLE, KING_SCORE,
+        KING_SEMI_OPEN_FILE_UNITS, KINGSIDE_DEFENSE, KNIGHT_ENDGAME_SCORE, KNIGHT_OUTPOST_SCORE,
+        KNIGHT_SCORE, MOBILITY_SCORE, NUM_PIECE_TYPES, NUM_SIDES, NUM_SQUARES,
+        PASSED_ENDGAME_SCORE, PASSED_SCORE, PAWN_ENDGAME_SCORE, PAWN_SCORE, PAWN_STORM_UNITS,
+        QUEEN_ENDGAME_SCORE, QUEEN_SCORE, QUEENSIDE_DEFENSE, ROOK_ENDGAME_SCORE,
+        ROOK_HALF_OPEN_FILE_SCORE, ROOK_ON_SEVENTH_SCORE, ROOK_OPEN_FILE_SCORE, ROOK_SCORE,
+        SAFE_CHECK_UNITS,
+    },
+    types::{Piece, TaperedScore},
+};
+
+const PIECE_NAMES: [&str; NUM_PIECE_TYPES] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+/// The weights used by `Position::evaluate_handcrafted`. `Default` gives the values in
+/// `constants.rs`; `load` reads a parameter file, such as one written by the tuner.
+///
+/// Parameter files list each parameter's name followed by its values, separated by whitespace.
+/// `#` starts a comment. Parameters left out keep their default values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvalParams {
+    pub piece_value: [TaperedScore; NUM_PIECE_TYPES], // The king's is unused (always on the board) and not in files
+    pub piece_square: [[TaperedScore; NUM_SQUARES]; NUM_PIECE_TYPES], // White's, A1 - H8. Black's are mirrored.
+    pub passed_pawn: [TaperedScore; NUM_SQUARES], // White's, A1 - H8. Black's are mirrored.
+    pub isolated_pawn: TaperedScore,
+    pub doubled_pawn: TaperedScore,
+    pub backward_pawn: TaperedScore,
+    pub mobility: [TaperedScore; NUM_PIECE_TYPES], // Only knights to queens' are used and in files
+    pub bishop_pair: TaperedScore,
+    pub knight_outpost: TaperedScore,
+    pub rook_on_seventh: TaperedScore,
+    pub connected_rooks: TaperedScore,
+    pub rook_open_file: TaperedScore,
+    pub rook_half_open_file: TaperedScore,
+    pub kingside_defense: [[i32; NUM_SQUARES]; NUM_SIDES], // Midgame pawn shield, [side][square]
+    pub queenside_defense: [[i32; NUM_SQUARES]; NUM_SIDES],
+    pub king_attack_weights: [i32; NUM_PIECE_TYPES],
+    pub safe_check_units: [i32; NUM_PIECE_TYPES],
+    pub king_open_file_units: i32,
+    pub king_semi_open_file_units: i32,
+    pub pawn_storm_units: [i32; 8],
+    pub king_safety_table: [i32; 100],
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        let tables = [
+            (&PAWN_SCORE, &PAWN_ENDGAME_SCORE),
+            (&KNIGHT_SCORE, &KNIGHT_ENDGAME_SCORE),
+            (&BISHOP_SCORE, &BISHOP_ENDGAME_SCORE),
+            (&ROOK_SCORE, &ROOK_ENDGAME_SCORE),
+            (&QUEEN_SCORE, &QUEEN_ENDGAME_SCORE),
+            (&KING_SCORE, &KING_ENDGAME_SCORE),
+        ];
+
+        let tapered = |midgame: &[i32; NUM_SQUARES], endgame: &[i32; NUM_SQUARES]| {
+            std::array::from_fn(|square| TaperedScore::new(midgame[square], endgame[square]))
+        };
+
+        Self {
+            piece_value: std::array::from_fn(|piece| {
+                match Piece::try_from(piece as u8).expect("Failed to convert usize to Piece") {
+                    Piece::King => TaperedScore::default(),
+                    piece => TaperedScore::new(piece.value(), piece.value()),
+                }
+            }),
+            piece_square: tables.map(|(midgame, endgame)| tapered(midgame, endgame)),
+            passed_pawn: tapered(&PASSED_SCORE, &PASSED_ENDGAME_SCORE),
+            isolated_pawn: ISOLATED_PAWN_SCORE,
+            doubled_pawn: DOUBLED_PAWN_SCORE,
+            backward_pawn: BACKWARD_PAWN_SCORE,
+            mobility: MOBILITY_SCORE,
+            bishop_pair: BISHOP_PAIR_SCORE,
+            knight_outpost: KNIGHT_OUTPOST_SCORE,
+            rook_on_seventh: ROOK_ON_SEVENTH_SCORE,
+            connected_rooks: CONNECTED_ROOKS_SCORE,
+            rook_open_file: ROOK_OPEN_FILE_SCORE,
+            rook_half_open_file: ROOK_HALF_OPEN_FILE_SCORE,
+            kingside_defense: KINGSIDE_DEFENSE,
+            queenside_defense: QUEENSIDE_DEFENSE,
+            king_attack_weights: KING_ATTACK_WEIGHTS,
+            safe_check_units: SAFE_CHECK_UNITS,
+            king_open_file_units: KING_OPEN_FILE_UNITS,
+            king_semi_open_file_units: KING_SEMI_OPEN_FILE_UNITS,
+            pawn_storm_units: PAWN_STORM_UNITS,
+            king_safety_table: KING_SAFETY_TABLE,
+        }
+    }
+}
+
+/// A midgame and an endgame entry for a set of tapered scores
+fn tapered_entries<'a>(
+    name: &str,
+    scores: impl Iterator<Item = &'a mut TaperedScore>,
+) -> [(String, Vec<&'a mut i32>); 2] {
+    let (midgame, endgame) = scores
+        .map(|score| (&mut score.midgame, &mut score.endgame))
+        .unzip();
+
+    [
+        (format!("{}_midgame", name), midgame),
+        (format!("{}_endgame", name), endgame),
+    ]
+}
+
+impl EvalParams {
+    /// Load a parameter file written in the format above
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Read parameters from the text of a parameter file, starting from the defaults
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        let mut entries = params.entries_mut();
+
+        let mut tokens = text
+            .lines()
+            .flat_map(|line| {
+                line.split('#')
+                    .next()
+                    .unwrap_or_default()
+                    .split_whitespace()
+            })
+            .peekable();
+
+        while let Some(name) = tokens.next() {
+            let Some((_, values)) = entries.iter_mut().find(|(entry, _)| entry == name) else {
+                return Err(format!("Unknown parameter: {}", name));
+            };
+
+            let mut new_values = Vec::new();
+
+            while let Some(value) = tokens.next_if(|token| token.parse::<i32>().is_ok()) {
+                new_values.push(value.parse::<i32>().unwrap());
+            }
+
+            if new_values.len() != values.len() {
+                return Err(format!(
+                    "Expected {} values for {}, got {}",
+                    values.len(),
+                    name,
+                    new_values.len()
+                ));
+            }
+
+            for (value, new_value) in values.iter_mut().zip(new_values) {
+                **value = new_value;
+            }
+        }
+
+        drop(entries);
+        Ok(params)
+    }
+
+    /// Every value, in file order. The tuner adjusts these one at a time.
+    pub fn values(&self) -> Vec<i32> {
+        self.clone()
+            .entries_mut()
+            .into_iter()
+            .flat_map(|(_, values)| values.into_iter().map(|value| *value))
+            .collect()
+    }
+
+    /// The parameters with `values`, in the order given by `values()`
+    pub fn from_values(values: &[i32]) -> Self {
+        let mut params = Self::default();
+
+        for (value, new_value) in params
+            .entries_mut()
+            .into_iter()
+            .flat_map(|(_, values)| values)
+            .zip(values)
+        {
+            *value = *new_value;
+        }
+
+        params
+    }
+
+    /// Each parameter's name and values, as they appear in a parameter file
+    fn entries_mut(&mut self) -> Vec<(String, Vec<&mut i32>)> {
+        let mut entries = Vec::new();
+
+        // Only the weights the evaluation uses, so the tuner doesn't adjust the others
+        let pieces = Piece::Pawn as usize..Piece::King as usize;
+        entries.extend(tapered_entries(
+            "piece_value",
+            self.piece_value[pieces].iter_mut(),
+        ));
+
+        for (name, table) in PIECE_NAMES.iter().zip(self.piece_square.iter_mut()) {
+            entries.extend(tapered_entries(
+                &format!("{}_square", name),
+                table.iter_mut(),
+            ));
+        }
+
+        entries.extend(tapered_entries("passed_pawn", self.passed_pawn.iter_mut()));
+
+        for (name, score) in [
+            ("isolated_pawn", &mut self.isolated_pawn),
+            ("doubled_pawn", &mut self.doubled_pawn),
+            ("backward_pawn", &mut self.backward_pawn),
+            ("bishop_pair", &mut self.bishop_pair),
+            ("knight_outpost", &mut self.knight_outpost),
+            ("rook_on_seventh", &mut self.rook_on_seventh),
+            ("connected_rooks", &mut self.connected_rooks),
+            ("rook_open_file", &mut self.rook_open_file),
+            ("rook_half_open_file", &mut self.rook_half_open_file),
+        ] {
+            entries.push((
+                name.to_string(),
+                vec![&mut score.midgame, &mut score.endgame],
+            ));
+        }
+
+        let pieces = Piece::Knight as usize..Piece::King as usize;
+        entries.extend(tapered_entries(
+            "mobility",
+            self.mobility[pieces].iter_mut(),
+        ));
+
+        for (name, tables) in [
+            ("kingside_defense", &mut self.kingside_defense),
+            ("queenside_defense", &mut self.queenside_defense),
+        ] {
+            let [white, black] = tables;
+            entries.push((format!("{}_white", name), white.iter_mut().collect()));
+            entries.push((format!("{}_black", name), black.iter_mut().collect()));
+        }
+
+        for (name, values) in [
+            ("king_attack_weights", &mut self.king_attack_weights[..]),
+            ("safe_check_units", &mut self.safe_check_units[..]),
+            ("pawn_storm_units", &mut self.pawn_storm_units[..]),
+            ("king_safety_table", &mut self.king_safety_table[..]),
+        ] {
+            entries.push((name.to_string(), values.iter_mut().collect()));
+        }
+
+        entries.push((
+            "king_open_file_units".to_string(),
+            vec![&mut self.king_open_file_units],
+        ));
+        entries.push((
+            "king_semi_open_file_units".to_string(),
+            vec![&mut self.king_semi_open_file_units],
+        ));
+
+        entries
+    }
+}
+
+/// The parameter file format, eight values to a line (one rank of a table)
+impl fmt::Display for EvalParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, values) in self.clone().entries_mut() {
+            writeln!(f, "{}", name)?;
+
+            for row in values.chunks(8) {
+                let row: Vec<String> = row.iter().map(|value| format!("{:5}", value)).collect();
+                writeln!(f, "{}", row.join(" "))?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
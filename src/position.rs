@@ -1,15 +1,55 @@
+use std::{cell::Cell, sync::Arc};
+
 use crate::{
     constants::{
-        BISHOP_CAPTURE_SCORE, CAPTURE_SCORE, CASTLE_MASK, COLUMN, DEFAULT_MAX_QUIESCENCE_DEPTH,
-        GAME_STACK, INFINITY_SCORE, ISOLATED_PAWN_SCORE, KING_CAPTURE_SCORE, KINGSIDE_DEFENSE,
-        KNIGHT_CAPTURE_SCORE, MATE_SCORE, MAX_HISTORY_SCORE, MAX_PLY, MOVE_STACK, NUM_PIECE_TYPES,
-        NUM_SIDES, NUM_SQUARES, PAWN_CAPTURE_SCORE, QUEEN_CAPTURE_SCORE, QUEENSIDE_DEFENSE,
-        REVERSE_SQUARE, ROOK_CAPTURE_SCORE, ROW,
+        BISHOP_CAPTURE_SCORE, CAPTURE_SCORE, CASTLE_MASK, COLUMN, COUNTERMOVE_SCORE,
+        DEFAULT_MAX_QUIESCENCE_DEPTH, DELTA_PRUNING_MARGIN, FUTILITY_MARGIN, GAME_STACK,
+        HASH_SCORE, INFINITY_SCORE, KILLER_SCORE, KING_CAPTURE_SCORE, KING_ZONE_MIN_ATTACKERS,
+        KNIGHT_CAPTURE_SCORE, LMP_BASE_MOVES, LMP_MAX_DEPTH, LMR_BASE, LMR_DIVISOR,
+        LMR_FULL_DEPTH_MOVES, LMR_MIN_DEPTH, MATE_SCORE, MATE_THRESHOLD, MAX_HISTORY_SCORE,
+        MAX_PHASE, MAX_PLY, MOVE_STACK, NULL_MOVE_MIN_DEPTH, NULL_MOVE_REDUCTION,
+        NULL_MOVE_REDUCTION_DEPTH_DIVISOR, NUM_PIECE_TYPES, NUM_SIDES, NUM_SQUARES,
+        PAWN_CAPTURE_SCORE, PHASE_WEIGHTS, QUEEN_CAPTURE_SCORE, QUIESCENCE_CHECK_PLIES,
+        RAZORING_MARGIN, REVERSE_FUTILITY_MARGIN, REVERSE_SQUARE, ROOK_CAPTURE_SCORE, ROW,
+        TB_WIN_SCORE,
     },
+    hash::{HashEntry, HashFlag, PawnEntry, score_from_hash, score_to_hash},
+    magic::{SlidingAttacks, sliding_attacks},
+    params::EvalParams,
+    syzygy::{self, Tablebases, Wdl},
     time::TimeManager,
-    types::{BitBoard, Board, Game, GameState, Move, Piece, Side, Square},
+    types::{
+        BitBoard, Board, EvalTerm, EvalTrace, Game, GameState, Move, MoveData, Piece, SearchAbort,
+        Side, Square, TaperedScore,
+    },
 };
 
+/// The squares one side attacks, collected during `Position::evaluate` for king safety
+#[derive(Default)]
+struct AttackInfo {
+    by_piece: [u64; NUM_PIECE_TYPES],
+    all: u64,
+    king_attackers: i32,    // Pieces attacking the enemy king zone
+    king_attack_units: i32, // `king_attack_weights` for each attacked square in the zone
+}
+
+impl AttackInfo {
+    fn add(&mut self, piece: Piece, attacks: BitBoard, enemy_king_zone: u64, params: &EvalParams) {
+        self.by_piece[piece as usize] |= attacks.0;
+        self.all |= attacks.0;
+
+        let zone_attacks = (attacks.0 & enemy_king_zone).count_ones() as i32;
+        let weight = params.king_attack_weights[piece as usize];
+
+        if zone_attacks > 0 && weight > 0 {
+            self.king_attackers += 1;
+            self.king_attack_units += weight * zone_attacks;
+        }
+    }
+}
+
+/// Clones share the transposition table, so each search thread can have its own `Position`
+#[derive(Clone)]
 pub struct Position {
     // DYNAMIC
     pub side: Side,
@@ -23,6 +63,10 @@ pub struct Position {
 
     pub pv_table: [[Option<Move>; MAX_PLY]; MAX_PLY], // Principal variation: [ply][move_index]
     pub pv_length: [usize; MAX_PLY],                  // Length of PV at each ply
+    pub excluded_root_moves: Vec<MoveData>, // Skipped at ply 0, e.g. the best lines already found in MultiPV
+    pub root_search_moves: Vec<MoveData>, // If not empty, only these are searched at ply 0 (UCI "searchmoves")
+    pub killer_moves: [[Option<MoveData>; 2]; MAX_PLY], // Quiet moves that caused a beta cutoff at each ply, most recent first
+    pub countermoves: [[Option<MoveData>; NUM_SQUARES]; NUM_SQUARES], // [from][to] of a move = quiet reply that refuted it
 
     pub current_pawn_score: [usize; NUM_SIDES],
     pub current_non_pawn_score: [usize; NUM_SIDES],
@@ -33,24 +77,26 @@ pub struct Position {
 
     pub nodes: usize, // Total nodes (position in search tree) searched since start of turn
     pub qnodes: usize, // Quiescence nodes searched
+    pub helper_nodes: usize, // Nodes searched by helper threads (Lazy SMP), updated between iterations
     pub max_depth_reached: usize, // Maximum quiescence depth reached in current search
 
-    pub hash_hits: usize,    // Number of transposition table hits
-    pub hash_stores: usize,  // Number of positions stored in hash table
-    pub beta_cutoffs: usize, // Number of beta cutoffs (fail-highs)
+    pub hash_hits: usize,              // Number of transposition table hits
+    pub hash_stores: usize,            // Number of positions stored in hash table
+    pub beta_cutoffs: usize,           // Number of beta cutoffs (fail-highs)
+    pub pawn_hash_probes: Cell<usize>, // Handcrafted evaluations looking up the pawn hash table
+    pub pawn_hash_hits: Cell<usize>,   // Pawn hash table hits
+    pub tb_hits: usize,                // Positions found in the endgame tablebases during search
     // STATIC
-    square_score: [[[i32; NUM_SQUARES]; NUM_PIECE_TYPES]; NUM_SIDES],
-    king_endgame_score: [[i32; NUM_SQUARES]; NUM_SIDES],
-    passed_pawns_score: [[i32; NUM_SQUARES]; NUM_SIDES], // Score for 7th rank is built into `square_score`
+    tablebases: Option<Arc<Tablebases>>, // Syzygy tablebases to probe, if any
+    eval_params: Arc<EvalParams>, // Weights for the handcrafted evaluation; `square_score` and `passed_pawns_score` are built from them
+    square_score: [[[TaperedScore; NUM_SQUARES]; NUM_PIECE_TYPES]; NUM_SIDES], // Piece-square tables, without the piece values
+    passed_pawns_score: [[TaperedScore; NUM_SQUARES]; NUM_SIDES], // Score for 7th rank is built into `square_score`
     bit_between: [[BitBoard; NUM_SQUARES]; NUM_SQUARES], // &'ed with `bit_all`. 0-result means nothing blocking the line
-    bit_after: [[BitBoard; NUM_SQUARES]; NUM_SQUARES], // Square and those after it in vector are 0
     bit_pawn_left_captures: [[BitBoard; NUM_SQUARES]; NUM_SIDES],
     bit_pawn_right_captures: [[BitBoard; NUM_SQUARES]; NUM_SIDES],
     bit_pawn_defends: [[BitBoard; NUM_SQUARES]; NUM_SIDES],
     bit_knight_moves: [BitBoard; NUM_SQUARES],
-    bit_bishop_moves: [BitBoard; NUM_SQUARES],
-    bit_rook_moves: [BitBoard; NUM_SQUARES],
-    bit_queen_moves: [BitBoard; NUM_SQUARES],
+    sliding_attacks: &'static SlidingAttacks, // Magic bitboard lookups for bishops, rooks and queens
     bit_king_moves: [BitBoard; NUM_SQUARES],
     mask_passed: [[BitBoard; NUM_SQUARES]; NUM_SIDES],
     mask_path: [[BitBoard; NUM_SQUARES]; NUM_SIDES],
@@ -93,14 +139,12 @@ impl Position {
             not_h_file,
         ) = Board::get_pawn_masks();
 
-        let (square_score, king_endgame_score, passed_pawns_score) = Board::get_score_tables();
+        let eval_params = Arc::new(EvalParams::default());
+        let (square_score, passed_pawns_score) = Board::get_score_tables(&eval_params);
 
         let mut first_move = [-1; MAX_PLY];
         first_move[0] = 0;
 
-        let (bit_queen_moves, bit_rook_moves, bit_bishop_moves) =
-            Board::get_queen_rook_bishop_moves();
-
         let mut position = Self {
             // Dynamic
             move_list: [None; MOVE_STACK],
@@ -109,10 +153,14 @@ impl Position {
             fifty: 0,
             nodes: 0,
             qnodes: 0,
+            helper_nodes: 0,
             max_depth_reached: 0,
             hash_hits: 0,
             hash_stores: 0,
             beta_cutoffs: 0,
+            pawn_hash_probes: Cell::new(0),
+            pawn_hash_hits: Cell::new(0),
+            tb_hits: 0,
             ply: 0,
             ply_from_start_of_game: 0,
             board: Board::new(),
@@ -122,21 +170,23 @@ impl Position {
             castle: 0b1111, // All castling rights available
             pv_table: [[None; MAX_PLY]; MAX_PLY],
             pv_length: [0; MAX_PLY],
+            excluded_root_moves: Vec::new(),
+            root_search_moves: Vec::new(),
+            killer_moves: [[None; 2]; MAX_PLY],
+            countermoves: [[None; NUM_SQUARES]; NUM_SQUARES],
             time_manager,
             side: Side::White,
             // Static
+            tablebases: None,
+            eval_params,
             square_score,
-            king_endgame_score,
             passed_pawns_score,
             bit_between: Board::get_bit_between(),
-            bit_after: Board::get_bit_after(),
             bit_pawn_left_captures,
             bit_pawn_right_captures,
             bit_pawn_defends,
             bit_knight_moves: Board::get_knight_moves(),
-            bit_bishop_moves,
-            bit_rook_moves,
-            bit_queen_moves,
+            sliding_attacks: sliding_attacks(),
             bit_king_moves: Board::get_king_moves(),
             mask_passed,
             mask_path,
@@ -171,11 +221,22 @@ impl Position {
     }
 
     fn add_capture(&mut self, from: Square, to: Square, score: isize, move_count: &mut isize) {
+        // Taking a piece worth at least as much as the attacker can't lose material
+        let exchange =
+            match self.board.value[from as usize].value() > self.board.value[to as usize].value() {
+                true => self.static_exchange_evaluation(from, to, None),
+                false => 0,
+            };
+
         let move_ = Move {
             from,
             to,
             promote: None,
-            score: score + CAPTURE_SCORE as isize,
+            // Losing captures go after every other move
+            score: match exchange < 0 {
+                true => exchange as isize,
+                false => score + CAPTURE_SCORE as isize,
+            },
         };
 
         self.move_list[*move_count as usize] = Some(move_);
@@ -383,22 +444,32 @@ impl Position {
             return true;
         }
 
-        let mut b1 = BitBoard(
-            (self.bit_rook_moves[square as usize].0
-                & (bit_pieces[Piece::Rook as usize].0 | bit_pieces[Piece::Queen as usize].0))
-                | (self.bit_bishop_moves[square as usize].0
-                    & (bit_pieces[Piece::Bishop as usize].0 | bit_pieces[Piece::Queen as usize].0)),
-        );
+        let rooks_and_queens =
+            bit_pieces[Piece::Rook as usize].0 | bit_pieces[Piece::Queen as usize].0;
+        let bishops_and_queens =
+            bit_pieces[Piece::Bishop as usize].0 | bit_pieces[Piece::Queen as usize].0;
 
-        while b1.0 != 0 {
-            let attacking_piece = b1.next_bit_mut();
+        // Only look up attacks when a slider is on one of the square's rays
+        if (self.sliding_attacks.rook_rays(square as u8).0 & rooks_and_queens) != 0
+            && (self
+                .sliding_attacks
+                .rook(square as u8, self.board.bit_all)
+                .0
+                & rooks_and_queens)
+                != 0
+        {
+            return true;
+        }
 
-            if (self.bit_between[attacking_piece as usize][square as usize].0
-                & self.board.bit_all.0)
-                == 0
-            {
-                return true;
-            }
+        if (self.sliding_attacks.bishop_rays(square as u8).0 & bishops_and_queens) != 0
+            && (self
+                .sliding_attacks
+                .bishop(square as u8, self.board.bit_all)
+                .0
+                & bishops_and_queens)
+                != 0
+        {
+            return true;
         }
 
         if (self.bit_king_moves[square as usize].0 & bit_pieces[Piece::King as usize].0) != 0 {
@@ -433,25 +504,16 @@ impl Position {
             return Square::try_from(b1.next_bit()).ok();
         }
 
-        for (piece, bit_moves) in [
-            (Piece::Bishop, self.bit_bishop_moves),
-            (Piece::Rook, self.bit_rook_moves),
-            (Piece::Queen, self.bit_queen_moves),
-        ] {
-            let mut b1 = BitBoard(
-                bit_moves[square as usize].0
+        for piece in [Piece::Bishop, Piece::Rook, Piece::Queen] {
+            let b1 = BitBoard(
+                self.sliding_attacks
+                    .slider(piece, square as u8, self.board.bit_all)
+                    .0
                     & self.board.bit_pieces[side as usize][piece as usize].0,
             );
 
-            while b1.0 != 0 {
-                let attacking_piece = b1.next_bit_mut();
-
-                if (self.bit_between[attacking_piece as usize][square as usize].0
-                    & self.board.bit_all.0)
-                    == 0
-                {
-                    return Square::try_from(attacking_piece).ok();
-                }
+            if b1.0 != 0 {
+                return Square::try_from(b1.next_bit()).ok();
             }
         }
 
@@ -467,6 +529,127 @@ impl Position {
         None
     }
 
+    /// Pieces of both sides that attack `square`, given the `occupied` squares.
+    /// Removing a piece from `occupied` uncovers any slider behind it (x-ray).
+    fn attackers_of_square(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        let square = square as usize;
+        let pieces = &self.board.bit_pieces;
+        let (white, black) = (Side::White as usize, Side::Black as usize);
+
+        let of_both_sides =
+            |piece: Piece| pieces[white][piece as usize].0 | pieces[black][piece as usize].0;
+
+        let pawns = (self.bit_pawn_left_captures[black][square].0
+            | self.bit_pawn_right_captures[black][square].0)
+            & pieces[white][Piece::Pawn as usize].0
+            | (self.bit_pawn_left_captures[white][square].0
+                | self.bit_pawn_right_captures[white][square].0)
+                & pieces[black][Piece::Pawn as usize].0;
+
+        let diagonal = of_both_sides(Piece::Bishop) | of_both_sides(Piece::Queen);
+        let straight = of_both_sides(Piece::Rook) | of_both_sides(Piece::Queen);
+
+        BitBoard(
+            pawns
+                | self.bit_knight_moves[square].0 & of_both_sides(Piece::Knight)
+                | self.bit_king_moves[square].0 & of_both_sides(Piece::King)
+                | self.sliding_attacks.bishop(square as u8, occupied).0 & diagonal
+                | self.sliding_attacks.rook(square as u8, occupied).0 & straight,
+        )
+    }
+
+    /// Static Exchange Evaluation: the material the side moving from `from` wins (or loses, if
+    /// negative) when both sides keep capturing on `to` with their least valuable attacker,
+    /// each free to stop when recapturing would lose. Pins are ignored.
+    pub fn static_exchange_evaluation(
+        &self,
+        from: Square,
+        to: Square,
+        promote: Option<Piece>,
+    ) -> i32 {
+        let mut side = match self.board.bit_units[Side::White as usize].is_bit_set(from) {
+            true => Side::White,
+            false => Side::Black,
+        };
+
+        let mut occupied = self.board.bit_all.0;
+        let mut piece = self.board.value[from as usize];
+        let mut captured = self.board.value[to as usize];
+
+        // En passant: the captured pawn is behind `to`
+        if captured == Piece::Empty
+            && piece == Piece::Pawn
+            && COLUMN[from as usize] != COLUMN[to as usize]
+        {
+            captured = Piece::Pawn;
+            occupied &= !(1u64 << (to as i32 + REVERSE_SQUARE[side as usize]));
+        }
+
+        // gain[n]: material for the side making capture n, if the exchange stopped there
+        let mut gain = [0; 32];
+        let mut depth = 0;
+
+        gain[0] = captured.value();
+
+        if let Some(promote) = promote {
+            gain[0] += promote.value() - Piece::Pawn.value();
+            piece = promote;
+        }
+
+        let mut from_bit = from.as_bit();
+
+        while depth < gain.len() - 1 {
+            depth += 1;
+
+            // If the piece now on `to` is recaptured
+            gain[depth] = piece.value() - gain[depth - 1];
+
+            // Neither side can do better by continuing
+            if (-gain[depth - 1]).max(gain[depth]) < 0 {
+                break;
+            }
+
+            occupied &= !from_bit;
+            side = side.opponent();
+
+            let attackers = self.attackers_of_square(to, BitBoard(occupied)).0 & occupied;
+            let side_attackers = attackers & self.board.bit_units[side as usize].0;
+
+            let Some(attacker) = [
+                Piece::Pawn,
+                Piece::Knight,
+                Piece::Bishop,
+                Piece::Rook,
+                Piece::Queen,
+                Piece::King,
+            ]
+            .into_iter()
+            .find(|&p| side_attackers & self.board.bit_pieces[side as usize][p as usize].0 != 0) else {
+                break;
+            };
+
+            // The king can't recapture onto a defended square
+            if attacker == Piece::King
+                && attackers & self.board.bit_units[side.opponent() as usize].0 != 0
+            {
+                break;
+            }
+
+            let attacker_bits =
+                side_attackers & self.board.bit_pieces[side as usize][attacker as usize].0;
+            from_bit = attacker_bits & attacker_bits.wrapping_neg();
+            piece = attacker;
+        }
+
+        // The last capture was never made. Work back, letting each side stand pat instead.
+        while depth > 1 {
+            depth -= 1;
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        }
+
+        gain[0]
+    }
+
     fn generate_en_passant_moves(&mut self, side: Side, move_count: &mut isize) {
         if self.ply_from_start_of_game == 0 {
             return;
@@ -797,29 +980,18 @@ impl Position {
         }
 
         // Bishops, rooks, queens
-        for (piece, bit_moves, capture_score) in [
-            (Piece::Bishop, self.bit_bishop_moves, BISHOP_CAPTURE_SCORE),
-            (Piece::Rook, self.bit_rook_moves, ROOK_CAPTURE_SCORE),
-            (Piece::Queen, self.bit_queen_moves, QUEEN_CAPTURE_SCORE),
+        for (piece, capture_score) in [
+            (Piece::Bishop, BISHOP_CAPTURE_SCORE),
+            (Piece::Rook, ROOK_CAPTURE_SCORE),
+            (Piece::Queen, QUEEN_CAPTURE_SCORE),
         ] {
             let mut pieces = BitBoard(self.board.bit_pieces[side_][piece as usize].0);
 
             while pieces.0 != 0 {
                 let square_from = pieces.next_bit_mut();
-                let mut possible_moves = BitBoard(bit_moves[square_from as usize].0);
-
-                // Remove squares blocked by friendly units and squares after them
-                let mut moves_to_self_occupied_squares =
-                    BitBoard(possible_moves.0 & self.board.bit_units[side_].0);
-
-                while moves_to_self_occupied_squares.0 != 0 {
-                    let square_to = moves_to_self_occupied_squares.next_bit_mut();
-
-                    moves_to_self_occupied_squares.0 &=
-                        self.bit_after[square_from as usize][square_to as usize].0;
-
-                    possible_moves.0 &= self.bit_after[square_from as usize][square_to as usize].0;
-                }
+                let possible_moves =
+                    self.sliding_attacks
+                        .slider(piece, square_from, self.board.bit_all);
 
                 let mut possible_captures =
                     BitBoard(possible_moves.0 & self.board.bit_units[opponent].0);
@@ -827,34 +999,26 @@ impl Position {
                 while possible_captures.0 != 0 {
                     let square_to = possible_captures.next_bit_mut();
 
-                    if (self.bit_between[square_from as usize][square_to as usize].0
-                        & self.board.bit_all.0)
-                        == 0
-                    {
-                        let captured_piece = self.board.value[square_to as usize];
-                        // Skip if empty (shouldn't happen, but safeguard against board corruption)
-                        if (captured_piece as usize) < capture_score.len() {
-                            self.add_capture(
-                                square_from
-                                    .try_into()
-                                    .expect("Failed to convert square_from to Square"),
-                                square_to
-                                    .try_into()
-                                    .expect("Failed to convert square_to to Square"),
-                                capture_score[captured_piece as usize] as isize,
-                                &mut move_count,
-                            );
-                        }
+                    let captured_piece = self.board.value[square_to as usize];
+                    // Skip if empty (shouldn't happen, but safeguard against board corruption)
+                    if (captured_piece as usize) < capture_score.len() {
+                        self.add_capture(
+                            square_from
+                                .try_into()
+                                .expect("Failed to convert square_from to Square"),
+                            square_to
+                                .try_into()
+                                .expect("Failed to convert square_to to Square"),
+                            capture_score[captured_piece as usize] as isize,
+                            &mut move_count,
+                        );
                     }
-
-                    possible_captures.0 &=
-                        self.bit_after[square_from as usize][square_to as usize].0;
-
-                    possible_moves.0 &= self.bit_after[square_from as usize][square_to as usize].0;
                 }
 
-                while possible_moves.0 != 0 {
-                    let square_to = possible_moves.next_bit_mut();
+                let mut quiet_moves = BitBoard(possible_moves.0 & !self.board.bit_all.0);
+
+                while quiet_moves.0 != 0 {
+                    let square_to = quiet_moves.next_bit_mut();
 
                     let square_from: Square = square_from
                         .try_into()
@@ -1059,10 +1223,10 @@ impl Position {
         }
 
         // Bishops, rooks, queens
-        for (piece, bit_moves, capture_score) in [
-            (Piece::Bishop, self.bit_bishop_moves, BISHOP_CAPTURE_SCORE),
-            (Piece::Rook, self.bit_rook_moves, ROOK_CAPTURE_SCORE),
-            (Piece::Queen, self.bit_queen_moves, QUEEN_CAPTURE_SCORE),
+        for (piece, capture_score) in [
+            (Piece::Bishop, BISHOP_CAPTURE_SCORE),
+            (Piece::Rook, ROOK_CAPTURE_SCORE),
+            (Piece::Queen, QUEEN_CAPTURE_SCORE),
         ] {
             let mut pieces = BitBoard(self.board.bit_pieces[side_][piece as usize].0);
 
@@ -1070,34 +1234,29 @@ impl Position {
                 let attacking_square = pieces.next_bit_mut();
 
                 let mut possible_captures = BitBoard(
-                    bit_moves[attacking_square as usize].0 & self.board.bit_units[opponent].0,
+                    self.sliding_attacks
+                        .slider(piece, attacking_square, self.board.bit_all)
+                        .0
+                        & self.board.bit_units[opponent].0,
                 );
 
                 while possible_captures.0 != 0 {
                     let square_to = possible_captures.next_bit_mut();
 
-                    if (self.bit_between[attacking_square as usize][square_to as usize].0
-                        & self.board.bit_all.0)
-                        == 0
-                    {
-                        let captured_piece = self.board.value[square_to as usize];
-                        // Skip if empty (shouldn't happen, but safeguard against board corruption)
-                        if (captured_piece as usize) < capture_score.len() {
-                            self.add_capture(
-                                attacking_square
-                                    .try_into()
-                                    .expect("Failed to convert square_from to Square"),
-                                square_to
-                                    .try_into()
-                                    .expect("Failed to convert square_to to Square"),
-                                capture_score[captured_piece as usize] as isize,
-                                &mut move_count,
-                            );
-                        }
+                    let captured_piece = self.board.value[square_to as usize];
+                    // Skip if empty (shouldn't happen, but safeguard against board corruption)
+                    if (captured_piece as usize) < capture_score.len() {
+                        self.add_capture(
+                            attacking_square
+                                .try_into()
+                                .expect("Failed to convert square_from to Square"),
+                            square_to
+                                .try_into()
+                                .expect("Failed to convert square_to to Square"),
+                            capture_score[captured_piece as usize] as isize,
+                            &mut move_count,
+                        );
                     }
-
-                    possible_captures.0 &=
-                        self.bit_after[attacking_square as usize][square_to as usize].0;
                 }
             }
         }
@@ -1546,171 +1705,411 @@ impl Position {
             }
         }
 
-        // `self.ply_from_start_of_game` has already been decremented above
-        if let Some(prev_game) = self.game_list[self.ply_from_start_of_game] {
-            self.board.hash.update_en_passant(
-                match (game.en_passant_file, game.en_passant_adjacent_opponent_pawn) {
-                    (Some(file), true) => Some(file),
-                    _ => None,
-                },
+        // `self.ply_from_start_of_game` has already been decremented above.
+        // With no earlier move (e.g. at the root), there was no en passant square.
+        let prev_en_passant_file =
+            self.game_list[self.ply_from_start_of_game].and_then(|prev_game| {
                 match (
                     prev_game.en_passant_file,
                     prev_game.en_passant_adjacent_opponent_pawn,
                 ) {
                     (Some(file), true) => Some(file),
                     _ => None,
-                },
-            );
-        }
+                }
+            });
+
+        self.board.hash.update_en_passant(
+            match (game.en_passant_file, game.en_passant_adjacent_opponent_pawn) {
+                (Some(file), true) => Some(file),
+                _ => None,
+            },
+            prev_en_passant_file,
+        );
 
         self.set_material_scores();
     }
 
+    /// Pass the turn without moving (for null-move pruning). Clears any en passant square.
+    pub fn make_null_move(&mut self) {
+        let game = Game {
+            fifty: self.fifty,
+            castle: self.castle,
+            hash: self.board.hash.current_key,
+            ..Game::new() // `from == to` marks a null move, and leaves no en passant
+        };
+
+        if let Some((file, true)) = self.get_en_passant_file_and_adjacent_opponent_pawn() {
+            self.board.hash.update_en_passant(Some(file), None);
+        }
+
+        self.side = self.side.opponent();
+        self.board.hash.toggle_side_to_move();
+
+        self.ply += 1;
+        self.ply_from_start_of_game += 1;
+
+        // Positions before the null move can't repeat, so stop repetition checks here
+        self.fifty = 0;
+
+        self.game_list[self.ply_from_start_of_game] = Some(game);
+    }
+
+    pub fn take_back_null_move(&mut self) {
+        let game = self.game_list[self.ply_from_start_of_game].expect("No null move to take back");
+
+        self.side = self.side.opponent();
+        self.ply -= 1;
+        self.ply_from_start_of_game -= 1;
+        self.fifty = game.fifty;
+
+        // Restores the side to move and en passant keys
+        self.board.hash.current_key = game.hash;
+    }
+
+    /// Whether the last move made was a null move
+    fn is_after_null_move(&self) -> bool {
+        self.game_list[self.ply_from_start_of_game].is_some_and(|game| game.from == game.to)
+    }
+
+    /// Whether `move_` (not yet made) takes a piece, including en passant
+    fn is_capture(&self, move_: Move) -> bool {
+        self.board.value[move_.to as usize] != Piece::Empty
+            || (self.board.value[move_.from as usize] == Piece::Pawn
+                && COLUMN[move_.from as usize] != COLUMN[move_.to as usize])
+    }
+
+    /// Whether quiet `move_` (not yet made) checks the opponent, directly or by uncovering a slider.
+    /// Cheaper than making the move, but blind to checks given by castling.
+    fn gives_check(&self, move_: Move) -> bool {
+        let side = self.side as usize;
+        let opponent = self.side.opponent() as usize;
+        let king: Square = self.board.bit_pieces[opponent][Piece::King as usize].into();
+        let king_square = king as usize;
+
+        let occupied = BitBoard((self.board.bit_all.0 & !move_.from.as_bit()) | move_.to.as_bit());
+
+        let direct = match self.board.value[move_.from as usize] {
+            Piece::Pawn => {
+                (self.bit_pawn_left_captures[opponent][king_square].0
+                    | self.bit_pawn_right_captures[opponent][king_square].0)
+                    & move_.to.as_bit()
+                    != 0
+            }
+            Piece::Knight => self.bit_knight_moves[king_square].is_bit_set(move_.to),
+            Piece::King | Piece::Empty => false,
+            slider => self
+                .sliding_attacks
+                .slider(slider, king_square as u8, occupied)
+                .is_bit_set(move_.to),
+        };
+
+        // The moving piece is still on `from` in the piece bitboards, so leave it out
+        let discovered = self.attackers_of_square(king, occupied).0
+            & self.board.bit_units[side].0
+            & !move_.from.as_bit()
+            != 0;
+
+        direct || discovered
+    }
+
+    /// Whether `side` has a knight, bishop, rook or queen. Without one, passing the turn is
+    /// often the best move (zugzwang), so null-move pruning is unsafe.
+    fn has_non_pawn_material(&self, side: Side) -> bool {
+        let pieces = self.board.bit_pieces[side as usize];
+
+        (pieces[Piece::Knight as usize].0
+            | pieces[Piece::Bishop as usize].0
+            | pieces[Piece::Rook as usize].0
+            | pieces[Piece::Queen as usize].0)
+            != 0
+    }
+
     fn evaluate_pawn(
         &self,
         side: Side,
         square: Square,
         kingside_pawns: &mut i32,
         queenside_pawns: &mut i32,
-    ) -> i32 {
-        let mut score: i32 = 0;
-
+        add: &mut impl FnMut(Side, EvalTerm, TaperedScore),
+    ) {
         let side_ = side as usize;
+        let opponent = side.opponent() as usize;
         let square = square as usize;
         let pawn = Piece::Pawn as usize;
 
-        if (self.mask_passed[side_][square].0
-            & self.board.bit_pieces[side.opponent() as usize][pawn].0)
-            == 0
-            && self.mask_path[side_][square].0 & self.board.bit_pieces[side_][pawn].0 == 0
-        {
-            score += self.passed_pawns_score[side_][square];
+        let own_pawns = self.board.bit_pieces[side_][pawn].0;
+        let enemy_pawns = self.board.bit_pieces[opponent][pawn].0;
+
+        let doubled = self.mask_path[side_][square].0 & own_pawns != 0;
+
+        if self.mask_passed[side_][square].0 & enemy_pawns == 0 && !doubled {
+            add(
+                side,
+                EvalTerm::PassedPawns,
+                self.passed_pawns_score[side_][square],
+            );
         }
 
-        if self.mask_isolated[square].0 & self.board.bit_pieces[side_][pawn].0 == 0 {
-            score += ISOLATED_PAWN_SCORE // Is negative
+        if doubled {
+            add(side, EvalTerm::DoubledPawns, self.eval_params.doubled_pawn); // Is negative
         }
 
-        *kingside_pawns += KINGSIDE_DEFENSE[side_][square];
-        *queenside_pawns += QUEENSIDE_DEFENSE[side_][square];
+        if self.mask_isolated[square].0 & own_pawns == 0 {
+            add(
+                side,
+                EvalTerm::IsolatedPawns,
+                self.eval_params.isolated_pawn,
+            ); // Is negative
+        } else if self.mask_isolated[square].0 & !self.mask_passed[side_][square].0 & own_pawns == 0
+        {
+            // Neighbours are all further up the board. Backward if it can't advance safely.
+            let stop_square = self.pawn_plus_index[side_][square] as usize;
+
+            if self.bit_pawn_defends[opponent][stop_square].0 & enemy_pawns != 0 {
+                add(
+                    side,
+                    EvalTerm::BackwardPawns,
+                    self.eval_params.backward_pawn,
+                ); // Is negative
+            }
+        }
 
-        score
+        *kingside_pawns += self.eval_params.kingside_defense[side_][square];
+        *queenside_pawns += self.eval_params.queenside_defense[side_][square];
     }
 
-    fn evaluate_rook(&self, side: Side, square: Square) -> i32 {
-        if self.mask_column[square as usize].0
-            & self.board.bit_pieces[side as usize][Piece::Pawn as usize].0
-            == 0
+    fn evaluate_knight(&self, side: Side, square: Square) -> TaperedScore {
+        let side_ = side as usize;
+        let square = square as usize;
+        let pawn = Piece::Pawn as usize;
+
+        // Outposts: ranks 4 to 6, defended by a pawn, and out of reach of enemy pawns
+        if (3..=5).contains(&self.ranks[side_][square])
+            && self.bit_pawn_defends[side_][square].0 & self.board.bit_pieces[side_][pawn].0 != 0
+            && self.mask_passed[side_][square].0
+                & self.mask_isolated[square].0
+                & self.board.bit_pieces[side.opponent() as usize][pawn].0
+                == 0
         {
-            if self.mask_column[square as usize].0
-                & self.board.bit_pieces[side.opponent() as usize][Piece::Pawn as usize].0
+            return self.eval_params.knight_outpost;
+        }
+
+        TaperedScore::default()
+    }
+
+    fn evaluate_rook(
+        &self,
+        side: Side,
+        square: Square,
+        add: &mut impl FnMut(Side, EvalTerm, TaperedScore),
+    ) {
+        let side_ = side as usize;
+        let opponent = side.opponent() as usize;
+        let square = square as usize;
+
+        if self.mask_column[square].0 & self.board.bit_pieces[side_][Piece::Pawn as usize].0 == 0 {
+            if self.mask_column[square].0 & self.board.bit_pieces[opponent][Piece::Pawn as usize].0
                 == 0
             {
-                return 20;
+                add(side, EvalTerm::RookFiles, self.eval_params.rook_open_file);
+            } else {
+                add(
+                    side,
+                    EvalTerm::RookFiles,
+                    self.eval_params.rook_half_open_file,
+                );
             }
+        }
 
-            return 10;
+        if self.ranks[side_][square] == 6 {
+            let seventh_rank = 0xFF << (square & !7);
+            let enemy_king = self.board.bit_pieces[opponent][Piece::King as usize].next_bit();
+
+            if seventh_rank & self.board.bit_pieces[opponent][Piece::Pawn as usize].0 != 0
+                || self.ranks[side_][enemy_king as usize] == 7
+            {
+                add(
+                    side,
+                    EvalTerm::RookOnSeventh,
+                    self.eval_params.rook_on_seventh,
+                );
+            }
         }
+    }
+
+    /// Squares attacked by `side`'s pawns
+    fn pawn_attacks(&self, side: Side) -> BitBoard {
+        let pawns = self.board.bit_pieces[side as usize][Piece::Pawn as usize].0;
 
-        0
+        BitBoard(match side {
+            Side::White => ((pawns & self.not_a_file.0) << 7) | ((pawns & self.not_h_file.0) << 9),
+            Side::Black => ((pawns & self.not_a_file.0) >> 9) | ((pawns & self.not_h_file.0) >> 7),
+        })
     }
 
-    /// Adds a score for each unit on the board.
-    /// Optionally adds a score for king position if opponent has a queen.
-    /// Returns side-to-move's score minus opponent's score.
-    pub fn evaluate(&self) -> i32 {
-        let mut score = [0, 0];
+    /// Mobility bonus for a piece's `attacks`, counting only the `safe` squares
+    fn evaluate_mobility(&self, piece: Piece, attacks: BitBoard, safe: BitBoard) -> TaperedScore {
+        self.eval_params.mobility[piece as usize] * (attacks.0 & safe.0).count_ones() as i32
+    }
 
-        let mut queenside_pawns = [0, 0];
-        let mut kingside_pawns = [0, 0];
+    /// Penalty for the danger to `side`'s king. The opponent collects attack units for:
+    /// - Squares its pieces attack around the king, once at least `KING_ZONE_MIN_ATTACKERS`
+    ///   pieces join in
+    /// - Open and half-open files on and beside the king
+    /// - Its pawns storming those files
+    /// - Checks it can give on squares `side` doesn't cover
+    ///
+    /// `king_safety_table` turns the units into a midgame penalty.
+    fn evaluate_king_safety(&self, side: Side, attacks: &[AttackInfo; NUM_SIDES]) -> TaperedScore {
+        let params = &*self.eval_params;
+        let side_ = side as usize;
+        let opponent = side.opponent() as usize;
+        let attacker = &attacks[opponent];
 
-        for side in Side::iter() {
-            let side_ = side as usize;
+        let mut units = if attacker.king_attackers >= KING_ZONE_MIN_ATTACKERS {
+            attacker.king_attack_units
+        } else {
+            0
+        };
 
-            // Add material score
-            score[side_] += self.traditional_material_score[side_] as i32;
+        let king_square = self.board.bit_pieces[side_][Piece::King as usize].next_bit() as usize;
+        let own_pawns = self.board.bit_pieces[side_][Piece::Pawn as usize].0;
+        let enemy_pawns = self.board.bit_pieces[opponent][Piece::Pawn as usize].0;
 
-            // Pawns
-            let mut pawns = self.board.bit_pieces[side_][Piece::Pawn as usize];
+        // Files on and beside the king
+        let king_column = COLUMN[king_square] as usize;
 
-            while pawns.0 != 0 {
-                let pawn_square = pawns.next_bit_mut();
+        for column in king_column.saturating_sub(1)..=(king_column + 1).min(7) {
+            let file = self.mask_column[king_square - king_column + column].0;
 
-                score[side_] += self.square_score[side_][Piece::Pawn as usize]
-                    [pawn_square as usize]
-                    + self.evaluate_pawn(
-                        side,
-                        pawn_square
-                            .try_into()
-                            .expect("Failed to convert pawn u8 to Square"),
-                        &mut kingside_pawns[side_],
-                        &mut queenside_pawns[side_],
-                    );
+            if file & own_pawns == 0 {
+                units += if file & enemy_pawns == 0 {
+                    params.king_open_file_units
+                } else {
+                    params.king_semi_open_file_units
+                };
             }
 
-            // Knights
-            let mut knights = self.board.bit_pieces[side_][Piece::Knight as usize];
+            let mut storming_pawns = BitBoard(file & enemy_pawns);
 
-            while knights.0 != 0 {
-                let knight_square = knights.next_bit_mut();
+            while storming_pawns.0 != 0 {
+                let pawn_square = storming_pawns.next_bit_mut();
+                units += params.pawn_storm_units[self.ranks[side_][pawn_square as usize] as usize];
+            }
+        }
 
-                score[side_] +=
-                    self.square_score[side_][Piece::Knight as usize][knight_square as usize];
+        // Safe checks
+        let safe = !attacks[side_].all & !self.board.bit_units[opponent].0;
+        let knight_checks = self.bit_knight_moves[king_square].0;
+        let bishop_checks = self
+            .sliding_attacks
+            .bishop(king_square as u8, self.board.bit_all)
+            .0;
+        let rook_checks = self
+            .sliding_attacks
+            .rook(king_square as u8, self.board.bit_all)
+            .0;
+
+        for (piece, checks) in [
+            (Piece::Knight, knight_checks),
+            (Piece::Bishop, bishop_checks),
+            (Piece::Rook, rook_checks),
+            (Piece::Queen, bishop_checks | rook_checks),
+        ] {
+            if attacker.by_piece[piece as usize] & checks & safe != 0 {
+                units += params.safe_check_units[piece as usize];
             }
+        }
 
-            // Bishops
-            let mut bishops = self.board.bit_pieces[side_][Piece::Bishop as usize];
+        let table = &params.king_safety_table;
+        let penalty = table[(units as usize).min(table.len() - 1)];
 
-            while bishops.0 != 0 {
-                let bishop_square = bishops.next_bit_mut();
+        TaperedScore::new(-penalty, 0)
+    }
 
-                score[side_] +=
-                    self.square_score[side_][Piece::Bishop as usize][bishop_square as usize];
-            }
+    /// How much non-pawn material is left, from `MAX_PHASE` in the opening down to 0 when only
+    /// kings and pawns remain. Promotions can't take it past `MAX_PHASE`.
+    pub fn game_phase(&self) -> i32 {
+        let phase: i32 = [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+            .into_iter()
+            .map(|piece| {
+                let count = self.board.bit_pieces[Side::White as usize][piece as usize]
+                    .0
+                    .count_ones()
+                    + self.board.bit_pieces[Side::Black as usize][piece as usize]
+                        .0
+                        .count_ones();
+
+                count as i32 * PHASE_WEIGHTS[piece as usize]
+            })
+            .sum();
+
+        phase.min(MAX_PHASE)
+    }
 
-            // Rooks
-            let mut rooks = self.board.bit_pieces[side_][Piece::Rook as usize];
+    /// Evaluate with `params` from now on (the handcrafted evaluation only)
+    pub fn set_eval_params(&mut self, params: Arc<EvalParams>) {
+        (self.square_score, self.passed_pawns_score) = Board::get_score_tables(&params);
+        self.eval_params = params;
+        self.board.hash.clear_pawn_table(); // Scored with the old parameters
+    }
 
-            while rooks.0 != 0 {
-                let rook_square = rooks.next_bit_mut();
+    pub fn eval_params(&self) -> &Arc<EvalParams> {
+        &self.eval_params
+    }
 
-                score[side_] += self.square_score[side_][Piece::Rook as usize]
-                    [rook_square as usize]
-                    + self.evaluate_rook(
-                        side,
-                        rook_square
-                            .try_into()
-                            .expect("Failed to convert rook u8 to Square"),
-                    );
-            }
+    /// Probe `tablebases` in the search from now on (see `probe_wdl`), or stop probing with `None`
+    pub fn set_tablebases(&mut self, tablebases: Option<Arc<Tablebases>>) {
+        self.tablebases = tablebases;
+    }
 
-            // Queens (can be multiple after promotions)
-            let mut queens = self.board.bit_pieces[side_][Piece::Queen as usize];
+    pub fn tablebases(&self) -> Option<Arc<Tablebases>> {
+        self.tablebases.clone()
+    }
 
-            while queens.0 != 0 {
-                let queen_square = queens.next_bit_mut();
+    /// Uses the loaded network if there is one (see `Board::set_network`), otherwise the
+    /// handcrafted evaluation.
+    /// Returns side-to-move's score minus opponent's score.
+    pub fn evaluate(&self) -> i32 {
+        match &self.board.nnue {
+            Some(nnue) => nnue.evaluate(self.side),
+            None => self.evaluate_handcrafted(),
+        }
+    }
 
-                score[side_] +=
-                    self.square_score[side_][Piece::Queen as usize][queen_square as usize];
-            }
+    /// Adds a midgame and an endgame score for each unit on the board, then blends the two by
+    /// `game_phase()` so the evaluation changes smoothly as pieces come off.
+    /// Pieces also score for mobility, pawn structure and coordination (bishop pair, knight
+    /// outposts, rooks on the 7th and connected rooks).
+    /// Each king is penalised for the attacks against it (see `evaluate_king_safety`).
+    /// The pawn shield in front of a castled king only counts in the midgame.
+    /// Returns side-to-move's score minus opponent's score.
+    pub fn evaluate_handcrafted(&self) -> i32 {
+        let mut score = [TaperedScore::default(); NUM_SIDES];
 
-            // King
-            let king_square = self.board.bit_pieces[side_][Piece::King as usize].next_bit();
+        self.pawn_hash_probes.set(self.pawn_hash_probes.get() + 1);
 
-            if self.board.bit_pieces[side.opponent() as usize][Piece::Queen as usize].0 == 0 {
-                score[side_] += self.king_endgame_score[side_][king_square as usize]
-            } else if self.board.bit_pieces[side_][Piece::King as usize].0 & self.mask_kingside.0
-                != 0
-            {
-                score[side_] += kingside_pawns[side_]
-            } else if self.board.bit_pieces[side_][Piece::King as usize].0 & self.mask_queenside.0
-                != 0
-            {
-                score[side_] += queenside_pawns[side_]
+        let pawns = match self.board.hash.probe_pawns() {
+            Some(pawns) => {
+                self.pawn_hash_hits.set(self.pawn_hash_hits.get() + 1);
+                pawns
             }
-        }
+            None => {
+                let pawns = self.evaluate_pawn_structure(&mut |_, _, _| {});
+                self.board.hash.store_pawns(&pawns);
+                pawns
+            }
+        };
+
+        score[Side::White as usize] += pawns.score[Side::White as usize];
+        score[Side::Black as usize] += pawns.score[Side::Black as usize];
+
+        self.evaluate_terms(&pawns, |side, _, term_score| {
+            score[side as usize] += term_score
+        });
 
-        let total = score[Side::White as usize] - score[Side::Black as usize];
+        let total =
+            (score[Side::White as usize] - score[Side::Black as usize]).taper(self.game_phase());
 
         match self.side {
             Side::White => total,
@@ -1718,32 +2117,307 @@ impl Position {
         }
     }
 
-    /// Quiescence search extends the regular search by only examining
-    /// capturing moves until the position becomes "quiet" (no more captures).
-    /// This avoids the horizon effect where the engine stops searching
-    /// in the middle of a tactical sequence. This function is recursive.
-    ///
-    /// # Arguments
-    /// * `alpha` - The best score the maximizing player can guarantee
-    /// * `beta` - The best score the minimizing player can guarantee
-    ///
-    /// # Returns
-    /// The best score achievable from this position with optimal play
-    /// from both sides, bounded by the alpha-beta window.
-    ///
-    /// # Algorithm
-    /// 1. Evaluate the current position (stand pat)
-    /// 2. Check for beta cutoff (position too good)
-    /// 3. Update alpha if stand pat improves it
-    /// 4. Generate and search all capture moves recursively until the position is quiet (base case)
-    /// 5. Apply alpha-beta pruning to reduce search space
+    /// The handcrafted evaluation broken down by term and side (with or without a network
+    /// loaded)
+    pub fn evaluate_with_trace(&self) -> EvalTrace {
+        let mut trace = EvalTrace {
+            terms: [[TaperedScore::default(); NUM_SIDES]; EvalTerm::COUNT],
+            phase: self.game_phase(),
+            side_to_move: self.side,
+        };
+
+        let mut add = |side: Side, term: EvalTerm, term_score| {
+            trace.terms[term as usize][side as usize] += term_score;
+        };
+
+        // Not from the pawn hash table, which only has the totals
+        let pawns = self.evaluate_pawn_structure(&mut add);
+        self.evaluate_terms(&pawns, add);
+
+        trace
+    }
+
+    /// Passes the pawn structure terms to `add`, returning their totals and the pawn shields for
+    /// the pawn hash table
+    fn evaluate_pawn_structure(
+        &self,
+        add: &mut impl FnMut(Side, EvalTerm, TaperedScore),
+    ) -> PawnEntry {
+        let mut pawns = PawnEntry::default();
+
+        for side in Side::iter() {
+            let side_ = side as usize;
+            let mut side_pawns = self.board.bit_pieces[side_][Piece::Pawn as usize];
+
+            while side_pawns.0 != 0 {
+                let pawn_square = side_pawns.next_bit_mut();
+
+                self.evaluate_pawn(
+                    side,
+                    pawn_square
+                        .try_into()
+                        .expect("Failed to convert pawn u8 to Square"),
+                    &mut pawns.kingside_shield[side_],
+                    &mut pawns.queenside_shield[side_],
+                    &mut |side, term, term_score| {
+                        pawns.score[side as usize] += term_score;
+                        add(side, term, term_score);
+                    },
+                );
+            }
+        }
+
+        pawns
+    }
+
+    /// Passes each contribution to the handcrafted evaluation, other than the pawn structure
+    /// terms in `pawn_structure`, to `add`, for `evaluate_handcrafted` to sum or `evaluate_with_trace`
+    /// to record
+    fn evaluate_terms(
+        &self,
+        pawn_structure: &PawnEntry,
+        mut add: impl FnMut(Side, EvalTerm, TaperedScore),
+    ) {
+        let params = &*self.eval_params;
+
+        let king_zones = [Side::White, Side::Black].map(|side| {
+            let king_square = self.board.bit_pieces[side as usize][Piece::King as usize].next_bit();
+            self.bit_king_moves[king_square as usize].0 | 1 << king_square
+        });
+
+        let mut attacks = [AttackInfo::default(), AttackInfo::default()];
+
+        for side in Side::iter() {
+            let side_ = side as usize;
+            let enemy_king_zone = king_zones[side.opponent() as usize];
+
+            // Add material score
+            let material = self.traditional_material_score[side_] as i32;
+            let mut material_score = TaperedScore::new(material, material);
+
+            for piece in [
+                Piece::Pawn,
+                Piece::Knight,
+                Piece::Bishop,
+                Piece::Rook,
+                Piece::Queen,
+            ] {
+                let count = self.board.bit_pieces[side_][piece as usize].0.count_ones() as i32;
+                material_score += params.piece_value[piece as usize] * count;
+            }
+
+            add(side, EvalTerm::Material, material_score);
+
+            // Pawns
+            let mut pawns = self.board.bit_pieces[side_][Piece::Pawn as usize];
+
+            while pawns.0 != 0 {
+                let pawn_square = pawns.next_bit_mut();
+
+                add(
+                    side,
+                    EvalTerm::PawnSquares,
+                    self.square_score[side_][Piece::Pawn as usize][pawn_square as usize],
+                );
+            }
+
+            attacks[side_].add(
+                Piece::Pawn,
+                self.pawn_attacks(side),
+                enemy_king_zone,
+                params,
+            );
+
+            // Squares worth counting for mobility
+            let safe =
+                BitBoard(!self.board.bit_units[side_].0 & !self.pawn_attacks(side.opponent()).0);
+
+            // Knights
+            let mut knights = self.board.bit_pieces[side_][Piece::Knight as usize];
+
+            while knights.0 != 0 {
+                let knight_square = knights.next_bit_mut();
+                let knight_attacks = self.bit_knight_moves[knight_square as usize];
+
+                attacks[side_].add(Piece::Knight, knight_attacks, enemy_king_zone, params);
+
+                add(
+                    side,
+                    EvalTerm::KnightSquares,
+                    self.square_score[side_][Piece::Knight as usize][knight_square as usize],
+                );
+                add(
+                    side,
+                    EvalTerm::KnightOutposts,
+                    self.evaluate_knight(
+                        side,
+                        knight_square
+                            .try_into()
+                            .expect("Failed to convert knight u8 to Square"),
+                    ),
+                );
+                add(
+                    side,
+                    EvalTerm::Mobility,
+                    self.evaluate_mobility(Piece::Knight, knight_attacks, safe),
+                );
+            }
+
+            // Bishops
+            let mut bishops = self.board.bit_pieces[side_][Piece::Bishop as usize];
+
+            if bishops.0.count_ones() >= 2 {
+                add(side, EvalTerm::BishopPair, params.bishop_pair);
+            }
+
+            while bishops.0 != 0 {
+                let bishop_square = bishops.next_bit_mut();
+                let bishop_attacks = self
+                    .sliding_attacks
+                    .bishop(bishop_square, self.board.bit_all);
+
+                attacks[side_].add(Piece::Bishop, bishop_attacks, enemy_king_zone, params);
+
+                add(
+                    side,
+                    EvalTerm::BishopSquares,
+                    self.square_score[side_][Piece::Bishop as usize][bishop_square as usize],
+                );
+                add(
+                    side,
+                    EvalTerm::Mobility,
+                    self.evaluate_mobility(Piece::Bishop, bishop_attacks, safe),
+                );
+            }
+
+            // Rooks
+            let mut rooks = self.board.bit_pieces[side_][Piece::Rook as usize];
+            let mut connected_rooks = false;
+
+            while rooks.0 != 0 {
+                let rook_square = rooks.next_bit_mut();
+                let rook_attacks = self.sliding_attacks.rook(rook_square, self.board.bit_all);
+
+                attacks[side_].add(Piece::Rook, rook_attacks, enemy_king_zone, params);
+
+                connected_rooks |=
+                    rook_attacks.0 & self.board.bit_pieces[side_][Piece::Rook as usize].0 != 0;
+
+                add(
+                    side,
+                    EvalTerm::RookSquares,
+                    self.square_score[side_][Piece::Rook as usize][rook_square as usize],
+                );
+                self.evaluate_rook(
+                    side,
+                    rook_square
+                        .try_into()
+                        .expect("Failed to convert rook u8 to Square"),
+                    &mut add,
+                );
+                add(
+                    side,
+                    EvalTerm::Mobility,
+                    self.evaluate_mobility(Piece::Rook, rook_attacks, safe),
+                );
+            }
+
+            if connected_rooks {
+                add(side, EvalTerm::ConnectedRooks, params.connected_rooks);
+            }
+
+            // Queens (can be multiple after promotions)
+            let mut queens = self.board.bit_pieces[side_][Piece::Queen as usize];
+
+            while queens.0 != 0 {
+                let queen_square = queens.next_bit_mut();
+                let queen_attacks = self.sliding_attacks.queen(queen_square, self.board.bit_all);
+
+                attacks[side_].add(Piece::Queen, queen_attacks, enemy_king_zone, params);
+
+                add(
+                    side,
+                    EvalTerm::QueenSquares,
+                    self.square_score[side_][Piece::Queen as usize][queen_square as usize],
+                );
+                add(
+                    side,
+                    EvalTerm::Mobility,
+                    self.evaluate_mobility(Piece::Queen, queen_attacks, safe),
+                );
+            }
+
+            // King
+            let king = self.board.bit_pieces[side_][Piece::King as usize];
+
+            attacks[side_].add(
+                Piece::King,
+                self.bit_king_moves[king.next_bit() as usize],
+                enemy_king_zone,
+                params,
+            );
+
+            add(
+                side,
+                EvalTerm::KingSquares,
+                self.square_score[side_][Piece::King as usize][king.next_bit() as usize],
+            );
+
+            let pawn_shield = if king.0 & self.mask_kingside.0 != 0 {
+                pawn_structure.kingside_shield[side_]
+            } else if king.0 & self.mask_queenside.0 != 0 {
+                pawn_structure.queenside_shield[side_]
+            } else {
+                0
+            };
+
+            add(
+                side,
+                EvalTerm::KingShelter,
+                TaperedScore::new(pawn_shield, 0),
+            );
+        }
+
+        // King safety needs both sides' attacks
+        for side in Side::iter() {
+            add(
+                side,
+                EvalTerm::KingSafety,
+                self.evaluate_king_safety(side, &attacks),
+            );
+        }
+    }
+
+    /// Quiescence search extends the regular search by only examining
+    /// capturing moves until the position becomes "quiet" (no more captures).
+    /// This avoids the horizon effect where the engine stops searching
+    /// in the middle of a tactical sequence. This function is recursive.
+    ///
+    /// # Arguments
+    /// * `alpha` - The best score the maximizing player can guarantee
+    /// * `beta` - The best score the minimizing player can guarantee
+    ///
+    /// # Returns
+    /// The best score achievable from this position with optimal play
+    /// from both sides, bounded by the alpha-beta window, or `SearchAbort`
+    /// if the search was stopped (the position is left mid-line; see `Engine::think`).
+    ///
+    /// # Algorithm
+    /// 1. Evaluate the current position (stand pat), unless in check
+    /// 2. Check for beta cutoff (position too good)
+    /// 3. Update alpha if stand pat improves it
+    /// 4. Generate and search all capture moves recursively until the position is quiet (base case).
+    ///    In check, search every evasion instead; with none, it's checkmate. In the first
+    ///    `QUIESCENCE_CHECK_PLIES`, also search quiet moves that give check.
+    /// 5. Skip captures that can't raise alpha even with a margin (delta pruning)
+    /// 6. Apply alpha-beta pruning to reduce search space
     pub fn quiescence_search(
         &mut self,
         mut alpha: i32,
         beta: i32,
         depth: u16,
         max_nodes: Option<usize>,
-    ) -> i32 {
+    ) -> Result<i32, SearchAbort> {
         self.nodes += 1;
         self.qnodes += 1;
 
@@ -1752,34 +2426,95 @@ impl Position {
         if let Some(max) = max_nodes
             && self.nodes >= max
         {
-            panic!("NodeLimitReached");
+            self.time_manager.stop();
+            return Err(SearchAbort::NodeLimitReached);
         }
 
-        if depth == 0 {
-            return self.evaluate();
+        // Out of room in the ply-indexed tables
+        if depth == 0 || self.ply >= MAX_PLY - 1 {
+            return Ok(self.evaluate());
         }
 
         if self.nodes & 1023 == 0 {
-            self.check_if_time_is_exhausted();
+            self.check_if_search_should_stop()?;
+        }
+
+        // Any stored entry was searched at least as deeply as quiescence
+        let hash_entry = self.probe_hash();
+
+        if let Some(entry) = hash_entry
+            && let Some(score) = entry.cutoff_score(0, alpha, beta)
+        {
+            return Ok(score_from_hash(score, self.ply));
         }
 
-        let mut best_score = self.evaluate();
+        let in_check = self.is_in_check();
 
-        if best_score >= beta {
-            return best_score;
+        // Standing pat isn't an option in check: the king has to be dealt with
+        let stand_pat = match in_check {
+            true => -INFINITY_SCORE,
+            false => self.evaluate(),
+        };
+
+        if stand_pat >= beta {
+            return Ok(stand_pat);
         }
 
+        let mut best_score = stand_pat;
+        let original_alpha = alpha;
         alpha = alpha.max(best_score);
 
-        self.generate_captures(self.side);
+        let search_quiet_checks = !in_check
+            && DEFAULT_MAX_QUIESCENCE_DEPTH.saturating_sub(depth) < QUIESCENCE_CHECK_PLIES;
+
+        match in_check || search_quiet_checks {
+            true => self.generate_moves_and_captures(self.side, |_, _, _| 0),
+            false => self.generate_captures(self.side),
+        }
+
+        self.score_hash_move(hash_entry.and_then(|entry| entry.best_move));
+
         let move_list_start = self.first_move[self.ply] as usize;
         let move_list_end = self.first_move[self.ply + 1] as usize;
 
-        // Search all captures
+        let mut best_move = None;
+        let mut legal_moves_count = 0;
+
+        // Search all captures (or all evasions)
         for move_index in move_list_start..move_list_end {
             self.sort(move_index as isize);
 
             let current_move = self.move_list[move_index].unwrap();
+            let is_capture = self.is_capture(current_move);
+
+            // Sorted last, the rest are captures that lose material (negative SEE). Skip them.
+            if !in_check && current_move.score < 0 {
+                break;
+            }
+
+            // Delta pruning: even winning the piece for free (plus a margin) leaves us below alpha
+            if !in_check && is_capture && current_move.promote.is_none() {
+                let optimistic_score = stand_pat
+                    + match self.board.value[current_move.to as usize] {
+                        Piece::Empty => Piece::Pawn.value(), // En passant
+                        victim => victim.value(),
+                    }
+                    + DELTA_PRUNING_MARGIN;
+
+                if optimistic_score <= alpha {
+                    best_score = best_score.max(optimistic_score);
+                    continue;
+                }
+            }
+
+            // Out of check, quiet moves are only searched for the check they give
+            if !in_check
+                && !is_capture
+                && current_move.promote.is_none()
+                && !self.gives_check(current_move)
+            {
+                continue;
+            }
 
             // Try to make the move
             if !self.make_move(current_move.from, current_move.to, current_move.promote) {
@@ -1787,27 +2522,167 @@ impl Position {
                 continue;
             }
 
-            let score = -self.quiescence_search(-beta, -alpha, depth - 1, max_nodes);
+            legal_moves_count += 1;
+
+            let score = -self.quiescence_search(-beta, -alpha, depth - 1, max_nodes)?;
 
             self.take_back_move();
 
             // Fail-high cutoff
             if score >= beta {
-                return score;
+                self.store_hash(current_move, 0, score, HashFlag::LowerBound);
+                return Ok(score);
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(current_move);
             }
 
-            best_score = best_score.max(score);
             alpha = alpha.max(score);
         }
 
-        best_score
+        if in_check && legal_moves_count == 0 {
+            // Checkmate - prefer shorter mates
+            return Ok(-MATE_SCORE + self.ply as i32);
+        }
+
+        // Standing pat leaves no move to store
+        if let Some(best_move) = best_move {
+            let flag = match best_score > original_alpha {
+                true => HashFlag::Exact,
+                false => HashFlag::UpperBound,
+            };
+
+            self.store_hash(best_move, 0, best_score, flag);
+        }
+
+        Ok(best_score)
+    }
+
+    /// Look up the current position in the transposition table
+    fn probe_hash(&mut self) -> Option<HashEntry> {
+        let entry = self.board.hash.probe();
+
+        if entry.is_some() {
+            self.hash_hits += 1;
+        }
+
+        entry
+    }
+
+    /// Store the result of searching the current position in the transposition table
+    fn store_hash(&mut self, best_move: Move, depth: u16, score: i32, flag: HashFlag) {
+        self.board.hash.store_move(
+            best_move,
+            depth.min(u8::MAX as u16) as u8,
+            score_to_hash(score, self.ply),
+            flag,
+        );
+
+        self.hash_stores += 1;
+    }
+
+    /// Boost the move from the transposition table so it is searched first
+    fn score_hash_move(&mut self, hash_move: Option<Move>) {
+        let Some(hash_move) = hash_move else {
+            return;
+        };
+
+        for i in self.first_move[self.ply]..self.first_move[self.ply + 1] {
+            if let Some(move_) = self.move_list[i as usize].as_mut()
+                && move_.from == hash_move.from
+                && move_.to == hash_move.to
+                && move_.promote == hash_move.promote
+            {
+                move_.score += HASH_SCORE as isize;
+                break;
+            }
+        }
+    }
+
+    /// Order this ply's killer moves and the countermove to the previous move ahead of other quiet moves
+    fn score_killers_and_countermove(&mut self) {
+        let killers = self.killer_moves[self.ply];
+        let countermove = self
+            .previous_move()
+            .and_then(|previous| self.countermoves[previous.from as usize][previous.to as usize]);
+
+        if killers == [None, None] && countermove.is_none() {
+            return;
+        }
+
+        for i in self.first_move[self.ply]..self.first_move[self.ply + 1] {
+            if let Some(move_) = self.move_list[i as usize].as_mut()
+                && move_.promote.is_none()
+                && move_.to.as_bit() & self.board.bit_all.0 == 0
+            {
+                let move_data = Some(MoveData {
+                    from: move_.from,
+                    to: move_.to,
+                    promote: None,
+                });
+
+                if move_data == killers[0] {
+                    move_.score += KILLER_SCORE[0] as isize;
+                } else if move_data == killers[1] {
+                    move_.score += KILLER_SCORE[1] as isize;
+                } else if move_data == countermove {
+                    move_.score += COUNTERMOVE_SCORE as isize;
+                }
+            }
+        }
+    }
+
+    /// The move that led to this position, unless it was a null move or came before the game was loaded
+    fn previous_move(&self) -> Option<Game> {
+        self.game_list[self.ply_from_start_of_game].filter(|game| game.from != game.to)
+    }
+
+    /// Remember a quiet move that caused a beta cutoff as a killer for this ply and as the
+    /// countermove to the previous move
+    fn store_killer_and_countermove(&mut self, move_: Move) {
+        let move_data = Some(MoveData {
+            from: move_.from,
+            to: move_.to,
+            promote: move_.promote,
+        });
+
+        let killers = &mut self.killer_moves[self.ply];
+
+        if killers[0] != move_data {
+            killers[1] = killers[0];
+            killers[0] = move_data;
+        }
+
+        if let Some(previous) = self.previous_move() {
+            self.countermoves[previous.from as usize][previous.to as usize] = move_data;
+        }
+    }
+
+    /// Forget killers and countermoves, which only apply to the search that found them
+    pub fn clear_killers_and_countermoves(&mut self) {
+        self.killer_moves = [[None; 2]; MAX_PLY];
+        self.countermoves = [[None; NUM_SQUARES]; NUM_SQUARES];
+    }
+
+    /// Nodes searched by all threads in the current search
+    pub fn total_nodes(&self) -> usize {
+        self.nodes + self.helper_nodes
     }
 
-    fn check_if_time_is_exhausted(&mut self) {
+    /// Called periodically during search to abort once time is up or a stop was requested
+    fn check_if_search_should_stop(&mut self) -> Result<(), SearchAbort> {
+        if self.time_manager.is_stopped() {
+            return Err(SearchAbort::Stopped);
+        }
+
         if self.time_manager.is_hard_limit_reached() {
-            self.time_manager.stopped = true;
-            panic!("TimeExhausted");
+            self.time_manager.stop();
+            return Err(SearchAbort::TimeExhausted);
         }
+
+        Ok(())
     }
 
     /// Incrementally sort the move list by selecting the best move from the
@@ -1878,18 +2753,40 @@ impl Position {
     ) -> i32 {
         self.beta_cutoffs += 1;
 
+        // A fail-high at the root (outside the aspiration window) reports the move that caused it
+        if self.ply == 0 {
+            self.update_principal_variation(current_move);
+        }
+
+        self.store_hash(current_move, depth, score, HashFlag::LowerBound);
+
         let is_capture = current_move.to.as_bit() & self.board.bit_all.0 != 0;
         let is_promotion = current_move.promote.is_some();
 
-        // Only update history table for quiet moves
+        // Only update history table, killers and countermoves for quiet moves
         if !is_capture && !is_promotion {
             self.update_history_table(history_table, depth, current_move);
+            self.store_killer_and_countermove(current_move);
         }
 
         // Fail-soft (return the score that caused the cutoff instead of beta)
         score
     }
 
+    /// Whether a root move is left out of the search by `excluded_root_moves` or `root_search_moves`
+    fn is_skipped_root_move(&self, move_: Move) -> bool {
+        if self.ply > 0 {
+            return false;
+        }
+
+        let matches = |other: &MoveData| {
+            other.from == move_.from && other.to == move_.to && other.promote == move_.promote
+        };
+
+        self.excluded_root_moves.iter().any(matches)
+            || (!self.root_search_moves.is_empty() && !self.root_search_moves.iter().any(matches))
+    }
+
     fn update_principal_variation(&mut self, best_move: Move) {
         self.pv_table[self.ply][self.ply] = Some(best_move);
 
@@ -1914,6 +2811,9 @@ impl Position {
     ///
     /// Alpha is the lower bound (player's best guaranteed score).
     /// Beta is the upper bound (opponent's best guaranteed score).
+    ///
+    /// Returns `Err(SearchAbort)` as soon as the stop signal is raised, without
+    /// taking back the moves made along the current line.
     pub fn search(
         &mut self,
         mut alpha: i32,
@@ -1921,13 +2821,14 @@ impl Position {
         mut depth: u16,
         history_table: &mut [[[isize; NUM_SQUARES]; NUM_SQUARES]; NUM_SIDES],
         max_nodes: Option<usize>,
-    ) -> i32 {
+    ) -> Result<i32, SearchAbort> {
         self.nodes += 1;
 
         if let Some(max) = max_nodes
             && self.nodes >= max
         {
-            panic!("NodeLimitReached");
+            self.time_manager.stop();
+            return Err(SearchAbort::NodeLimitReached);
         }
 
         self.max_depth_reached = self.max_depth_reached.max(self.ply);
@@ -1936,7 +2837,7 @@ impl Position {
             || self.has_insufficient_material()
         {
             self.pv_length[self.ply] = self.ply; // Truncate PV at this point
-            return 0;
+            return Ok(0);
         }
 
         if depth == 0 {
@@ -1944,8 +2845,14 @@ impl Position {
             return self.quiescence_search(alpha, beta, DEFAULT_MAX_QUIESCENCE_DEPTH, max_nodes);
         }
 
+        // Out of room in the ply-indexed tables
+        if self.ply >= MAX_PLY - 1 {
+            self.pv_length[self.ply] = self.ply;
+            return Ok(self.evaluate());
+        }
+
         if self.nodes & 1023 == 0 {
-            self.check_if_time_is_exhausted();
+            self.check_if_search_should_stop()?;
         }
 
         let in_check = self.is_in_check();
@@ -1956,23 +2863,164 @@ impl Position {
 
         self.pv_length[self.ply] = self.ply;
 
+        let hash_entry = self.probe_hash();
+
+        // Only take cutoffs in null-window nodes below the root so the PV stays intact
+        if self.ply > 0
+            && beta - alpha == 1
+            && let Some(entry) = hash_entry
+            && let Some(score) = entry.cutoff_score(depth.min(u8::MAX as u16) as u8, alpha, beta)
+        {
+            return Ok(score_from_hash(score, self.ply));
+        }
+
+        // Tablebase result. Only right after a capture or pawn move, since the tables don't know
+        // how close the fifty-move rule is. A win only bounds the score from below (a mate may be
+        // faster) and a loss from above, so those return only outside the window.
+        let mut tablebase_bound = None;
+
+        if self.ply > 0
+            && self.fifty == 0
+            && let Some(wdl) = self.probe_wdl()
+        {
+            self.tb_hits += 1;
+
+            let score = match wdl {
+                Wdl::Win => TB_WIN_SCORE - self.ply as i32,
+                Wdl::Loss => -TB_WIN_SCORE + self.ply as i32,
+                _ => 0,
+            };
+
+            let is_cutoff = match wdl {
+                Wdl::Win => score >= beta,
+                Wdl::Loss => score <= alpha,
+                _ => true,
+            };
+
+            if is_cutoff {
+                return Ok(score);
+            }
+
+            tablebase_bound = Some((wdl, score));
+        }
+
+        let is_null_window = beta - alpha == 1;
+
+        // The frontier prunings below trust the static evaluation, so skip them where it means
+        // little: in check, at PV nodes, and when a mate score is at stake
+        let static_eval = (self.ply > 0 && is_null_window && !in_check).then(|| self.evaluate());
+
+        // Reverse futility pruning: so far above beta that a few plies won't bring it back down
+        if let Some(eval) = static_eval
+            && beta.abs() < MATE_THRESHOLD
+            && let Some(&margin) = REVERSE_FUTILITY_MARGIN.get(depth as usize)
+            && eval - margin >= beta
+        {
+            return Ok(eval);
+        }
+
+        // Razoring: so far below alpha that only a capture could help, so let quiescence decide
+        if let Some(eval) = static_eval
+            && alpha.abs() < MATE_THRESHOLD
+            && let Some(&margin) = RAZORING_MARGIN.get(depth as usize)
+            && eval + margin < alpha
+        {
+            let score =
+                self.quiescence_search(alpha, beta, DEFAULT_MAX_QUIESCENCE_DEPTH, max_nodes)?;
+
+            if score <= alpha {
+                return Ok(score);
+            }
+        }
+
+        // Null-move pruning: if passing the turn still fails high after a reduced search,
+        // a real move almost certainly would too
+        if let Some(eval) = static_eval
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && beta.abs() < MATE_THRESHOLD
+            && !self.is_after_null_move()
+            && self.has_non_pawn_material(self.side)
+            && eval >= beta
+        {
+            let reduction = NULL_MOVE_REDUCTION + depth / NULL_MOVE_REDUCTION_DEPTH_DIVISOR;
+
+            // The child generates its moves where this node's would start
+            self.first_move[self.ply + 1] = self.first_move[self.ply];
+
+            self.make_null_move();
+            let null_move_ply = self.ply;
+
+            let search_result = self.search(
+                -beta,
+                -beta + 1,
+                depth.saturating_sub(1 + reduction),
+                history_table,
+                max_nodes,
+            );
+
+            // An aborted search leaves its line on the board
+            while self.ply > null_move_ply {
+                self.take_back_move();
+            }
+            self.take_back_null_move();
+
+            let score = -search_result?;
+
+            if score >= beta {
+                // Don't trust mate scores from a position the opponent couldn't reach
+                return Ok(if score >= MATE_THRESHOLD { beta } else { score });
+            }
+        }
+
+        let original_alpha = alpha;
         let mut best_score = -INFINITY_SCORE;
+        let mut best_move = None;
+        let mut max_score = INFINITY_SCORE;
+
+        match tablebase_bound {
+            Some((Wdl::Win, score)) => {
+                best_score = score;
+                alpha = alpha.max(score);
+            }
+            Some((_, score)) => max_score = score,
+            None => {}
+        }
 
         self.generate_moves_and_captures(self.side, |side, from, to| {
             history_table[side as usize][from as usize][to as usize]
         });
 
+        self.score_killers_and_countermove();
+        self.score_hash_move(hash_entry.and_then(|entry| entry.best_move));
+
         let move_list_start = self.first_move[self.ply] as usize;
         let move_list_end = self.first_move[self.ply + 1] as usize;
 
         let mut legal_moves_count = 0;
+        let mut quiet_moves_count = 0;
         let mut is_pv_node = true;
 
+        let can_prune_late_moves =
+            self.ply > 0 && is_null_window && !in_check && depth <= LMP_MAX_DEPTH;
+
+        // Futility pruning: quiet moves can't lift a score this far below alpha back above it
+        let futility_score = static_eval
+            .filter(|_| alpha.abs() < MATE_THRESHOLD)
+            .zip(FUTILITY_MARGIN.get(depth as usize))
+            .map(|(eval, margin)| eval + margin)
+            .filter(|&futility_score| futility_score <= alpha);
+
         for move_index in move_list_start..move_list_end {
             self.sort(move_index as isize);
 
             let current_move = self.move_list[move_index].expect("Found empty Move");
 
+            if self.is_skipped_root_move(current_move) {
+                continue;
+            }
+
+            let is_quiet = !self.is_capture(current_move) && current_move.promote.is_none();
+
             if !self.make_move(current_move.from, current_move.to, current_move.promote) {
                 // Move is illegal (leaves king in check)
                 continue;
@@ -1980,34 +3028,91 @@ impl Position {
 
             legal_moves_count += 1;
 
-            let score: i32;
+            // Late move pruning: quiet moves this far down the history ordering rarely help at
+            // shallow depth. Only once a move has been found that doesn't get mated, and never
+            // for checks.
+            if can_prune_late_moves
+                && is_quiet
+                && best_score > -MATE_THRESHOLD
+                && quiet_moves_count >= LMP_BASE_MOVES + (depth * depth) as usize
+                && !self.is_in_check()
+            {
+                self.take_back_move();
+                continue;
+            }
+
+            // Futile quiet moves are skipped on the same terms, counting as the score they can't
+            // beat so the node's upper bound stays sound
+            if let Some(futility_score) = futility_score
+                && is_quiet
+                && best_score > -MATE_THRESHOLD
+                && !self.is_in_check()
+            {
+                self.take_back_move();
+                best_score = best_score.max(futility_score);
+                continue;
+            }
+
+            if is_quiet {
+                quiet_moves_count += 1;
+            }
+
+            let mut score: i32;
 
             if is_pv_node {
                 // First move: search with full window (standard alpha-beta)
-                score = -self.search(-beta, -alpha, depth - 1, history_table, max_nodes);
+                score = -self.search(-beta, -alpha, depth - 1, history_table, max_nodes)?;
                 is_pv_node = false;
             } else {
+                // Late move reductions: search late quiet moves shallower, unless they give check
+                let reduction = if is_quiet
+                    && depth >= LMR_MIN_DEPTH
+                    && legal_moves_count > LMR_FULL_DEPTH_MOVES
+                    && !in_check
+                    && !self.is_in_check()
+                {
+                    // Reduce PV nodes less, and always leave at least one ply
+                    late_move_reduction(depth, legal_moves_count)
+                        .saturating_sub(!is_null_window as u16)
+                        .min(depth - 2)
+                } else {
+                    0
+                };
+
                 // Non-PV moves: search with null/"zero" window (PVS/NegaScout optimization)
-                score = -self.search(-(alpha + 1), -alpha, depth - 1, history_table, max_nodes);
+                score = -self.search(
+                    -(alpha + 1),
+                    -alpha,
+                    depth - 1 - reduction,
+                    history_table,
+                    max_nodes,
+                )?;
+
+                if reduction > 0 && score > alpha {
+                    // The reduced search failed high, so verify it at full depth
+                    score =
+                        -self.search(-(alpha + 1), -alpha, depth - 1, history_table, max_nodes)?;
+                }
 
                 if score > alpha && score < beta {
                     // Re-search with full window to get exact score
                     let re_search_score =
-                        -self.search(-beta, -alpha, depth - 1, history_table, max_nodes);
+                        -self.search(-beta, -alpha, depth - 1, history_table, max_nodes)?;
 
                     self.take_back_move();
 
                     if re_search_score >= beta {
-                        return self.on_beta_cutoff(
+                        return Ok(self.on_beta_cutoff(
                             current_move,
                             re_search_score,
                             depth,
                             history_table,
-                        );
+                        ));
                     }
 
                     if re_search_score > best_score {
                         best_score = re_search_score;
+                        best_move = Some(current_move);
                         self.update_principal_variation(current_move);
                         alpha = alpha.max(best_score);
                     }
@@ -2019,11 +3124,12 @@ impl Position {
             self.take_back_move();
 
             if score >= beta {
-                return self.on_beta_cutoff(current_move, score, depth, history_table);
+                return Ok(self.on_beta_cutoff(current_move, score, depth, history_table));
             }
 
             if score > best_score {
                 best_score = score;
+                best_move = Some(current_move);
                 self.update_principal_variation(current_move);
                 alpha = alpha.max(best_score);
             }
@@ -2034,17 +3140,259 @@ impl Position {
 
             if in_check {
                 // Checkmate - return negative score, prefer shorter mates
-                return -MATE_SCORE + self.ply as i32;
+                return Ok(-MATE_SCORE + self.ply as i32);
             }
-            return 0; // Stalemate
+            return Ok(0); // Stalemate
         }
 
         if self.fifty >= 100 {
             self.pv_length[self.ply] = self.ply;
-            return 0;
+            return Ok(0);
+        }
+
+        let best_score = best_score.min(max_score);
+
+        // With root moves skipped, the best move and score are not the position's own
+        if let Some(best_move) = best_move
+            && (self.ply > 0
+                || (self.excluded_root_moves.is_empty() && self.root_search_moves.is_empty()))
+        {
+            let flag = match best_score > original_alpha {
+                true => HashFlag::Exact,
+                false => HashFlag::UpperBound,
+            };
+
+            self.store_hash(best_move, depth, best_score, flag);
+        }
+
+        Ok(best_score)
+    }
+
+    /// Run `probe` with the tablebases, if they cover this position: few enough pieces, no
+    /// castling rights, and room in the ply-indexed tables to play out the captures. They're
+    /// lent out of the position meanwhile, so `probe` can make moves while it borrows them.
+    fn with_covering_tablebases<T>(
+        &mut self,
+        probe: impl FnOnce(&mut Self, &Tablebases) -> Option<T>,
+    ) -> Option<T> {
+        let pieces = self.board.bit_all.0.count_ones() as usize;
+        let max_pieces = self.tablebases.as_ref()?.max_pieces();
+
+        if self.castle != 0 || pieces > max_pieces || self.ply + pieces + 1 >= MAX_PLY {
+            return None;
+        }
+
+        let tablebases = self.tablebases.take()?;
+        let result = probe(self, &tablebases);
+        self.tablebases = Some(tablebases);
+
+        result
+    }
+
+    /// Win/draw/loss for the side to move from the Syzygy tablebases, ignoring the fifty-move
+    /// counter. `None` if the tables don't cover the position.
+    pub fn probe_wdl(&mut self) -> Option<Wdl> {
+        self.with_covering_tablebases(|position, tablebases| {
+            position.search_tablebase_wdl(tablebases, false)
+        })
+        .map(|(wdl, _)| wdl)
+    }
+
+    /// Distance to zeroing from the Syzygy tablebases: plies until a capture or pawn move that
+    /// keeps the result, positive when the side to move wins and 0 for a draw. Ignores the
+    /// fifty-move counter. `None` if the tables don't cover the position.
+    pub fn probe_dtz(&mut self) -> Option<i32> {
+        self.with_covering_tablebases(|position, tablebases| position.tablebase_dtz(tablebases))
+    }
+
+    /// Rank each legal move by its tablebase result, higher is better (see `syzygy::dtz_rank`).
+    /// Uses the DTZ tables when they're all available and otherwise the WDL tables, which can
+    /// only tell wins, draws and losses apart. `None` if the tables don't cover the position.
+    pub fn tablebase_root_moves(&mut self) -> Option<Vec<(MoveData, i32)>> {
+        self.with_covering_tablebases(|position, tablebases| {
+            position
+                .rank_root_moves_by_dtz(tablebases)
+                .or_else(|| position.rank_root_moves_by_wdl(tablebases))
+        })
+    }
+
+    fn rank_root_moves_by_dtz(&mut self, tablebases: &Tablebases) -> Option<Vec<(MoveData, i32)>> {
+        let fifty = self.fifty as i32;
+        let repeated = self.repetitions() > 0;
+        let mut ranked = Vec::new();
+
+        for move_ in self.legal_moves() {
+            self.make_move(move_.from, move_.to, move_.promote);
+
+            // Counted from the root, so one ply more than the reply's
+            let dtz = if self.fifty == 0 {
+                self.search_tablebase_wdl(tablebases, false)
+                    .map(|(wdl, _)| (-wdl).dtz_before_zeroing())
+            } else if self.fifty >= 100 || self.repetitions() >= 2 {
+                Some(0)
+            } else {
+                self.tablebase_dtz(tablebases)
+                    .map(|dtz| -dtz - dtz.signum())
+            };
+
+            // A mating move reaches zeroing at once
+            let is_mate = dtz == Some(2) && self.is_in_check() && self.legal_moves().is_empty();
+
+            self.take_back_move();
+
+            let dtz = if is_mate { 1 } else { dtz? };
+            ranked.push((
+                MoveData {
+                    from: move_.from,
+                    to: move_.to,
+                    promote: move_.promote,
+                },
+                syzygy::dtz_rank(dtz, fifty, repeated),
+            ));
+        }
+
+        Some(ranked)
+    }
+
+    fn rank_root_moves_by_wdl(&mut self, tablebases: &Tablebases) -> Option<Vec<(MoveData, i32)>> {
+        let mut ranked = Vec::new();
+
+        for move_ in self.legal_moves() {
+            self.make_move(move_.from, move_.to, move_.promote);
+
+            let wdl = if self.fifty >= 100 || self.repetitions() >= 2 {
+                Some(Wdl::Draw)
+            } else {
+                self.search_tablebase_wdl(tablebases, false)
+                    .map(|(wdl, _)| -wdl)
+            };
+
+            self.take_back_move();
+
+            ranked.push((
+                MoveData {
+                    from: move_.from,
+                    to: move_.to,
+                    promote: move_.promote,
+                },
+                wdl?.rank(),
+            ));
+        }
+
+        Some(ranked)
+    }
+
+    /// Win/draw/loss, trying the captures (and pawn moves, with `zeroing_moves`) first: the
+    /// tables don't store en passant rights, and only hold "don't care" values when a capture
+    /// wins. Also returns whether the best move zeroes the fifty-move counter.
+    fn search_tablebase_wdl(
+        &mut self,
+        tablebases: &Tablebases,
+        zeroing_moves: bool,
+    ) -> Option<(Wdl, bool)> {
+        self.generate_moves_and_captures(self.side, |_, _, _| 0);
+
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        let mut other_moves = false; // Whether a legal move is left unsearched
+
+        for i in self.first_move[self.ply]..self.first_move[self.ply + 1] {
+            let Some(move_) = self.move_list[i as usize] else {
+                continue;
+            };
+
+            let is_pawn_move = self.board.value[move_.from as usize] == Piece::Pawn;
+            if !(self.is_capture(move_) || (zeroing_moves && is_pawn_move)) {
+                if !other_moves && self.make_move(move_.from, move_.to, move_.promote) {
+                    self.take_back_move();
+                    other_moves = true;
+                }
+                continue;
+            }
+
+            if !self.make_move(move_.from, move_.to, move_.promote) {
+                continue;
+            }
+
+            searched += 1;
+            let result = self.search_tablebase_wdl(tablebases, false);
+            self.take_back_move();
+
+            let wdl = -result?.0;
+            if wdl > best {
+                best = wdl;
+
+                if wdl == Wdl::Win {
+                    return Some((wdl, true));
+                }
+            }
+        }
+
+        // With every move searched, the table isn't needed (and may be wrong about en passant)
+        let no_more_moves = searched > 0 && !other_moves;
+        let wdl = if no_more_moves {
+            best
+        } else {
+            tablebases.probe_wdl_table(self)?
+        };
+
+        if best >= wdl {
+            Some((best, best > Wdl::Draw || no_more_moves))
+        } else {
+            Some((wdl, false))
+        }
+    }
+
+    fn tablebase_dtz(&mut self, tablebases: &Tablebases) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search_tablebase_wdl(tablebases, true)?;
+
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+
+        // The table has no useful value when a zeroing move is best
+        if zeroing_best {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+        if let syzygy::DtzProbe::Value(dtz) = tablebases.probe_dtz_table(self, wdl)? {
+            return Some((dtz + if cursed { 100 } else { 0 }) * (wdl as i32).signum());
+        }
+
+        // The file only stores the other side to move: take the best reply's DTZ
+        let mut min_dtz = None;
+        for move_ in self.legal_moves() {
+            let is_zeroing =
+                self.is_capture(move_) || self.board.value[move_.from as usize] == Piece::Pawn;
+
+            self.make_move(move_.from, move_.to, move_.promote);
+
+            // After a zeroing move, its sign is all that's needed
+            let dtz = if is_zeroing {
+                self.search_tablebase_wdl(tablebases, false)
+                    .map(|(reply, _)| -reply.dtz_before_zeroing())
+            } else {
+                self.tablebase_dtz(tablebases).map(|reply| -reply)
+            };
+            let is_mate = dtz == Some(1) && self.is_in_check() && self.legal_moves().is_empty();
+
+            self.take_back_move();
+
+            let mut dtz = dtz?;
+            if !is_zeroing {
+                dtz += dtz.signum(); // Counted from this position
+            }
+
+            if is_mate {
+                min_dtz = Some(1);
+            } else if dtz.signum() == (wdl as i32).signum() && min_dtz.is_none_or(|min| dtz < min) {
+                min_dtz = Some(dtz);
+            }
         }
 
-        best_score
+        // No legal moves: mated
+        Some(min_dtz.unwrap_or(-1))
     }
 
     /// Load a position from a FEN (Forsyth-Edwards Notation) string.
@@ -2548,6 +3896,18 @@ impl Position {
     }
 
     pub fn get_legal_moves(&mut self) -> Vec<String> {
+        let mut moves: Vec<String> = self
+            .legal_moves()
+            .iter()
+            .map(|mv| Board::move_to_uci_string(mv.from, mv.to, mv.promote, false))
+            .collect();
+
+        moves.sort();
+        moves
+    }
+
+    /// The legal moves, in generation order
+    fn legal_moves(&mut self) -> Vec<Move> {
         self.generate_moves_and_captures(self.side, |_, _, _| 0);
 
         let mut moves = Vec::new();
@@ -2557,11 +3917,124 @@ impl Position {
                 && self.make_move(mv.from, mv.to, mv.promote)
             {
                 self.take_back_move();
-                moves.push(Board::move_to_uci_string(mv.from, mv.to, mv.promote, false));
+                moves.push(mv);
             }
         }
 
-        moves.sort();
         moves
     }
 }
+
+/// Plies to take off the search of a late quiet move. Grows slowly with both the depth and how
+/// late the move comes in the ordering.
+fn late_move_reduction(depth: u16, move_number: usize) -> u16 {
+    (LMR_BASE + (depth as f64).ln() * (move_number as f64).ln() / LMR_DIVISOR) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::BENCH_POSITIONS;
+
+    fn position_from_fen(fen: &str) -> Position {
+        let mut position = Position::from_fen(fen).unwrap();
+        position.set_material_scores();
+        position
+    }
+
+    fn quiescence_score(position: &mut Position, depth: u16) -> i32 {
+        position
+            .quiescence_search(-INFINITY_SCORE, INFINITY_SCORE, depth, None)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_quiescence_search_detects_checkmate() {
+        // Fool's mate: standing pat would score White's extra material
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        let mut position = position_from_fen(fen);
+
+        let score = quiescence_score(&mut position, DEFAULT_MAX_QUIESCENCE_DEPTH);
+
+        assert_eq!(score, -MATE_SCORE);
+    }
+
+    #[test]
+    fn test_quiescence_search_evades_check_instead_of_standing_pat() {
+        // The knight checks and forks: every king move lets it take the queen
+        let fen = "4k3/8/8/8/8/8/2n5/Q3K3 w - - 0 1";
+        let mut position = position_from_fen(fen);
+        assert!(position.evaluate() > 0);
+
+        let score = quiescence_score(&mut position, DEFAULT_MAX_QUIESCENCE_DEPTH);
+
+        assert!(score < 0, "Lost the queen, but scored {score}");
+    }
+
+    #[test]
+    fn test_quiescence_search_finds_quiet_mating_check() {
+        // Ra8# is quiet, so it's only tried at the start of quiescence
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+
+        let score = quiescence_score(&mut position_from_fen(fen), DEFAULT_MAX_QUIESCENCE_DEPTH);
+        assert_eq!(score, MATE_SCORE - 1);
+
+        let score = quiescence_score(
+            &mut position_from_fen(fen),
+            DEFAULT_MAX_QUIESCENCE_DEPTH - 1,
+        );
+        assert!(score < MATE_THRESHOLD);
+    }
+
+    #[test]
+    fn test_delta_pruning_skips_captures_that_cannot_raise_alpha() {
+        // exd5 wins a pawn, which is far short of alpha
+        let fen = "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1";
+        let mut position = position_from_fen(fen);
+        let alpha = position.evaluate() + DELTA_PRUNING_MARGIN + Piece::Pawn.value() + 1;
+
+        let score = position
+            .quiescence_search(alpha, alpha + 1, DEFAULT_MAX_QUIESCENCE_DEPTH, None)
+            .unwrap();
+
+        assert!(score <= alpha);
+        assert_eq!(position.qnodes, 1, "The capture should not be searched");
+    }
+
+    #[test]
+    fn test_gives_check_matches_making_the_move() {
+        let discovered_checks = [
+            "4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1",
+            "7k/8/5P2/8/3B4/8/8/6K1 w - - 0 1",
+            "1k6/8/8/8/8/8/1R6/1N4K1 b - - 0 1",
+        ];
+
+        for fen in BENCH_POSITIONS.iter().chain(&discovered_checks) {
+            let mut position = Position::from_fen(fen).unwrap();
+            position.generate_moves_and_captures(position.side, |_, _, _| 0);
+
+            for index in position.first_move[0]..position.first_move[1] {
+                let move_ = position.move_list[index as usize].unwrap();
+                let is_castle = position.board.value[move_.from as usize] == Piece::King
+                    && COLUMN[move_.from as usize].abs_diff(COLUMN[move_.to as usize]) == 2;
+
+                if position.is_capture(move_) || move_.promote.is_some() || is_castle {
+                    continue;
+                }
+
+                let gives_check = position.gives_check(move_);
+
+                if position.make_move(move_.from, move_.to, move_.promote) {
+                    assert_eq!(
+                        gives_check,
+                        position.is_in_check(),
+                        "{fen}: {:?}{:?}",
+                        move_.from,
+                        move_.to
+                    );
+                    position.take_back_move();
+                }
+            }
+        }
+    }
+}
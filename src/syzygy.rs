@@ -0,0 +1,1391 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io,
+    ops::Neg,
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::{
+    constants::NUM_SQUARES,
+    position::Position,
+    types::{Piece, Side},
+};
+
+/// Largest tables in the Syzygy format (7 pieces, kings included)
+const MAX_PIECES: usize = 7;
+
+/// Files start with these bytes
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+/// Header byte after the magic
+const HEADER_SPLIT: u8 = 1; // Separate tables for each side to move
+const HEADER_HAS_PAWNS: u8 = 2;
+
+/// Compressed blocks are read this many bytes at a time, into a buffer on the stack
+const DECOMPRESS_CHUNK_SIZE: usize = 256;
+
+/// Flags of each table in a file
+const FLAG_STM: u8 = 1; // DTZ: side to move of the stored positions (1 = black)
+const FLAG_MAPPED: u8 = 2; // DTZ: values are looked up in the file's value map
+const FLAG_WIN_PLIES: u8 = 4; // DTZ: wins are stored in plies rather than moves
+const FLAG_LOSS_PLIES: u8 = 8; // DTZ: losses are stored in plies rather than moves
+const FLAG_WIDE: u8 = 16; // DTZ: the value map holds 16-bit values
+const FLAG_SINGLE_VALUE: u8 = 128; // Every position has the same value
+
+/// Order of the piece letters in table names, e.g. "KRPvKR"
+const PIECE_LETTERS: [(Piece, char); 6] = [
+    (Piece::King, 'K'),
+    (Piece::Queen, 'Q'),
+    (Piece::Rook, 'R'),
+    (Piece::Bishop, 'B'),
+    (Piece::Knight, 'N'),
+    (Piece::Pawn, 'P'),
+];
+
+/// Root moves are ranked so that faster wins and slower losses come first; a rank this far from
+/// zero is a win (or loss) that the fifty-move rule can't spoil
+pub const MAX_DTZ: i32 = 1 << 18;
+
+/// Game-theoretical result for the side to move. "Cursed" wins and "blessed" losses are drawn
+/// under the fifty-move rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    /// Distance to zeroing of a position where the best move zeroes the fifty-move counter
+    pub fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Win => 1,
+            Wdl::CursedWin => 101,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -101,
+            Wdl::Loss => -1,
+        }
+    }
+
+    /// Rank of a root move reaching this result, when the DTZ tables are unavailable
+    pub fn rank(self) -> i32 {
+        match self {
+            Wdl::Win => MAX_DTZ,
+            Wdl::CursedWin => MAX_DTZ - 101,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -MAX_DTZ + 101,
+            Wdl::Loss => -MAX_DTZ,
+        }
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+impl fmt::Display for Wdl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Wdl::Loss => "loss",
+            Wdl::BlessedLoss => "blessed loss",
+            Wdl::Draw => "draw",
+            Wdl::CursedWin => "cursed win",
+            Wdl::Win => "win",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Rank of a root move with this distance to zeroing from the root (positive when the mover
+/// wins). `fifty` is the root position's fifty-move counter and `repeated` whether the root has
+/// occurred before. Wins the fifty-move rule can't spoil rank equally, as do losses with no
+/// fifty-move draw in sight; otherwise faster wins and slower losses rank higher.
+pub fn dtz_rank(dtz: i32, fifty: i32, repeated: bool) -> i32 {
+    if dtz > 0 {
+        if dtz + fifty <= 99 && !repeated {
+            MAX_DTZ
+        } else {
+            MAX_DTZ - (dtz + fifty)
+        }
+    } else if dtz < 0 {
+        if -dtz * 2 + fifty < 100 {
+            -MAX_DTZ
+        } else {
+            -MAX_DTZ + (-dtz + fifty)
+        }
+    } else {
+        0
+    }
+}
+
+/// Result of looking a position up in a DTZ table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DtzProbe {
+    /// Plies to zeroing, signed like the result
+    Value(i32),
+    /// The file only stores the other side to move; search one ply deeper
+    OtherSideToMove,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    fn magic(self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+/// A table file found by `Tablebases::open`, loaded on first use
+struct TableEntry {
+    name: String, // e.g. "KRvK"
+    path: PathBuf,
+    table: OnceLock<Option<Table>>, // `None` if the file couldn't be loaded
+    failed: AtomicBool,             // A read failed, so the table isn't probed again
+}
+
+/// Syzygy endgame tablebases (https://www.chessprogramming.org/Syzygy_Bases): win/draw/loss
+/// (.rtbw) and distance-to-zeroing (.rtbz) files in the configured directories. Files are opened
+/// on first use and read on demand, so only the headers and indexes are kept in memory.
+pub struct Tablebases {
+    wdl_tables: HashMap<u64, TableEntry>, // Material (see `material_key`) -> table
+    dtz_tables: HashMap<u64, TableEntry>,
+    max_pieces: usize,
+    errors: Mutex<Vec<String>>, // Tables that failed to load or read, until `take_errors`
+}
+
+impl Tablebases {
+    /// Find the tables in `path`: directories separated by `:` (`;` on Windows)
+    pub fn open(path: &str) -> io::Result<Tablebases> {
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        let mut wdl_tables = HashMap::new();
+        let mut dtz_tables = HashMap::new();
+        let mut max_pieces = 0;
+
+        for directory in path.split(separator).filter(|dir| !dir.is_empty()) {
+            for entry in fs::read_dir(directory)? {
+                let path = entry?.path();
+                let (Some(name), Some(extension)) = (
+                    path.file_stem().and_then(|stem| stem.to_str()),
+                    path.extension().and_then(|extension| extension.to_str()),
+                ) else {
+                    continue;
+                };
+
+                let Some((white, black)) = table_sides(name) else {
+                    continue;
+                };
+
+                let tables = match extension {
+                    "rtbw" => {
+                        max_pieces = max_pieces.max(white.len() + black.len());
+                        &mut wdl_tables
+                    }
+                    "rtbz" => &mut dtz_tables,
+                    _ => continue,
+                };
+                let key = material_key(name_material(white), name_material(black));
+                tables.entry(key).or_insert_with(|| TableEntry {
+                    name: name.to_string(),
+                    path: path.clone(),
+                    table: OnceLock::new(),
+                    failed: AtomicBool::new(false),
+                });
+            }
+        }
+
+        Ok(Tablebases {
+            wdl_tables,
+            dtz_tables,
+            max_pieces,
+            errors: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Number of WDL and DTZ files found
+    pub fn num_tables(&self) -> usize {
+        self.wdl_tables.len() + self.dtz_tables.len()
+    }
+
+    /// Most pieces (kings included) in any WDL table found
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Tables that failed to load or read since the last call. Each is reported once, and isn't
+    /// probed again.
+    pub fn take_errors(&self) -> Vec<String> {
+        std::mem::take(&mut *self.errors.lock().unwrap())
+    }
+
+    /// Look the position up in its WDL table. Only valid when the side to move can't capture
+    /// (see `Position::probe_wdl`); castling rights must be gone.
+    pub fn probe_wdl_table(&self, position: &Position) -> Option<Wdl> {
+        if position.board.bit_all.0.count_ones() == 2 {
+            return Some(Wdl::Draw); // Bare kings
+        }
+
+        let (entry, table, black_stronger) = self.table(position, TableKind::Wdl)?;
+        match table.probe(position, black_stronger, Wdl::Draw) {
+            Ok(Some(value)) => Wdl::from_value(value - 2),
+            Ok(None) => None,
+            Err(error) => {
+                self.disable(entry, error);
+                None
+            }
+        }
+    }
+
+    /// Look the position up in its DTZ table, given its WDL. Only valid when the side to move
+    /// can't capture or has no winning zeroing move (see `Position::probe_dtz`).
+    pub fn probe_dtz_table(&self, position: &Position, wdl: Wdl) -> Option<DtzProbe> {
+        let (entry, table, black_stronger) = self.table(position, TableKind::Dtz)?;
+        match table.probe(position, black_stronger, wdl) {
+            Ok(Some(value)) => Some(DtzProbe::Value(value)),
+            Ok(None) => Some(DtzProbe::OtherSideToMove),
+            Err(error) => {
+                self.disable(entry, error);
+                None
+            }
+        }
+    }
+
+    /// Stop probing a table that failed to read, like one that failed to load
+    fn disable(&self, entry: &TableEntry, error: io::Error) {
+        // Another thread may have disabled it already
+        if !entry.failed.swap(true, Ordering::Relaxed) {
+            self.errors.lock().unwrap().push(format!(
+                "Failed to read tablebase {}: {}",
+                entry.path.display(),
+                error
+            ));
+        }
+    }
+
+    /// The table holding the position's material, and whether black has the table's "white"
+    /// pieces. Runs at search nodes, so it doesn't allocate once the table is loaded.
+    fn table(&self, position: &Position, kind: TableKind) -> Option<(&TableEntry, &Table, bool)> {
+        let white = position_material(position, Side::White);
+        let black = position_material(position, Side::Black);
+        let tables = match kind {
+            TableKind::Wdl => &self.wdl_tables,
+            TableKind::Dtz => &self.dtz_tables,
+        };
+
+        let (entry, black_stronger) = match tables.get(&material_key(white, black)) {
+            Some(entry) => (entry, false),
+            None => (tables.get(&material_key(black, white))?, true),
+        };
+
+        if entry.failed.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let table = entry.table.get_or_init(|| {
+            match Table::open(&entry.path, &entry.name, kind) {
+                Ok(table) => Some(table),
+                Err(error) => {
+                    // Runs once, however many threads are waiting for the table
+                    self.errors.lock().unwrap().push(format!(
+                        "Failed to load tablebase {}: {}",
+                        entry.path.display(),
+                        error
+                    ));
+                    None
+                }
+            }
+        });
+
+        table.as_ref().map(|table| (entry, table, black_stronger))
+    }
+}
+
+/// The two sides of a table name, e.g. ("KQ", "KR") for "KQvKR", if it is one
+fn table_sides(name: &str) -> Option<(&str, &str)> {
+    let (white, black) = name.split_once('v')?;
+
+    let is_table = [white, black].iter().all(|side| {
+        side.starts_with('K')
+            && side.matches('K').count() == 1
+            && side.chars().all(|letter| "KQRBNP".contains(letter))
+    }) && white.len() + black.len() <= MAX_PIECES;
+
+    is_table.then_some((white, black))
+}
+
+/// Both sides' material as a table lookup key, white's (the table's first side) in the high bits
+fn material_key(white: u32, black: u32) -> u64 {
+    ((white as u64) << 32) | black as u64
+}
+
+/// One side's piece counts in a position, four bits for each piece type
+fn position_material(position: &Position, side: Side) -> u32 {
+    PIECE_LETTERS
+        .iter()
+        .enumerate()
+        .map(|(i, &(piece, _))| {
+            let count = position.board.bit_pieces[side as usize][piece as usize]
+                .0
+                .count_ones();
+            count.min(15) << (4 * i)
+        })
+        .sum()
+}
+
+/// One side's piece counts from its part of a table name, e.g. "KRP", as `position_material`
+fn name_material(side: &str) -> u32 {
+    side.chars()
+        .filter_map(|letter| PIECE_LETTERS.iter().position(|&(_, l)| l == letter))
+        .map(|i| 1 << (4 * i))
+        .sum()
+}
+
+/// Piece code used inside the files: 1-6 (pawn to king) for white, 9-14 for black
+fn table_piece(piece: Piece, side: Side) -> u8 {
+    piece as u8 + 1 + if side == Side::Black { 8 } else { 0 }
+}
+
+fn file_of(square: usize) -> usize {
+    square & 7
+}
+
+fn rank_of(square: usize) -> usize {
+    square >> 3
+}
+
+/// Rank minus file: 0 on the a1-h8 diagonal, negative below it
+fn off_diagonal(square: usize) -> i32 {
+    rank_of(square) as i32 - file_of(square) as i32
+}
+
+fn corrupt(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Lookups for turning a position into an index in a table
+struct Indexing {
+    map_pawns: [usize; NUM_SQUARES], // Order of pawn squares: files a-d first, flipped files next
+    map_b1h1h7: [u64; NUM_SQUARES],  // Squares below the a1-h8 diagonal: 0-27
+    map_a1d1d4: [usize; NUM_SQUARES], // Squares of the a1-d1-d4 triangle: 0-9, diagonal last
+    map_kk: [[u64; NUM_SQUARES]; 10], // [first king's triangle index][second king] -> 0-461
+    binomial: [[u64; NUM_SQUARES]; 7], // [k][n] = n choose k
+    lead_pawn_index: [[u64; NUM_SQUARES]; 7], // [lead pawns][first lead pawn's square]
+    lead_pawns_size: [[u64; 4]; 7],  // [lead pawns][file]
+}
+
+impl Indexing {
+    fn new() -> Indexing {
+        let mut map_b1h1h7 = [0; NUM_SQUARES];
+        let mut code = 0;
+        for (square, entry) in map_b1h1h7.iter_mut().enumerate() {
+            if off_diagonal(square) < 0 {
+                *entry = code;
+                code += 1;
+            }
+        }
+
+        let mut map_a1d1d4 = [0; NUM_SQUARES];
+        let mut diagonal = Vec::new();
+        let mut code = 0;
+        for (square, entry) in map_a1d1d4.iter_mut().enumerate().take(28) {
+            if file_of(square) > 3 {
+                continue;
+            }
+            if off_diagonal(square) < 0 {
+                *entry = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // Both kings on the diagonal come last; with the first king on it, the second is kept
+        // below it
+        let mut map_kk = [[0; NUM_SQUARES]; 10];
+        let mut both_on_diagonal = Vec::new();
+        let mut code = 0;
+        for (index, row) in map_kk.iter_mut().enumerate() {
+            for (square1, &square1_index) in map_a1d1d4.iter().enumerate().take(28) {
+                let in_triangle = file_of(square1) <= 3 && off_diagonal(square1) <= 0;
+                if !in_triangle || square1_index != index {
+                    continue;
+                }
+                for (square2, entry) in row.iter_mut().enumerate() {
+                    let distance = file_of(square1)
+                        .abs_diff(file_of(square2))
+                        .max(rank_of(square1).abs_diff(rank_of(square2)));
+                    if distance <= 1 || (off_diagonal(square1) == 0 && off_diagonal(square2) > 0) {
+                        continue;
+                    }
+                    if off_diagonal(square1) == 0 && off_diagonal(square2) == 0 {
+                        both_on_diagonal.push((index, square2));
+                    } else {
+                        *entry = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (index, square2) in both_on_diagonal {
+            map_kk[index][square2] = code;
+            code += 1;
+        }
+
+        let mut binomial = [[0; NUM_SQUARES]; 7];
+        binomial[0][0] = 1;
+        for n in 1..NUM_SQUARES {
+            for k in 0..=n.min(6) {
+                binomial[k][n] = if k > 0 { binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        let mut map_pawns = [0; NUM_SQUARES];
+        let mut lead_pawn_index = [[0; NUM_SQUARES]; 7];
+        let mut lead_pawns_size = [[0; 4]; 7];
+        let mut available = 48;
+        for lead_pawns in 1..=6 {
+            for (file, size) in lead_pawns_size[lead_pawns].iter_mut().enumerate() {
+                let mut index = 0;
+                for rank in 1..=6 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        available -= 1;
+                        map_pawns[square] = available;
+                        available -= 1;
+                        map_pawns[square ^ 7] = available;
+                    }
+                    lead_pawn_index[lead_pawns][square] = index;
+                    index += binomial[lead_pawns - 1][map_pawns[square]];
+                }
+                *size = index;
+            }
+        }
+
+        Indexing {
+            map_pawns,
+            map_b1h1h7,
+            map_a1d1d4,
+            map_kk,
+            binomial,
+            lead_pawn_index,
+            lead_pawns_size,
+        }
+    }
+}
+
+static INDEXING: LazyLock<Indexing> = LazyLock::new(Indexing::new);
+
+/// Positional reads from a table file
+struct TableFile {
+    file: File,
+    len: u64,
+}
+
+impl TableFile {
+    fn open(path: &Path) -> io::Result<TableFile> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(TableFile { file, len })
+    }
+
+    /// Fill `buffer` from `offset`; bytes past the end of the file read as zero
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        buffer.fill(0);
+        if offset >= self.len {
+            return Ok(());
+        }
+        let available = (self.len - offset).min(buffer.len() as u64) as usize;
+        read_exact_at(&self.file, &mut buffer[..available], offset)
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buffer, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buffer: &mut [u8], mut offset: u64) -> io::Result<()> {
+    while !buffer.is_empty() {
+        match std::os::windows::fs::FileExt::seek_read(file, buffer, offset)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            read => {
+                buffer = &mut buffer[read..];
+                offset += read as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the header and indexes of a table file, in order (little-endian)
+struct Reader<'a> {
+    file: &'a TableFile,
+    offset: u64,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        if self.offset + len as u64 > self.file.len {
+            return Err(corrupt("unexpected end of file"));
+        }
+        let mut buffer = vec![0; len];
+        self.file.read_at(self.offset, &mut buffer)?;
+        self.offset += len as u64;
+        Ok(buffer)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn skip(&mut self, len: u64) {
+        self.offset += len;
+    }
+
+    /// Move to the next multiple of `alignment` from the start of the file
+    fn align(&mut self, alignment: u64) {
+        self.offset = self.offset.next_multiple_of(alignment);
+    }
+}
+
+/// One table of a file: the positions with one side to move (WDL) and, with pawns, the leading
+/// pawn on one file. Values are Huffman-coded in blocks; `sparse_index` finds the block holding
+/// an index and `block_lengths` walks from there.
+#[derive(Default)]
+struct PairsData {
+    flags: u8,
+    pieces: [u8; MAX_PIECES],           // Table piece codes in index order
+    group_len: [usize; MAX_PIECES + 1], // Pieces in each group, zero-terminated
+    group_index: [u64; MAX_PIECES + 1], // Multiplier of each group's index; the last is the size
+    block_size: u64,
+    span: u64, // Indexes covered by each sparse index entry
+    num_blocks: u32,
+    padding: u32,
+    min_sym_len: u32, // The value itself for single-value tables
+    lowest_sym: Vec<u16>,
+    base64: Vec<u64>,
+    btree: Vec<(u16, u16)>, // Symbol -> (left, right) pair; a right of 0xFFF marks a value
+    symlen: Vec<u32>,       // Values in each symbol, less one
+    sparse_index: Vec<(u32, u16)>, // (block, offset in block)
+    block_lengths: Vec<u16>, // Values in each block, less one
+    data_offset: u64,
+    dtz_map_index: [usize; 4], // Start of each result's values in the DTZ value map
+}
+
+impl PairsData {
+    /// Split the pieces into groups that are indexed together, and set their multipliers
+    fn set_groups(&mut self, order: [u8; 2], file: usize, table: &TableShape) {
+        let indexing = &*INDEXING;
+        let TableShape {
+            num_pieces,
+            has_pawns,
+            has_unique_pieces,
+            pawn_count,
+        } = *table;
+
+        // Leading pawns or kings come first
+        let mut first_len: i32 = if has_pawns {
+            0
+        } else if has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        let mut groups = 0;
+        self.group_len[0] = 1;
+        for i in 1..num_pieces {
+            first_len -= 1;
+            if first_len > 0 || self.pieces[i] == self.pieces[i - 1] {
+                self.group_len[groups] += 1;
+            } else {
+                groups += 1;
+                self.group_len[groups] = 1;
+            }
+        }
+        self.group_len[groups + 1] = 0;
+
+        let pawns_on_both_sides = has_pawns && pawn_count[1] > 0;
+        let mut next = if pawns_on_both_sides { 2 } else { 1 };
+        let mut free_squares = 64
+            - self.group_len[0]
+            - if pawns_on_both_sides {
+                self.group_len[1]
+            } else {
+                0
+            };
+        let mut index: u64 = 1;
+
+        let mut k = 0;
+        while next < groups + 1 || k == order[0] || k == order[1] {
+            if k == order[0] {
+                self.group_index[0] = index;
+                index *= if has_pawns {
+                    indexing.lead_pawns_size[self.group_len[0]][file]
+                } else if has_unique_pieces {
+                    31_332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                self.group_index[1] = index;
+                index *= indexing.binomial[self.group_len[1]][48 - self.group_len[0]];
+            } else {
+                self.group_index[next] = index;
+                index *= indexing.binomial[self.group_len[next]][free_squares];
+                free_squares -= self.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        self.group_index[groups + 1] = index;
+    }
+
+    /// Number of indexes in the table
+    fn size(&self) -> u64 {
+        let groups = self.group_len.iter().take_while(|&&len| len != 0).count();
+        self.group_index[groups]
+    }
+
+    /// Read the Huffman code description and the sizes of the index arrays
+    fn read_sizes(&mut self, reader: &mut Reader) -> io::Result<()> {
+        self.flags = reader.u8()?;
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            self.min_sym_len = reader.u8()? as u32;
+            return Ok(());
+        }
+
+        let block_size_bits = reader.u8()?;
+        let span_bits = reader.u8()?;
+        if block_size_bits > 32 || span_bits > 40 {
+            return Err(corrupt("bad block size"));
+        }
+        self.block_size = 1 << block_size_bits;
+        self.span = 1 << span_bits;
+        self.padding = reader.u8()? as u32;
+        self.num_blocks = reader.u32()?;
+        let max_sym_len = reader.u8()? as u32;
+        self.min_sym_len = reader.u8()? as u32;
+        if self.min_sym_len == 0 || max_sym_len < self.min_sym_len || max_sym_len > 32 {
+            return Err(corrupt("bad symbol lengths"));
+        }
+
+        let lengths = (max_sym_len - self.min_sym_len + 1) as usize;
+        self.lowest_sym = (0..lengths)
+            .map(|_| reader.u16())
+            .collect::<io::Result<_>>()?;
+
+        // base64[i] is the lowest code of length i + min_sym_len, left-aligned in 64 bits
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            self.base64[i] = (self.base64[i + 1] + self.lowest_sym[i] as u64)
+                .checked_sub(self.lowest_sym[i + 1] as u64)
+                .ok_or_else(|| corrupt("bad symbol table"))?
+                / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            *base = base
+                .checked_shl(64 - i as u32 - self.min_sym_len)
+                .unwrap_or(0);
+        }
+
+        let num_syms = reader.u16()? as usize;
+        let tree = reader.bytes(num_syms * 3)?;
+        if num_syms % 2 == 1 {
+            reader.skip(1);
+        }
+        self.btree = tree
+            .chunks_exact(3)
+            .map(|bytes| {
+                let left = ((bytes[1] as u16 & 0xF) << 8) | bytes[0] as u16;
+                let right = ((bytes[2] as u16) << 4) | (bytes[1] as u16 >> 4);
+                (left, right)
+            })
+            .collect();
+
+        self.symlen = vec![0; num_syms];
+        let mut visited = vec![false; num_syms];
+        for sym in 0..num_syms {
+            self.set_symlen(sym, &mut visited)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_symlen(&mut self, sym: usize, visited: &mut [bool]) -> io::Result<()> {
+        if visited[sym] {
+            return Ok(());
+        }
+        visited[sym] = true;
+
+        let (left, right) = self.btree[sym];
+        if right == 0xFFF {
+            self.symlen[sym] = 0;
+            return Ok(());
+        }
+
+        let (left, right) = (left as usize, right as usize);
+        if left >= self.btree.len() || right >= self.btree.len() {
+            return Err(corrupt("bad symbol tree"));
+        }
+        self.set_symlen(left, visited)?;
+        self.set_symlen(right, visited)?;
+        self.symlen[sym] = self.symlen[left] + self.symlen[right] + 1;
+        Ok(())
+    }
+
+    /// Entries in the sparse index
+    fn sparse_index_len(&self) -> usize {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            0
+        } else {
+            self.size().div_ceil(self.span) as usize
+        }
+    }
+
+    /// Entries in the block length array
+    fn block_lengths_len(&self) -> usize {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            0
+        } else {
+            (self.num_blocks + self.padding) as usize
+        }
+    }
+
+    /// The value at `index`
+    fn decompress(&self, file: &TableFile, index: u64) -> io::Result<u32> {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            return Ok(self.min_sym_len);
+        }
+
+        let &(block, offset) = self
+            .sparse_index
+            .get((index / self.span) as usize)
+            .ok_or_else(|| corrupt("index out of range"))?;
+
+        // The sparse entry points at the middle of its span
+        let mut block = block as usize;
+        let mut offset = offset as i64 + (index % self.span) as i64 - (self.span / 2) as i64;
+        let block_length = |block: usize| -> io::Result<i64> {
+            self.block_lengths
+                .get(block)
+                .map(|&length| length as i64)
+                .ok_or_else(|| corrupt("block out of range"))
+        };
+        while offset < 0 {
+            block = block
+                .checked_sub(1)
+                .ok_or_else(|| corrupt("block out of range"))?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        // Read the block a chunk at a time, to a little past its end so the bit buffer can always
+        // be refilled. `chunk_start` is the file offset of `buffer[0]`.
+        let mut buffer = [0; DECOMPRESS_CHUNK_SIZE];
+        let mut chunk_start = self.data_offset + block as u64 * self.block_size;
+        let block_end = chunk_start + self.block_size + 8;
+        let mut chunk_len = (block_end - chunk_start).min(buffer.len() as u64) as usize;
+        file.read_at(chunk_start, &mut buffer[..chunk_len])?;
+
+        let mut offset = offset as u32;
+        let mut bits = u64::from_be_bytes(buffer[..8].try_into().unwrap());
+        let mut position = 8;
+        let mut bits_available = 64;
+        let mut sym;
+
+        loop {
+            let mut len = 0;
+            while bits < self.base64[len] {
+                len += 1;
+                if len == self.base64.len() {
+                    return Err(corrupt("bad code"));
+                }
+            }
+
+            sym = ((bits - self.base64[len]) >> (64 - len as u32 - self.min_sym_len)) as usize
+                + self.lowest_sym[len] as usize;
+            let sym_len = *self.symlen.get(sym).ok_or_else(|| corrupt("bad symbol"))?;
+            if offset < sym_len + 1 {
+                break;
+            }
+            offset -= sym_len + 1;
+
+            let len = len as u32 + self.min_sym_len;
+            bits <<= len;
+            bits_available -= len;
+            if bits_available <= 32 {
+                if position + 4 > chunk_len {
+                    chunk_start += position as u64;
+                    chunk_len = (block_end - chunk_start).min(buffer.len() as u64) as usize;
+                    if chunk_len < 4 {
+                        return Err(corrupt("block overrun"));
+                    }
+                    file.read_at(chunk_start, &mut buffer[..chunk_len])?;
+                    position = 0;
+                }
+
+                let bytes = &buffer[position..position + 4];
+                bits_available += 32;
+                bits |=
+                    (u32::from_be_bytes(bytes.try_into().unwrap()) as u64) << (64 - bits_available);
+                position += 4;
+            }
+        }
+
+        // Find the value within the symbol's pairs
+        while self.symlen[sym] != 0 {
+            let (left, right) = self.btree[sym];
+            let left_len = self.symlen[left as usize];
+            if offset < left_len + 1 {
+                sym = left as usize;
+            } else {
+                offset -= left_len + 1;
+                sym = right as usize;
+            }
+        }
+
+        Ok(self.btree[sym].0 as u32)
+    }
+}
+
+/// The parts of a table's material that decide how positions are indexed
+#[derive(Clone, Copy)]
+struct TableShape {
+    num_pieces: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool, // Some piece type other than the king appears once on a side
+    pawn_count: [usize; 2],  // Pawns of the leading color, then the other's
+}
+
+/// A WDL or DTZ file
+struct Table {
+    file: TableFile,
+    kind: TableKind,
+    symmetric: bool, // Same material on both sides
+    shape: TableShape,
+    pairs: Vec<Vec<PairsData>>, // [side to move][leading pawn's file]
+    dtz_map: Vec<u8>,
+}
+
+impl Table {
+    /// Read the header and indexes of the table for `name` (e.g. "KRvK")
+    fn open(path: &Path, name: &str, kind: TableKind) -> io::Result<Table> {
+        let file = TableFile::open(path)?;
+        let mut reader = Reader {
+            file: &file,
+            offset: 0,
+        };
+        if reader.bytes(4)? != kind.magic() {
+            return Err(corrupt("not a Syzygy table"));
+        }
+
+        let (white, black) = name.split_once('v').unwrap_or((name, ""));
+        let num_pieces = white.len() + black.len();
+        let symmetric = white == black;
+        let has_pawns = name.contains('P');
+        let white_pawns = white.matches('P').count();
+        let black_pawns = black.matches('P').count();
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = if white_leads {
+            [white_pawns, black_pawns]
+        } else {
+            [black_pawns, white_pawns]
+        };
+        let has_unique_pieces = [white, black].iter().any(|side| {
+            "QRBNP"
+                .chars()
+                .any(|letter| side.matches(letter).count() == 1)
+        });
+        let shape = TableShape {
+            num_pieces,
+            has_pawns,
+            has_unique_pieces,
+            pawn_count,
+        };
+
+        let header = reader.u8()?;
+        if (header & HEADER_HAS_PAWNS != 0) != has_pawns
+            || (kind == TableKind::Wdl && (header & HEADER_SPLIT != 0) == symmetric)
+        {
+            return Err(corrupt("header does not match the table name"));
+        }
+
+        let num_sides = if kind == TableKind::Wdl && !symmetric {
+            2
+        } else {
+            1
+        };
+        let num_files = if has_pawns { 4 } else { 1 };
+        let pawns_on_both_sides = has_pawns && pawn_count[1] > 0;
+        let mut pairs: Vec<Vec<PairsData>> = (0..num_sides)
+            .map(|_| (0..num_files).map(|_| PairsData::default()).collect())
+            .collect();
+
+        for file in 0..num_files {
+            let order_byte = reader.u8()?;
+            let pawn_order_byte = if pawns_on_both_sides {
+                reader.u8()?
+            } else {
+                0xFF
+            };
+            let order = [
+                [order_byte & 0xF, pawn_order_byte & 0xF],
+                [order_byte >> 4, pawn_order_byte >> 4],
+            ];
+
+            for k in 0..num_pieces {
+                let byte = reader.u8()?;
+                for (side, side_pairs) in pairs.iter_mut().enumerate() {
+                    side_pairs[file].pieces[k] = if side == 0 { byte & 0xF } else { byte >> 4 };
+                }
+            }
+
+            for (side, side_pairs) in pairs.iter_mut().enumerate() {
+                side_pairs[file].set_groups(order[side], file, &shape);
+            }
+        }
+        reader.align(2);
+
+        for file in 0..num_files {
+            for side_pairs in pairs.iter_mut() {
+                side_pairs[file].read_sizes(&mut reader)?;
+            }
+        }
+
+        let mut dtz_map = Vec::new();
+        if kind == TableKind::Dtz {
+            let map_start = reader.offset;
+            for data in pairs[0].iter_mut() {
+                if data.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                if data.flags & FLAG_WIDE != 0 {
+                    reader.align(2);
+                    for map_index in data.dtz_map_index.iter_mut() {
+                        *map_index = ((reader.offset - map_start) / 2) as usize + 1;
+                        let len = reader.u16()? as u64;
+                        reader.skip(2 * len);
+                    }
+                } else {
+                    for map_index in data.dtz_map_index.iter_mut() {
+                        *map_index = (reader.offset - map_start) as usize + 1;
+                        let len = reader.u8()? as u64;
+                        reader.skip(len);
+                    }
+                }
+            }
+            reader.align(2);
+
+            let map_len = (reader.offset - map_start) as usize;
+            reader.offset = map_start;
+            dtz_map = reader.bytes(map_len)?;
+        }
+
+        for file in 0..num_files {
+            for side_pairs in pairs.iter_mut() {
+                let data = &mut side_pairs[file];
+                let bytes = reader.bytes(data.sparse_index_len() * 6)?;
+                data.sparse_index = bytes
+                    .chunks_exact(6)
+                    .map(|entry| {
+                        let block = u32::from_le_bytes(entry[..4].try_into().unwrap());
+                        let offset = u16::from_le_bytes(entry[4..].try_into().unwrap());
+                        (block, offset)
+                    })
+                    .collect();
+            }
+        }
+
+        for file in 0..num_files {
+            for side_pairs in pairs.iter_mut() {
+                let data = &mut side_pairs[file];
+                let bytes = reader.bytes(data.block_lengths_len() * 2)?;
+                data.block_lengths = bytes
+                    .chunks_exact(2)
+                    .map(|entry| u16::from_le_bytes([entry[0], entry[1]]))
+                    .collect();
+            }
+        }
+
+        for file in 0..num_files {
+            for side_pairs in pairs.iter_mut() {
+                let data = &mut side_pairs[file];
+                reader.align(64);
+                data.data_offset = reader.offset;
+                reader.skip(data.num_blocks as u64 * data.block_size);
+            }
+        }
+
+        Ok(Table {
+            file,
+            kind,
+            symmetric,
+            shape,
+            pairs,
+            dtz_map,
+        })
+    }
+
+    /// The stored value for the position: WDL + 2, or DTZ in plies for the given WDL. `None` when
+    /// a DTZ file only stores the other side to move.
+    fn probe(
+        &self,
+        position: &Position,
+        black_stronger: bool,
+        wdl: Wdl,
+    ) -> io::Result<Option<i32>> {
+        let indexing = &*INDEXING;
+
+        // Tables store the stronger side as white, and symmetric ones white to move
+        let black_to_move = position.side == Side::Black;
+        let flip = (self.symmetric && black_to_move) || black_stronger;
+        let (flip_color, flip_squares) = if flip { (8, 56) } else { (0, 0) };
+        let stm = (flip != black_to_move) as usize;
+
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut tb_file = 0;
+
+        if self.shape.has_pawns {
+            // The leading pawns' color in the table, in the position's colors
+            let pawn = self.pairs[0][0].pieces[0] ^ flip_color;
+            let side = if pawn & 8 != 0 {
+                Side::Black
+            } else {
+                Side::White
+            };
+            lead_pawns = position.board.bit_pieces[side as usize][Piece::Pawn as usize].0;
+
+            let mut bits = lead_pawns;
+            while bits != 0 {
+                squares[size] = bits.trailing_zeros() as usize ^ flip_squares;
+                pieces[size] = pawn ^ flip_color;
+                size += 1;
+                bits &= bits - 1;
+            }
+
+            let lead = (0..size)
+                .max_by_key(|&i| indexing.map_pawns[squares[i]])
+                .unwrap_or(0);
+            squares.swap(0, lead);
+            tb_file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+        let lead_pawns_count = size;
+
+        // Symmetric tables without pawns serve both sides to move
+        let stores_stm = (self.pairs[0][tb_file].flags & FLAG_STM) as usize == stm
+            || (self.symmetric && !self.shape.has_pawns);
+        if self.kind == TableKind::Dtz && !stores_stm {
+            return Ok(None);
+        }
+
+        let mut bits = position.board.bit_all.0 & !lead_pawns;
+        while bits != 0 {
+            let square = bits.trailing_zeros() as usize;
+            let piece = position.board.value[square];
+            let side = if position.board.bit_units[Side::White as usize].0 & (1 << square) != 0 {
+                Side::White
+            } else {
+                Side::Black
+            };
+            squares[size] = square ^ flip_squares;
+            pieces[size] = table_piece(piece, side) ^ flip_color;
+            size += 1;
+            bits &= bits - 1;
+        }
+
+        let data = &self.pairs[stm % self.pairs.len()][tb_file];
+
+        // Put the pieces in the table's order
+        for i in lead_pawns_count..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if data.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // The leading piece is kept on files a-d
+        if file_of(squares[0]) > 3 {
+            for square in squares[..size].iter_mut() {
+                *square ^= 7;
+            }
+        }
+
+        let mut index;
+        if self.shape.has_pawns {
+            index = indexing.lead_pawn_index[lead_pawns_count][squares[0]];
+            squares[1..lead_pawns_count].sort_by_key(|&square| indexing.map_pawns[square]);
+            for (i, &square) in squares[1..lead_pawns_count].iter().enumerate() {
+                index += indexing.binomial[i + 1][indexing.map_pawns[square]];
+            }
+        } else {
+            // ...and without pawns on ranks 1-4, below the a1-h8 diagonal
+            if rank_of(squares[0]) > 3 {
+                for square in squares[..size].iter_mut() {
+                    *square ^= 56;
+                }
+            }
+            for i in 0..data.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for square in squares[i..size].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            if self.shape.has_unique_pieces {
+                let adjust1 = (squares[1] > squares[0]) as usize;
+                let adjust2 =
+                    (squares[2] > squares[0]) as usize + (squares[2] > squares[1]) as usize;
+
+                index = if off_diagonal(squares[0]) != 0 {
+                    ((indexing.map_a1d1d4[squares[0]] * 63 + squares[1] - adjust1) * 62
+                        + squares[2]
+                        - adjust2) as u64
+                } else if off_diagonal(squares[1]) != 0 {
+                    ((6 * 63 + rank_of(squares[0]) * 28) as u64 + indexing.map_b1h1h7[squares[1]])
+                        * 62
+                        + (squares[2] - adjust2) as u64
+                } else if off_diagonal(squares[2]) != 0 {
+                    (6 * 63 * 62
+                        + 4 * 28 * 62
+                        + rank_of(squares[0]) * 7 * 28
+                        + (rank_of(squares[1]) - adjust1) * 28) as u64
+                        + indexing.map_b1h1h7[squares[2]]
+                } else {
+                    (6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank_of(squares[0]) * 7 * 6
+                        + (rank_of(squares[1]) - adjust1) * 6
+                        + (rank_of(squares[2]) - adjust2)) as u64
+                };
+            } else {
+                index = indexing.map_kk[indexing.map_a1d1d4[squares[0]]][squares[1]];
+            }
+        }
+
+        // The other groups' squares, skipping those already taken by earlier groups
+        index *= data.group_index[0];
+        let mut group_start = data.group_len[0];
+        let mut remaining_pawns = self.shape.has_pawns && self.shape.pawn_count[1] > 0;
+        let mut next = 1;
+        while data.group_len[next] != 0 {
+            let len = data.group_len[next];
+            squares[group_start..group_start + len].sort_unstable();
+
+            let mut group_index = 0;
+            for i in 0..len {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start]
+                    .iter()
+                    .filter(|&&earlier| square > earlier)
+                    .count();
+                let rank_skip = if remaining_pawns { 8 } else { 0 };
+                group_index += indexing.binomial[i + 1][square - adjust - rank_skip];
+            }
+
+            remaining_pawns = false;
+            index += group_index * data.group_index[next];
+            group_start += len;
+            next += 1;
+        }
+
+        let value = data.decompress(&self.file, index)? as i32;
+        Ok(Some(self.map_score(data, value, wdl)))
+    }
+
+    /// Convert a decompressed value to WDL + 2, or to DTZ in plies
+    fn map_score(&self, data: &PairsData, value: i32, wdl: Wdl) -> i32 {
+        if self.kind == TableKind::Wdl {
+            return value;
+        }
+
+        // Index of the value map for each WDL (loss to win)
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+        let mut value = value;
+        if data.flags & FLAG_MAPPED != 0 {
+            let index = data.dtz_map_index[WDL_MAP[(wdl as i32 + 2) as usize]] + value as usize;
+            value = if data.flags & FLAG_WIDE != 0 {
+                self.dtz_map
+                    .get(2 * index..2 * index + 2)
+                    .map_or(0, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as i32)
+            } else {
+                self.dtz_map.get(index).map_or(0, |&byte| byte as i32)
+            };
+        }
+
+        // Values are in moves unless the flags say plies; cursed results are always in moves
+        let in_moves = match wdl {
+            Wdl::Win => data.flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => data.flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+
+        value + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_tables() {
+        let indexing = &*INDEXING;
+
+        let max_kk = indexing.map_kk.iter().flatten().max().unwrap();
+        assert_eq!(*max_kk, 461, "462 ways to place two kings up to symmetry");
+        assert_eq!(*indexing.map_b1h1h7.iter().max().unwrap(), 27);
+        assert_eq!(*indexing.map_a1d1d4.iter().max().unwrap(), 9);
+        assert_eq!(indexing.binomial[2][5], 10);
+        assert_eq!(indexing.binomial[3][48], 17_296);
+        assert_eq!(*indexing.map_pawns.iter().max().unwrap(), 47);
+        assert_eq!(
+            indexing.lead_pawns_size[1].iter().sum::<u64>(),
+            24,
+            "One leading pawn on files a-d, ranks 2-7"
+        );
+    }
+
+    #[test]
+    fn test_material_keys_match_table_names() {
+        assert_eq!(table_sides("KRPvKN"), Some(("KRP", "KN")));
+        assert_eq!(table_sides("KRPKN"), None);
+        assert_eq!(table_sides("KRvKK"), None);
+
+        let position = Position::from_fen("8/8/8/8/8/1kn5/2RP4/7K w - - 0 1").unwrap();
+        let white = position_material(&position, Side::White);
+        let black = position_material(&position, Side::Black);
+        assert_eq!(white, name_material("KRP"));
+        assert_eq!(black, name_material("KN"));
+        assert_ne!(material_key(white, black), material_key(black, white));
+    }
+
+    #[test]
+    fn test_decompress_fixed_length_codes() {
+        // Four values, each a 2-bit code: symbols 0-3 are leaves for values 10-13. Blocks of 8
+        // bytes hold 31 values (the last two bits are padding), so decoding past the 16th value
+        // refills the bit buffer. Each sparse entry covers 16 indexes.
+        let path =
+            std::env::temp_dir().join(format!("syzygy_decompress_{}.bin", std::process::id()));
+        let values: Vec<u8> = (0..62).map(|i| (i * 7 % 4) as u8).collect();
+        let mut bytes = Vec::new();
+        for block in values.chunks(31) {
+            let mut bits: u64 = 0;
+            for (i, &value) in block.iter().enumerate() {
+                bits |= (value as u64) << (62 - 2 * i);
+            }
+            bytes.extend_from_slice(&bits.to_be_bytes());
+        }
+        fs::write(&path, &bytes).unwrap();
+        let file = TableFile::open(&path).unwrap();
+
+        let data = PairsData {
+            block_size: 8,
+            span: 16,
+            num_blocks: 2,
+            min_sym_len: 2,
+            lowest_sym: vec![0],
+            base64: vec![0],
+            btree: (10..14).map(|value| (value, 0xFFF)).collect(),
+            symlen: vec![0; 4],
+            sparse_index: vec![(0, 8), (0, 24), (1, 9), (1, 25)],
+            block_lengths: vec![30, 30],
+            ..Default::default()
+        };
+
+        for (index, &value) in values.iter().enumerate() {
+            assert_eq!(
+                data.decompress(&file, index as u64).unwrap(),
+                10 + value as u32,
+                "Value at index {}",
+                index
+            );
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_dtz_rank_prefers_fast_wins_and_slow_losses() {
+        assert_eq!(dtz_rank(0, 0, false), 0);
+        assert!(dtz_rank(3, 0, false) >= dtz_rank(21, 0, false));
+        assert!(dtz_rank(-3, 0, false) <= dtz_rank(-21, 0, false));
+        assert!(
+            dtz_rank(90, 20, false) < MAX_DTZ - 100,
+            "Spoiled by the fifty-move rule"
+        );
+        assert!(dtz_rank(90, 20, false) > 0);
+    }
+}
@@ -1,14 +1,27 @@
-use std::time::{Duration, Instant};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
-use crate::constants::{DEFAULT_PLAYER_TIME_REMAINING_MS, SOFT_TO_HARD_LIMIT_RATIO};
+use crate::constants::{
+    DEFAULT_MOVES_TO_GO, DEFAULT_PLAYER_TIME_REMAINING_MS, SOFT_TO_HARD_LIMIT_RATIO,
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct TimeManager {
     pub start_time: Instant,
     pub soft_limit: Duration,
     pub hard_limit: Duration,
     pub fixed_time: bool,
-    pub stopped: bool,
+    /// Raised when the search must stop: time ran out, the node limit was hit,
+    /// or an external caller (e.g. UCI `stop`) asked for it. Shared with `Engine`.
+    pub stop_signal: Arc<AtomicBool>,
+    /// Raised while pondering. Time limits are ignored until it is lowered (UCI `ponderhit`).
+    ponder_signal: Arc<AtomicBool>,
+    pondering: bool,
 }
 
 /// For ease of use in tests
@@ -19,7 +32,9 @@ impl Default for TimeManager {
             soft_limit: Duration::from_millis(DEFAULT_PLAYER_TIME_REMAINING_MS),
             hard_limit: Duration::from_millis(DEFAULT_PLAYER_TIME_REMAINING_MS),
             fixed_time: false,
-            stopped: false,
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            ponder_signal: Arc::new(AtomicBool::new(false)),
+            pondering: false,
         }
     }
 }
@@ -27,11 +42,12 @@ impl Default for TimeManager {
 impl TimeManager {
     /// Create a new TimeManager for each search
     pub fn new(
-        wtime: u64,            // Remaining white time (ms)
-        btime: u64,            // Remaining black time (ms)
-        winc: u64,             // White increment (ms)
-        binc: u64,             // Black increment (ms)
-        movetime: Option<u64>, // Explicit per-move time in ms
+        wtime: u64,             // Remaining white time (ms)
+        btime: u64,             // Remaining black time (ms)
+        winc: u64,              // White increment (ms)
+        binc: u64,              // Black increment (ms)
+        movestogo: Option<u64>, // Moves until the next time control
+        movetime: Option<u64>,  // Explicit per-move time in ms
         is_white_turn: bool,
     ) -> Self {
         let start_time = Instant::now();
@@ -44,8 +60,10 @@ impl TimeManager {
                     false => (btime, binc),
                 };
 
-                // Use 1/30 of remaining time + increment, but never more than 25% of total
-                (time_left / 30 + increment).min(time_left / 4)
+                // Split the remaining time over the moves left (assume 30 if unknown),
+                // but never use more than 25% of it on one move
+                let moves_left = movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                (time_left / moves_left + increment).min(time_left / 4)
             }
         };
 
@@ -63,13 +81,56 @@ impl TimeManager {
             soft_limit: Duration::from_millis(soft_limit_ms),
             hard_limit: Duration::from_millis(hard_limit_ms),
             fixed_time: movetime.is_some(),
-            stopped: false,
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            ponder_signal: Arc::new(AtomicBool::new(false)),
+            pondering: false,
         }
     }
 
+    /// A TimeManager that never runs out of time, e.g. for "go infinite". The search
+    /// ends at its depth limit or when stopped.
+    pub fn unlimited() -> Self {
+        Self {
+            start_time: Instant::now(),
+            soft_limit: Duration::MAX,
+            hard_limit: Duration::MAX,
+            fixed_time: false,
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            ponder_signal: Arc::new(AtomicBool::new(false)),
+            pondering: false,
+        }
+    }
+
+    /// Share the signal that is raised while pondering. The search starts pondering
+    /// if it is raised now, and the clock starts when it is lowered.
+    pub fn set_ponder_signal(&mut self, ponder_signal: Arc<AtomicBool>) {
+        self.pondering = ponder_signal.load(Ordering::Relaxed);
+        self.ponder_signal = ponder_signal;
+    }
+
+    /// Whether the search is still pondering. Restarts the clock once pondering ends.
+    pub fn is_pondering(&mut self) -> bool {
+        if self.pondering && !self.ponder_signal.load(Ordering::Relaxed) {
+            self.pondering = false;
+            self.start_time = Instant::now();
+        }
+
+        self.pondering
+    }
+
     pub fn reset_for_next_move(&mut self) {
         self.start_time = Instant::now();
-        self.stopped = false;
+        self.stop_signal.store(false, Ordering::Relaxed);
+    }
+
+    /// Signal the search to stop at the next check
+    pub fn stop(&self) {
+        self.stop_signal.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the search has been signalled to stop
+    pub fn is_stopped(&self) -> bool {
+        self.stop_signal.load(Ordering::Relaxed)
     }
 
     /// Time since search began
@@ -78,12 +139,12 @@ impl TimeManager {
     }
 
     /// Check if we should stop the current iteration
-    pub fn is_soft_limit_reached(&self) -> bool {
-        !self.fixed_time && self.elapsed() >= self.soft_limit
+    pub fn is_soft_limit_reached(&mut self) -> bool {
+        !self.is_pondering() && !self.fixed_time && self.elapsed() >= self.soft_limit
     }
 
     /// Check if we must abort immediately
-    pub fn is_hard_limit_reached(&self) -> bool {
-        self.elapsed() >= self.hard_limit
+    pub fn is_hard_limit_reached(&mut self) -> bool {
+        !self.is_pondering() && self.elapsed() >= self.hard_limit
     }
 }
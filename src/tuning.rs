@@ -0,0 +1,289 @@
+use std::{fs, io, sync::Arc, thread};
+
+use crate::{
+    params::EvalParams,
+    position::Position,
+    types::{Piece, Side, Square},
+};
+
+/// A position from a tuning set, with the result of the game it was taken from
+pub struct TuningPosition {
+    pieces: Vec<(Side, Piece, Square)>,
+    side: Side,
+    pub result: f64, // For White: 1 for a win, 0.5 for a draw, 0 for a loss
+}
+
+impl TuningPosition {
+    /// Parse a FEN or EPD line ending with the game's result, e.g.
+    /// - `<fen> [1.0]`, `<fen> [0.5]` or `<fen> [0.0]`
+    /// - `<epd> c9 "1-0";`, `c9 "1/2-1/2";` or `c9 "0-1";`
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        if fields.len() < 3 {
+            return Err(format!("Expected a FEN and a result: {}", line));
+        }
+
+        let result_field = fields[fields.len() - 1].trim_matches(|c| "\"[];".contains(c));
+        let result = match result_field {
+            "1-0" => 1.0,
+            "1/2-1/2" => 0.5,
+            "0-1" => 0.0,
+            _ => match result_field.parse::<f64>() {
+                Ok(result) if (0.0..=1.0).contains(&result) => result,
+                _ => return Err(format!("Invalid result: {}", fields[fields.len() - 1])),
+            },
+        };
+
+        let side = match fields[1] {
+            "w" => Side::White,
+            "b" => Side::Black,
+            _ => return Err(format!("Invalid side to move: {}", fields[1])),
+        };
+
+        let mut pieces = Vec::new();
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+
+        if ranks.len() != 8 {
+            return Err(format!("Invalid FEN board: {}", fields[0]));
+        }
+
+        // FEN starts at rank 8
+        for (rank_idx, rank_str) in ranks.iter().enumerate() {
+            let rank = 7 - rank_idx;
+            let mut file = 0;
+
+            for ch in rank_str.chars() {
+                if let Some(empty_squares) = ch.to_digit(10) {
+                    file += empty_squares as usize;
+                    continue;
+                }
+
+                let piece = match ch.to_ascii_lowercase() {
+                    'p' => Piece::Pawn,
+                    'n' => Piece::Knight,
+                    'b' => Piece::Bishop,
+                    'r' => Piece::Rook,
+                    'q' => Piece::Queen,
+                    'k' => Piece::King,
+                    _ => return Err(format!("Invalid piece character: {}", ch)),
+                };
+
+                let side = match ch.is_ascii_uppercase() {
+                    true => Side::White,
+                    false => Side::Black,
+                };
+
+                if file >= 8 {
+                    return Err(format!(
+                        "Invalid FEN: too many squares in rank {}",
+                        rank + 1
+                    ));
+                }
+
+                pieces.push((side, piece, Square::try_from((rank * 8 + file) as u8)?));
+                file += 1;
+            }
+
+            if file != 8 {
+                return Err(format!("Invalid FEN: rank {} isn't 8 squares", rank + 1));
+            }
+        }
+
+        for side in Side::iter() {
+            if pieces
+                .iter()
+                .filter(|&&(s, piece, _)| s == side && piece == Piece::King)
+                .count()
+                != 1
+            {
+                return Err(format!("Expected one king for each side: {}", fields[0]));
+            }
+        }
+
+        Ok(Self {
+            pieces,
+            side,
+            result,
+        })
+    }
+
+    /// Set up `position` with this position's pieces and side to move
+    fn set_up(&self, position: &mut Position) {
+        for square in Square::iter() {
+            let piece = position.board.value[square as usize];
+
+            if piece != Piece::Empty {
+                let side = match position.board.bit_units[Side::White as usize].is_bit_set(square) {
+                    true => Side::White,
+                    false => Side::Black,
+                };
+
+                position.board.remove_piece(side, piece, square);
+            }
+        }
+
+        for &(side, piece, square) in &self.pieces {
+            position.board.add_piece(side, piece, square);
+        }
+
+        position.side = self.side;
+        position.set_material_scores();
+    }
+}
+
+/// Read a tuning set with one position per line (see `TuningPosition::parse`). Blank lines and
+/// lines starting with `#` are skipped.
+pub fn load_positions(path: &str) -> io::Result<Vec<TuningPosition>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            TuningPosition::parse(line)
+                .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+        })
+        .collect()
+}
+
+/// Texel tuning (https://www.chessprogramming.org/Texel%27s_Tuning_Method): adjusts the
+/// evaluation parameters so the static evaluation of each position predicts the result of its
+/// game. The evaluation is turned into an expected score with a sigmoid scaled by `k`.
+///
+/// Positions should be quiet (no captures or checks pending), since no search is done.
+pub struct Tuner {
+    positions: Vec<TuningPosition>,
+    workers: Vec<Position>, // One per thread, set up again for each position evaluated
+    pub k: f64,
+}
+
+impl Tuner {
+    pub fn new(positions: Vec<TuningPosition>, threads: usize) -> Self {
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let mut position = Position::default();
+                position.board.hash.resize_table(0); // Evaluation doesn't use it
+                position
+            })
+            .collect();
+
+        Self {
+            positions,
+            workers,
+            k: 1.0,
+        }
+    }
+
+    pub fn num_positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Mean squared difference between each game's result and the expected score from the
+    /// evaluation with `params`
+    pub fn error(&mut self, params: &EvalParams) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+
+        let params = Arc::new(params.clone());
+        let k = self.k;
+        let chunk_size = self.positions.len().div_ceil(self.workers.len());
+
+        let total: f64 = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .positions
+                .chunks(chunk_size)
+                .zip(self.workers.iter_mut())
+                .map(|(positions, worker)| {
+                    worker.set_eval_params(Arc::clone(&params));
+
+                    scope.spawn(move || {
+                        positions
+                            .iter()
+                            .map(|position| {
+                                position.set_up(worker);
+
+                                let evaluation = worker.evaluate_handcrafted();
+
+                                // From White's point of view, like the result
+                                let evaluation = match worker.side {
+                                    Side::White => evaluation,
+                                    Side::Black => -evaluation,
+                                };
+
+                                (position.result - expected_score(evaluation, k)).powi(2)
+                            })
+                            .sum::<f64>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Tuning thread panicked"))
+                .sum()
+        });
+
+        total / self.positions.len() as f64
+    }
+
+    /// Set `k` to the value that best fits the evaluation with `params` to the results, before
+    /// any parameters change. Searches to 3 decimal places.
+    pub fn fit_k(&mut self, params: &EvalParams) -> f64 {
+        let mut best_k: f64 = 1.0;
+        let mut step = 1.0;
+
+        for _ in 0..4 {
+            let (start, end) = ((best_k - 10.0 * step).max(0.0), best_k + 10.0 * step);
+            let mut best_error = f64::MAX;
+            let mut k = start;
+
+            while k <= end {
+                self.k = k;
+                let error = self.error(params);
+
+                if error < best_error {
+                    best_error = error;
+                    best_k = k;
+                }
+
+                k += step;
+            }
+
+            step /= 10.0;
+        }
+
+        self.k = best_k;
+        best_k
+    }
+
+    /// One pass of local search: moves each parameter up or down by `step` if that lowers the
+    /// error. Returns the error afterwards; the pass made no change if it equals `error`.
+    pub fn local_search_pass(&mut self, params: &mut EvalParams, error: f64, step: i32) -> f64 {
+        let mut values = params.values();
+        let mut best_error = error;
+
+        for i in 0..values.len() {
+            for delta in [step, -step] {
+                values[i] += delta;
+
+                let new_error = self.error(&EvalParams::from_values(&values));
+
+                if new_error < best_error {
+                    best_error = new_error;
+                    break;
+                }
+
+                values[i] -= delta;
+            }
+        }
+
+        *params = EvalParams::from_values(&values);
+        best_error
+    }
+}
+
+/// Expected score for White (0 to 1) from an evaluation in centipawns
+fn expected_score(evaluation: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * evaluation as f64 / 400.0))
+}
@@ -1,6 +1,9 @@
+use std::cmp::Ordering;
+
 use crate::{
-    constants::{NUM_PIECE_TYPES, NUM_SIDES, NUM_SQUARES},
+    constants::{MATE_SCORE, MATE_THRESHOLD, MAX_PHASE, NUM_PIECE_TYPES, NUM_SIDES, NUM_SQUARES},
     hash::Hash,
+    nnue::Accumulator,
 };
 
 /// Right-most bit represents A1
@@ -21,12 +24,14 @@ pub enum Square {
   A8, B8, C8, D8, E8, F8, G8, H8,
 }
 
+#[derive(Clone)]
 pub struct Board {
     pub value: [Piece; NUM_SQUARES],
     pub bit_pieces: [[BitBoard; NUM_PIECE_TYPES]; NUM_SIDES], // [side][piece]
     pub bit_units: [BitBoard; NUM_SIDES],                     // [side]
     pub bit_all: BitBoard,
     pub hash: Hash,
+    pub nnue: Option<Accumulator>, // Kept up to date like `hash`, while a network is loaded
 }
 
 #[repr(u8)]
@@ -51,7 +56,333 @@ pub enum GameState {
     DrawByInsufficientMaterial,
 }
 
-#[derive(Debug)]
+/// Why a search was aborted before completing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SearchAbort {
+    TimeExhausted,
+    NodeLimitReached,
+    Stopped, // Stop signal raised externally (or by another search thread)
+}
+
+impl std::fmt::Display for SearchAbort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchAbort::TimeExhausted => write!(f, "Search time exhausted"),
+            SearchAbort::NodeLimitReached => write!(f, "Search node limit reached"),
+            SearchAbort::Stopped => write!(f, "Search stopped"),
+        }
+    }
+}
+
+/// A search score from the side to move's point of view
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    /// Forced mate in N moves. Negative if the side to move is getting mated.
+    Mate(i32),
+}
+
+impl Score {
+    /// Converts a raw search score, where mates are `MATE_SCORE` minus the ply of the mate
+    pub fn from_search_score(score: i32) -> Self {
+        if score > MATE_THRESHOLD {
+            let plies = MATE_SCORE - score;
+            Score::Mate((plies + 1) / 2)
+        } else if score < -MATE_THRESHOLD {
+            let plies = MATE_SCORE + score;
+            Score::Mate(-plies / 2)
+        } else {
+            Score::Centipawns(score)
+        }
+    }
+
+    /// The equivalent raw search score (mates are rounded to the nearest ply)
+    pub fn to_search_score(&self) -> i32 {
+        match *self {
+            Score::Centipawns(score) => score,
+            Score::Mate(moves) if moves > 0 => MATE_SCORE - (2 * moves - 1),
+            Score::Mate(moves) => -MATE_SCORE - 2 * moves,
+        }
+    }
+
+    pub fn is_mate(&self) -> bool {
+        matches!(self, Score::Mate(_))
+    }
+
+    /// The score in UCI `info` format, e.g. "cp 35" or "mate -3"
+    pub fn to_uci_string(&self) -> String {
+        match self {
+            Score::Centipawns(score) => format!("cp {}", score),
+            Score::Mate(moves) => format!("mate {}", moves),
+        }
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_search_score().cmp(&other.to_search_score())
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Centipawns as a number, mates as e.g. "M3" or "-M3"
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(score) => write!(f, "{}", score),
+            Score::Mate(moves) if *moves < 0 => write!(f, "-M{}", -moves),
+            Score::Mate(moves) => write!(f, "M{}", moves),
+        }
+    }
+}
+
+/// Whether a score is exact, or only a bound because it fell outside the search window
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ScoreBound {
+    #[default]
+    Exact,
+    Lower, // Failed high: the score is at least this
+    Upper, // Failed low: the score is at most this
+}
+
+/// An evaluation term with separate midgame and endgame values, blended by the game phase
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaperedScore {
+    pub midgame: i32,
+    pub endgame: i32,
+}
+
+impl TaperedScore {
+    pub const fn new(midgame: i32, endgame: i32) -> Self {
+        Self { midgame, endgame }
+    }
+
+    /// Interpolate between the endgame (`phase` 0) and midgame (`phase` `MAX_PHASE`) values
+    pub fn taper(self, phase: i32) -> i32 {
+        (self.midgame * phase + self.endgame * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl std::ops::Add for TaperedScore {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.midgame + other.midgame, self.endgame + other.endgame)
+    }
+}
+
+impl std::ops::AddAssign for TaperedScore {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl std::ops::Sub for TaperedScore {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.midgame - other.midgame, self.endgame - other.endgame)
+    }
+}
+
+impl std::ops::Mul<i32> for TaperedScore {
+    type Output = Self;
+
+    fn mul(self, count: i32) -> Self {
+        Self::new(self.midgame * count, self.endgame * count)
+    }
+}
+
+/// A part of the handcrafted evaluation, as reported by `Position::evaluate_with_trace`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EvalTerm {
+    Material,
+    PawnSquares, // Piece-square tables
+    KnightSquares,
+    BishopSquares,
+    RookSquares,
+    QueenSquares,
+    KingSquares,
+    PassedPawns,
+    IsolatedPawns,
+    DoubledPawns,
+    BackwardPawns,
+    Mobility,
+    BishopPair,
+    KnightOutposts,
+    RookFiles, // Open and half-open files
+    RookOnSeventh,
+    ConnectedRooks,
+    KingShelter, // Pawn shield in front of a castled king
+    KingSafety,  // Attacks against the king
+}
+
+impl EvalTerm {
+    pub const COUNT: usize = 19;
+
+    pub fn iter() -> impl Iterator<Item = EvalTerm> {
+        [
+            EvalTerm::Material,
+            EvalTerm::PawnSquares,
+            EvalTerm::KnightSquares,
+            EvalTerm::BishopSquares,
+            EvalTerm::RookSquares,
+            EvalTerm::QueenSquares,
+            EvalTerm::KingSquares,
+            EvalTerm::PassedPawns,
+            EvalTerm::IsolatedPawns,
+            EvalTerm::DoubledPawns,
+            EvalTerm::BackwardPawns,
+            EvalTerm::Mobility,
+            EvalTerm::BishopPair,
+            EvalTerm::KnightOutposts,
+            EvalTerm::RookFiles,
+            EvalTerm::RookOnSeventh,
+            EvalTerm::ConnectedRooks,
+            EvalTerm::KingShelter,
+            EvalTerm::KingSafety,
+        ]
+        .into_iter()
+    }
+
+    /// The piece-square table term for `piece`
+    pub fn piece_squares(piece: Piece) -> EvalTerm {
+        match piece {
+            Piece::Pawn => EvalTerm::PawnSquares,
+            Piece::Knight => EvalTerm::KnightSquares,
+            Piece::Bishop => EvalTerm::BishopSquares,
+            Piece::Rook => EvalTerm::RookSquares,
+            Piece::Queen => EvalTerm::QueenSquares,
+            Piece::King | Piece::Empty => EvalTerm::KingSquares,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EvalTerm::Material => "Material",
+            EvalTerm::PawnSquares => "Pawn squares",
+            EvalTerm::KnightSquares => "Knight squares",
+            EvalTerm::BishopSquares => "Bishop squares",
+            EvalTerm::RookSquares => "Rook squares",
+            EvalTerm::QueenSquares => "Queen squares",
+            EvalTerm::KingSquares => "King squares",
+            EvalTerm::PassedPawns => "Passed pawns",
+            EvalTerm::IsolatedPawns => "Isolated pawns",
+            EvalTerm::DoubledPawns => "Doubled pawns",
+            EvalTerm::BackwardPawns => "Backward pawns",
+            EvalTerm::Mobility => "Mobility",
+            EvalTerm::BishopPair => "Bishop pair",
+            EvalTerm::KnightOutposts => "Knight outposts",
+            EvalTerm::RookFiles => "Rook files",
+            EvalTerm::RookOnSeventh => "Rook on 7th",
+            EvalTerm::ConnectedRooks => "Connected rooks",
+            EvalTerm::KingShelter => "King shelter",
+            EvalTerm::KingSafety => "King safety",
+        }
+    }
+}
+
+/// Each term's midgame and endgame contribution to a handcrafted evaluation, for each side
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvalTrace {
+    pub terms: [[TaperedScore; NUM_SIDES]; EvalTerm::COUNT], // [term][side]
+    pub phase: i32,
+    pub side_to_move: Side,
+}
+
+impl EvalTrace {
+    pub fn get(&self, term: EvalTerm, side: Side) -> TaperedScore {
+        self.terms[term as usize][side as usize]
+    }
+
+    /// All of `side`'s terms added up
+    pub fn side_total(&self, side: Side) -> TaperedScore {
+        self.terms
+            .iter()
+            .fold(TaperedScore::default(), |total, term| {
+                total + term[side as usize]
+            })
+    }
+
+    /// White's score minus Black's, blended by the game phase. Equals `Position::evaluate` with
+    /// White to move. Tapering each term separately can round differently.
+    pub fn total(&self) -> i32 {
+        (self.side_total(Side::White) - self.side_total(Side::Black)).taper(self.phase)
+    }
+}
+
+/// A table of every term in pawns: each side's midgame and endgame values, then White's minus
+/// Black's
+impl std::fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pawns = |centipawns: i32| format!("{:.2}", centipawns as f64 / 100.0);
+        let row = |f: &mut std::fmt::Formatter<'_>, name: &str, white: TaperedScore, black| {
+            let difference: TaperedScore = white - black;
+
+            writeln!(
+                f,
+                "{:<16} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}",
+                name,
+                pawns(white.midgame),
+                pawns(white.endgame),
+                pawns(black.midgame),
+                pawns(black.endgame),
+                pawns(difference.midgame),
+                pawns(difference.endgame),
+            )
+        };
+        let separator = format!(
+            "{}+{}+{}+{}",
+            "-".repeat(17),
+            "-".repeat(15),
+            "-".repeat(15),
+            "-".repeat(14)
+        );
+
+        writeln!(
+            f,
+            "{:<16} | {:^13} | {:^13} | {:^13}",
+            "Term", "White", "Black", "Total"
+        )?;
+        writeln!(
+            f,
+            "{:<16} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}",
+            "", "MG", "EG", "MG", "EG", "MG", "EG"
+        )?;
+        writeln!(f, "{}", separator)?;
+
+        for term in EvalTerm::iter() {
+            row(
+                f,
+                term.name(),
+                self.get(term, Side::White),
+                self.get(term, Side::Black),
+            )?;
+        }
+
+        writeln!(f, "{}", separator)?;
+        row(
+            f,
+            "Total",
+            self.side_total(Side::White),
+            self.side_total(Side::Black),
+        )?;
+        writeln!(f)?;
+        writeln!(f, "Game phase: {}/{}", self.phase, MAX_PHASE)?;
+        write!(
+            f,
+            "Evaluation: {:+.2} (White's view)",
+            self.total() as f64 / 100.0
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct MoveData {
     pub from: Square,
     pub to: Square,
@@ -161,6 +492,15 @@ impl Difficulty {
         }
     }
 
+    /// Maps a UCI "Skill Level" (0-20) to a difficulty. 20 is full strength (`None`).
+    pub fn from_skill_level(skill_level: u8) -> Option<Difficulty> {
+        if skill_level >= 20 {
+            return None;
+        }
+
+        Difficulty::iter().nth(skill_level as usize * 6 / 20)
+    }
+
     pub fn iter() -> impl Iterator<Item = Difficulty> {
         [
             Difficulty::Beginner,
@@ -1,116 +1,387 @@
 use crate::{
-    engine::Engine,
+    constants::{DEFAULT_BENCH_DEPTH, DEFAULT_MOVE_OVERHEAD_MS, SEARCH_THREAD_STACK_SIZE},
+    engine::{Engine, SearchLine, SearchResult},
+    hash::DEFAULT_HASH_SIZE_MB,
     position::Position,
-    types::{Board, MoveData},
+    types::{Board, Difficulty, MoveData, ScoreBound},
+};
+use std::{
+    io::{self, BufRead, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    thread::{self, ScopedJoinHandle},
 };
-use std::io::{self, Write};
 
 const ENGINE_NAME: &str = "Chess Engine";
 const ENGINE_AUTHOR: &str = "Brendan Dagys";
 
-pub fn uci_loop(engine: &mut Engine) {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+/// The type of a UCI option, with its default and allowed values
+enum UciOptionType {
+    Check { default: bool },
+    Spin { default: i64, min: i64, max: i64 },
+    String { default: &'static str },
+    Button,
+}
 
-    loop {
-        let mut input = String::new();
-        if stdin.read_line(&mut input).is_err() {
-            break;
-        }
+struct UciOption {
+    name: &'static str,
+    option_type: UciOptionType,
+}
+
+/// A validated value for a `setoption` command
+#[derive(Debug, PartialEq)]
+enum UciOptionValue {
+    Check(bool),
+    Spin(i64),
+    String(String),
+    Button,
+}
+
+/// Options advertised in response to `uci` and accepted by `setoption`
+const UCI_OPTIONS: [UciOption; 12] = [
+    UciOption {
+        name: "Hash",
+        option_type: UciOptionType::Spin {
+            default: DEFAULT_HASH_SIZE_MB as i64,
+            min: 1,
+            max: 65536,
+        },
+    },
+    UciOption {
+        name: "Clear Hash",
+        option_type: UciOptionType::Button,
+    },
+    UciOption {
+        name: "Threads",
+        option_type: UciOptionType::Spin {
+            default: 1,
+            min: 1,
+            max: 256,
+        },
+    },
+    UciOption {
+        name: "OwnBook",
+        option_type: UciOptionType::Check { default: true },
+    },
+    UciOption {
+        name: "BookFile",
+        option_type: UciOptionType::String { default: "<empty>" },
+    },
+    UciOption {
+        name: "EvalFile",
+        option_type: UciOptionType::String { default: "<empty>" },
+    },
+    UciOption {
+        name: "EvalParamsFile",
+        option_type: UciOptionType::String { default: "<empty>" },
+    },
+    UciOption {
+        name: "SyzygyPath",
+        option_type: UciOptionType::String { default: "<empty>" },
+    },
+    UciOption {
+        name: "MultiPV",
+        option_type: UciOptionType::Spin {
+            default: 1,
+            min: 1,
+            max: 256,
+        },
+    },
+    UciOption {
+        name: "Move Overhead",
+        option_type: UciOptionType::Spin {
+            default: DEFAULT_MOVE_OVERHEAD_MS as i64,
+            min: 0,
+            max: 5000,
+        },
+    },
+    UciOption {
+        name: "Ponder",
+        option_type: UciOptionType::Check { default: false },
+    },
+    UciOption {
+        name: "Skill Level",
+        option_type: UciOptionType::Spin {
+            default: 20,
+            min: 0,
+            max: 20,
+        },
+    },
+];
+
+impl UciOption {
+    /// The `option ...` line sent in response to `uci`
+    fn declaration(&self) -> String {
+        let details = match self.option_type {
+            UciOptionType::Check { default } => format!("type check default {}", default),
+            UciOptionType::Spin { default, min, max } => {
+                format!("type spin default {} min {} max {}", default, min, max)
+            }
+            UciOptionType::String { default } => format!("type string default {}", default),
+            UciOptionType::Button => "type button".to_string(),
+        };
 
-        let input = input.trim();
-        if input.is_empty() {
-            continue;
+        format!("option name {} {}", self.name, details)
+    }
+
+    /// Validate a `setoption` value against this option's type. Spin values are clamped to range.
+    fn parse_value(&self, value: Option<&str>) -> Result<UciOptionValue, String> {
+        match (&self.option_type, value) {
+            (UciOptionType::Button, _) => Ok(UciOptionValue::Button),
+            (UciOptionType::Check { .. }, Some(value)) => match value.to_lowercase().as_str() {
+                "true" => Ok(UciOptionValue::Check(true)),
+                "false" => Ok(UciOptionValue::Check(false)),
+                _ => Err(format!("Invalid value for {}: {}", self.name, value)),
+            },
+            (UciOptionType::Spin { min, max, .. }, Some(value)) => value
+                .parse::<i64>()
+                .map(|value| UciOptionValue::Spin(value.clamp(*min, *max)))
+                .map_err(|_| format!("Invalid value for {}: {}", self.name, value)),
+            (UciOptionType::String { .. }, Some(value)) => Ok(UciOptionValue::String(
+                if value == "<empty>" { "" } else { value }.to_string(),
+            )),
+            (_, None) => Err(format!("Missing value for {}", self.name)),
         }
+    }
+}
 
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        let command = parts[0];
+pub fn uci_loop(engine: &mut Engine) {
+    let stdin = io::stdin();
+    run_uci_loop(engine, stdin.lock());
+}
 
-        match command {
-            "uci" => {
-                println!("id name {}", ENGINE_NAME);
-                println!("id author {}", ENGINE_AUTHOR);
-                println!("uciok");
-                stdout.flush().unwrap();
-            }
-            "isready" => {
-                println!("readyok");
-                stdout.flush().unwrap();
+/// Read UCI commands from `input` until `quit` or end of input. `go` runs the search on a
+/// worker thread so that `stop`, `ponderhit`, `isready` and `quit` are still answered while it thinks.
+fn run_uci_loop<R: BufRead>(engine: &mut Engine, mut input_reader: R) {
+    let mut stdout = io::stdout();
+    let stop_signal = engine.stop_signal();
+    let ponder_signal = engine.ponder_signal();
+
+    // Set by `stop` (and `quit`) so that a finished `go infinite`/`go ponder` may send `bestmove`
+    let stop_requested = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        // The engine is lent to the worker thread for the duration of a search
+        let mut idle_engine = Some(engine);
+        let mut search: Option<ScopedJoinHandle<&mut Engine>> = None;
+
+        loop {
+            let mut input = String::new();
+            match input_reader.read_line(&mut input) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
             }
-            "ucinewgame" => {
-                engine.new_game();
+
+            let input = input.trim();
+            if input.is_empty() {
+                continue;
             }
-            "position" => {
-                if let Err(e) = parse_position_command(engine, input) {
-                    eprintln!("Error parsing position: {}", e);
+
+            let parts: Vec<&str> = input.split_whitespace().collect();
+            let command = parts[0];
+
+            match command {
+                "isready" => {
+                    println!("readyok");
+                    stdout.flush().unwrap();
                 }
-            }
-            "go" => {
-                parse_go_command(engine, input);
-
-                let result = engine.think(Some(|depth, score, position: &mut Position| {
-                    // Output UCI info line if requested
-                    if let Some(_) = position
-                        .pv_table
-                        .get(0)
-                        .and_then(|ply| ply.get(0))
-                        .and_then(|&m| m)
-                    {
-                        let time_ms = position.time_manager.elapsed().as_millis() as u64;
-                        let nps = if time_ms > 0 {
-                            (position.nodes as u64 * 1000) / time_ms
-                        } else {
-                            0
-                        };
-
-                        // Build PV string from pv_table at root (ply 0)
-                        let mut pv_string = String::new();
-                        for i in 0..position.pv_length[0] {
-                            if let Some(mv) = position.pv_table[0][i] {
-                                if !pv_string.is_empty() {
-                                    pv_string.push(' ');
-                                }
-                                pv_string.push_str(&Board::move_to_uci_string(
-                                    mv.from, mv.to, mv.promote, false,
-                                ));
+                "stop" => {
+                    if let Some(handle) = search.take() {
+                        idle_engine = Some(stop_search(handle, &stop_signal, &stop_requested));
+                    }
+                }
+                "ponderhit" => {
+                    // The opponent played the expected move, so the search continues on our clock
+                    ponder_signal.store(false, Ordering::Relaxed);
+                    if let Some(handle) = &search {
+                        handle.thread().unpark();
+                    }
+                }
+                "quit" => {
+                    break;
+                }
+                _ => {
+                    // Every other command needs the engine, so end the current search first
+                    let engine = match search.take() {
+                        Some(handle) => stop_search(handle, &stop_signal, &stop_requested),
+                        None => idle_engine
+                            .take()
+                            .expect("Engine is idle when not searching"),
+                    };
+
+                    match command {
+                        "uci" => {
+                            println!("id name {}", ENGINE_NAME);
+                            println!("id author {}", ENGINE_AUTHOR);
+                            for option in &UCI_OPTIONS {
+                                println!("{}", option.declaration());
                             }
+                            println!("uciok");
+                            stdout.flush().unwrap();
+                        }
+                        "ucinewgame" => {
+                            engine.new_game();
+                        }
+                        "setoption" => {
+                            if let Err(e) = parse_setoption_command(engine, input) {
+                                eprintln!("Error setting option: {}", e);
+                            }
+                        }
+                        "position" => {
+                            if let Err(e) = parse_position_command(engine, input) {
+                                eprintln!("Error parsing position: {}", e);
+                            }
+                        }
+                        "go" => {
+                            parse_go_command(engine, input);
+
+                            let wait_for_stop = parts.contains(&"infinite");
+                            let ponder_signal = &ponder_signal;
+                            let stop_requested = &stop_requested;
+
+                            let worker = thread::Builder::new()
+                                .stack_size(SEARCH_THREAD_STACK_SIZE)
+                                .spawn_scoped(scope, move || {
+                                    let result = search_and_report(engine);
+
+                                    // `bestmove` must wait for `stop` (or `ponderhit`) when the
+                                    // search ends early in these modes
+                                    while !stop_requested.load(Ordering::Relaxed)
+                                        && (wait_for_stop || ponder_signal.load(Ordering::Relaxed))
+                                    {
+                                        thread::park();
+                                    }
+
+                                    report_best_move(&result);
+                                    engine
+                                })
+                                .expect("Failed to spawn search thread");
+                            search = Some(worker);
+
+                            continue;
+                        }
+                        "d" | "display" => {
+                            engine.position.display_board(false);
+                        }
+                        "eval" => {
+                            println!("{}", engine.evaluation_report());
+                        }
+                        "bench" => {
+                            let depth = parts
+                                .get(1)
+                                .and_then(|depth| depth.parse().ok())
+                                .unwrap_or(DEFAULT_BENCH_DEPTH);
+
+                            let result = Engine::bench(depth);
+                            let nps = result.nodes as u64 * 1000 / result.time_ms.max(1);
+
+                            println!("Total time (ms) : {}", result.time_ms);
+                            println!("Nodes searched  : {}", result.nodes);
+                            println!("Nodes/second    : {}", nps);
+                        }
+                        _ => {
+                            // Unknown command - silently ignore per UCI spec
                         }
-
-                        println!(
-                            "info depth {} seldepth {} score cp {} nodes {} nps {} time {} pv {}",
-                            depth,
-                            position.max_depth_reached,
-                            score,
-                            position.nodes,
-                            nps,
-                            time_ms,
-                            pv_string
-                        );
                     }
-                }));
-
-                // Output the best move
-                if let (Some(from), Some(to)) = (result.best_move_from, result.best_move_to) {
-                    let best_move =
-                        Board::move_to_uci_string(from, to, result.best_move_promote, false);
-                    println!("bestmove {}", best_move);
-                } else {
-                    // No legal moves found
-                    println!("bestmove 0000");
+
+                    idle_engine = Some(engine);
                 }
-                stdout.flush().unwrap();
-            }
-            "stop" => {} // TODO: implement stop functionality
-            "quit" => {
-                break;
-            }
-            "d" | "display" => {
-                engine.position.display_board(false);
-            }
-            _ => {
-                // Unknown command - silently ignore per UCI spec
             }
         }
+
+        if let Some(handle) = search.take() {
+            stop_search(handle, &stop_signal, &stop_requested);
+        }
+    });
+}
+
+/// Signal a running search to stop and wait for it to print `bestmove`
+fn stop_search<'a>(
+    handle: ScopedJoinHandle<'_, &'a mut Engine>,
+    stop_signal: &AtomicBool,
+    stop_requested: &AtomicBool,
+) -> &'a mut Engine {
+    stop_signal.store(true, Ordering::Relaxed);
+    stop_requested.store(true, Ordering::Relaxed);
+    handle.thread().unpark();
+    let engine = handle.join().expect("Search thread panicked");
+
+    // The search may have finished on its own before seeing the signal
+    stop_signal.store(false, Ordering::Relaxed);
+    stop_requested.store(false, Ordering::Relaxed);
+    engine.ponder_signal().store(false, Ordering::Relaxed);
+
+    engine
+}
+
+/// Run a search with the current settings, printing `info` lines
+fn search_and_report(engine: &mut Engine) -> SearchResult {
+    let result = engine.think(Some(
+        |depth, lines: &[SearchLine], position: &mut Position| {
+            let time_ms = position.time_manager.elapsed().as_millis() as u64;
+            let nps = (position.total_nodes() as u64 * 1000)
+                .checked_div(time_ms)
+                .unwrap_or(0);
+
+            // Output one UCI info line per principal variation
+            for (index, line) in lines.iter().enumerate() {
+                let pv_string = line
+                    .principal_variation
+                    .iter()
+                    .map(|mv| Board::move_to_uci_string(mv.from, mv.to, mv.promote, false))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+
+                let bound = match line.bound {
+                    ScoreBound::Exact => "",
+                    ScoreBound::Lower => " lowerbound",
+                    ScoreBound::Upper => " upperbound",
+                };
+
+                println!(
+                    "info depth {} seldepth {} multipv {} score {}{} nodes {} nps {} tbhits {} time {} pv {}",
+                    depth,
+                    position.max_depth_reached,
+                    index + 1,
+                    line.score.to_uci_string(),
+                    bound,
+                    position.total_nodes(),
+                    nps,
+                    position.tb_hits,
+                    time_ms,
+                    pv_string
+                );
+            }
+        },
+    ));
+
+    for error in engine.take_tablebase_errors() {
+        println!("info string {}", error);
+    }
+
+    result
+}
+
+/// Output the best move, and the expected reply to ponder on if there is one
+fn report_best_move(result: &SearchResult) {
+    println!("{}", best_move_command(result));
+    io::stdout().flush().unwrap();
+}
+
+/// The `bestmove` command for a search result
+fn best_move_command(result: &SearchResult) -> String {
+    let (Some(from), Some(to)) = (result.best_move_from, result.best_move_to) else {
+        // No legal moves found
+        return "bestmove 0000".to_string();
+    };
+
+    let best_move = Board::move_to_uci_string(from, to, result.best_move_promote, false);
+    match result.ponder_move {
+        Some(ponder_move) => format!(
+            "bestmove {} ponder {}",
+            best_move,
+            Board::move_to_uci_string(ponder_move.from, ponder_move.to, ponder_move.promote, false)
+        ),
+        None => format!("bestmove {}", best_move),
     }
 }
 
@@ -131,8 +402,9 @@ pub fn parse_position_command(engine: &mut Engine, command: &str) -> Result<(),
 
     // Parse position type (startpos or fen)
     if parts[index] == "startpos" {
-        engine.position =
-            Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        engine.set_position(
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap(),
+        );
         index += 1;
     } else if parts[index] == "fen" {
         index += 1;
@@ -150,7 +422,7 @@ pub fn parse_position_command(engine: &mut Engine, command: &str) -> Result<(),
         }
 
         let fen = fen_parts.join(" ");
-        engine.position = Position::from_fen(&fen).unwrap();
+        engine.set_position(Position::from_fen(&fen).map_err(|e| e.to_string())?);
     } else {
         return Err(format!("Unknown position type: {}", parts[index]));
     }
@@ -182,12 +454,92 @@ pub fn parse_position_command(engine: &mut Engine, command: &str) -> Result<(),
     Ok(())
 }
 
+/// Parse UCI setoption command and apply it to the engine
+/// Examples:
+///   setoption name Hash value 256
+///   setoption name Skill Level value 10
+///   setoption name Clear Hash
+pub fn parse_setoption_command(engine: &mut Engine, command: &str) -> Result<(), String> {
+    let parts: Vec<&str> = command.split_whitespace().collect();
+
+    if parts.get(1) != Some(&"name") {
+        return Err("Invalid setoption command".to_string());
+    }
+
+    // Names and values may contain spaces
+    let value_index = parts.iter().position(|&part| part == "value");
+    let name = parts[2..value_index.unwrap_or(parts.len())].join(" ");
+    let value = value_index.map(|i| parts[i + 1..].join(" "));
+
+    let option = UCI_OPTIONS
+        .iter()
+        .find(|option| option.name.eq_ignore_ascii_case(&name))
+        .ok_or_else(|| format!("Unknown option: {}", name))?;
+
+    let value = option.parse_value(value.as_deref())?;
+
+    match (option.name, value) {
+        ("Hash", UciOptionValue::Spin(size_mb)) => engine.set_hash_size(size_mb as usize),
+        ("Clear Hash", UciOptionValue::Button) => engine.clear_hash(),
+        ("Threads", UciOptionValue::Spin(threads)) => {
+            engine.search_settings.threads = threads as usize;
+        }
+        ("OwnBook", UciOptionValue::Check(own_book)) => engine.own_book = own_book,
+        ("BookFile", UciOptionValue::String(path)) => {
+            if path.is_empty() {
+                engine.book = None;
+            } else {
+                engine.load_opening_book(&path)?;
+            }
+        }
+        ("EvalFile", UciOptionValue::String(path)) => {
+            if path.is_empty() {
+                engine.use_handcrafted_eval();
+            } else {
+                engine.load_eval_file(&path)?;
+            }
+        }
+        ("EvalParamsFile", UciOptionValue::String(path)) => {
+            if path.is_empty() {
+                engine.use_default_eval_params();
+            } else {
+                engine.load_eval_params(&path)?;
+            }
+        }
+        ("SyzygyPath", UciOptionValue::String(path)) => {
+            let num_tables = engine.set_syzygy_path(&path)?;
+
+            if !path.is_empty() {
+                println!("info string Found {} tablebase files", num_tables);
+            }
+        }
+        ("MultiPV", UciOptionValue::Spin(multi_pv)) => {
+            engine.search_settings.multi_pv = multi_pv as usize;
+        }
+        ("Move Overhead", UciOptionValue::Spin(move_overhead)) => {
+            engine.search_settings.move_overhead = move_overhead as u64;
+        }
+        // The GUI decides when to ponder by sending `go ponder`
+        ("Ponder", UciOptionValue::Check(_)) => {}
+        ("Skill Level", UciOptionValue::Spin(skill_level)) => {
+            engine.set_difficulty(Difficulty::from_skill_level(skill_level as u8));
+        }
+        (name, value) => unreachable!("Option {} has no handler for {:?}", name, value),
+    }
+
+    Ok(())
+}
+
 /// Parse UCI go command and update search settings
 /// Examples:
 ///   go depth 10
 ///   go movetime 5000
 ///   go wtime 300000 btime 300000 winc 0 binc 0
+///   go wtime 300000 btime 300000 movestogo 40
+///   go mate 3
+///   go searchmoves e2e4 d2d4 depth 8
 ///   go infinite
+///   go ponder wtime 300000 btime 300000
 pub fn parse_go_command(engine: &mut Engine, command: &str) {
     let parts: Vec<&str> = command.split_whitespace().collect();
 
@@ -196,69 +548,72 @@ pub fn parse_go_command(engine: &mut Engine, command: &str) {
     let mut winc = None;
     let mut binc = None;
     let mut movetime = None;
+    let mut movestogo = None;
     let mut max_depth = None;
     let mut max_nodes = None;
+    let mut mate = None;
+    let mut infinite = false;
+    let mut ponder = false;
+    let mut search_moves = Vec::new();
 
     let mut i = 1; // Skip "go"
     while i < parts.len() {
         match parts[i] {
-            "wtime" => {
-                if i + 1 < parts.len() {
-                    wtime = parts[i + 1].parse::<u64>().ok();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+            "wtime" if i + 1 < parts.len() => {
+                wtime = parts[i + 1].parse::<u64>().ok();
+                i += 2;
             }
-            "btime" => {
-                if i + 1 < parts.len() {
-                    btime = parts[i + 1].parse::<u64>().ok();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+            "btime" if i + 1 < parts.len() => {
+                btime = parts[i + 1].parse::<u64>().ok();
+                i += 2;
             }
-            "winc" => {
-                if i + 1 < parts.len() {
-                    winc = parts[i + 1].parse::<u64>().ok();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+            "winc" if i + 1 < parts.len() => {
+                winc = parts[i + 1].parse::<u64>().ok();
+                i += 2;
             }
-            "binc" => {
-                if i + 1 < parts.len() {
-                    binc = parts[i + 1].parse::<u64>().ok();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+            "binc" if i + 1 < parts.len() => {
+                binc = parts[i + 1].parse::<u64>().ok();
+                i += 2;
             }
-            "movetime" => {
-                if i + 1 < parts.len() {
-                    movetime = parts[i + 1].parse::<u64>().ok();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+            "movetime" if i + 1 < parts.len() => {
+                movetime = parts[i + 1].parse::<u64>().ok();
+                i += 2;
             }
-            "depth" => {
-                if i + 1 < parts.len() {
-                    max_depth = parts[i + 1].parse::<u16>().ok();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+            "movestogo" if i + 1 < parts.len() => {
+                movestogo = parts[i + 1].parse::<u64>().ok();
+                i += 2;
+            }
+            "depth" if i + 1 < parts.len() => {
+                max_depth = parts[i + 1].parse::<u16>().ok();
+                i += 2;
             }
             "infinite" => {
                 max_depth = Some(100);
+                infinite = true;
+                i += 1;
+            }
+            "ponder" => {
+                ponder = true;
                 i += 1;
             }
-            "nodes" => {
-                if i + 1 < parts.len() {
-                    max_nodes = parts[i + 1].parse::<usize>().ok();
-                    i += 2;
-                } else {
+            "nodes" if i + 1 < parts.len() => {
+                max_nodes = parts[i + 1].parse::<usize>().ok();
+                i += 2;
+            }
+            "mate" if i + 1 < parts.len() => {
+                mate = parts[i + 1].parse::<u16>().ok();
+                i += 2;
+            }
+            "searchmoves" => {
+                i += 1;
+
+                // Moves continue until the next parameter
+                let legal_moves = engine.position.get_legal_moves();
+
+                while i < parts.len() && Board::move_from_uci_string(parts[i]).is_ok() {
+                    if legal_moves.iter().any(|legal_move| legal_move == parts[i]) {
+                        search_moves.extend(Board::move_from_uci_string(parts[i]));
+                    }
                     i += 1;
                 }
             }
@@ -281,15 +636,24 @@ pub fn parse_go_command(engine: &mut Engine, command: &str) {
     if let Some(bi) = binc {
         engine.search_settings.binc = bi;
     }
-    if let Some(mt) = movetime {
-        engine.search_settings.movetime = Some(mt);
-    }
-    if let Some(max_depth) = max_depth {
-        engine.search_settings.max_depth = max_depth;
-    }
-    if let Some(nodes) = max_nodes {
-        engine.search_settings.max_nodes = Some(nodes);
-    }
+
+    // A mate search without a clock runs until the mate is proven or refuted
+    let has_time_limit = wtime.is_some() || btime.is_some() || movetime.is_some();
+
+    // Limits only apply to this search
+    engine.search_settings.movetime = movetime;
+    engine.search_settings.movestogo = movestogo;
+    engine.search_settings.max_depth = max_depth.unwrap_or(match mate {
+        Some(_) => 100,
+        None => engine.default_max_depth(),
+    });
+    engine.search_settings.max_nodes = max_nodes;
+    engine.search_settings.mate = mate;
+    engine.search_settings.infinite = infinite || (mate.is_some() && !has_time_limit);
+    engine.search_settings.search_moves = search_moves;
+
+    // Pondering ignores the clock until `ponderhit`
+    engine.ponder_signal().store(ponder, Ordering::Relaxed);
 }
 
 #[cfg(test)]
@@ -338,4 +702,201 @@ mod tests {
         let result = parse_position_command(&mut engine, "position startpos moves e2e4 e7e5");
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_stop_ends_infinite_search() {
+        let mut engine = Engine::default();
+        let input = "position startpos moves e2e4\ngo infinite\nisready\nstop\nquit\n";
+
+        // Returns only if `stop` interrupts the search
+        run_uci_loop(&mut engine, input.as_bytes());
+
+        assert_eq!(engine.position.ply, 0);
+        assert_eq!(
+            engine.position.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+    }
+
+    #[test]
+    fn test_stop_before_first_depth_reports_legal_move() {
+        let mut engine = Engine::default();
+        engine.own_book = false;
+        // Captures everywhere, so depth 1 takes thousands of nodes
+        let fen = "rnbqkbnr/8/8/pppppppp/PPPPPPPP/8/8/RNBQKBNR w - - 0 1";
+        parse_position_command(&mut engine, &format!("position fen {}", fen)).unwrap();
+        parse_go_command(&mut engine, "go infinite");
+
+        // `stop` arrives before depth 1 is searched
+        engine.stop_signal().store(true, Ordering::Relaxed);
+        let result = search_and_report(&mut engine);
+
+        let command = best_move_command(&result);
+        let best_move = command.split_whitespace().nth(1).unwrap();
+        assert!(
+            engine
+                .position
+                .get_legal_moves()
+                .contains(&best_move.to_string()),
+            "{}",
+            command
+        );
+    }
+
+    #[test]
+    fn test_quit_during_search() {
+        let mut engine = Engine::default();
+        run_uci_loop(&mut engine, "go infinite\nquit\n".as_bytes());
+
+        // A later search is not affected by the stop that ended the previous one
+        engine.search_settings.max_depth = 2;
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+        assert_eq!(result.depth, 2);
+    }
+
+    #[test]
+    fn test_uci_options_are_declared() {
+        let declarations: Vec<String> = UCI_OPTIONS.iter().map(|o| o.declaration()).collect();
+
+        assert!(
+            declarations
+                .contains(&"option name Threads type spin default 1 min 1 max 256".to_string())
+        );
+        assert!(declarations.contains(&"option name OwnBook type check default true".to_string()));
+        assert!(
+            declarations.contains(&"option name BookFile type string default <empty>".to_string())
+        );
+        assert!(
+            declarations.contains(&"option name EvalFile type string default <empty>".to_string())
+        );
+        assert!(
+            declarations
+                .contains(&"option name EvalParamsFile type string default <empty>".to_string())
+        );
+        assert!(
+            declarations
+                .contains(&"option name SyzygyPath type string default <empty>".to_string())
+        );
+        assert!(declarations.contains(&"option name Clear Hash type button".to_string()));
+    }
+
+    #[test]
+    fn test_setoption_applies_values() {
+        let mut engine = Engine::default();
+
+        parse_setoption_command(&mut engine, "setoption name Hash value 1").unwrap();
+        parse_setoption_command(&mut engine, "setoption name Threads value 4").unwrap();
+        parse_setoption_command(&mut engine, "setoption name MultiPV value 3").unwrap();
+        parse_setoption_command(&mut engine, "setoption name move overhead value 50").unwrap();
+        parse_setoption_command(&mut engine, "setoption name OwnBook value false").unwrap();
+
+        assert!(engine.position.board.hash.num_slots() <= 1024 * 1024);
+        assert_eq!(engine.search_settings.threads, 4);
+        assert_eq!(engine.search_settings.multi_pv, 3);
+        assert_eq!(engine.search_settings.move_overhead, 50);
+        assert!(!engine.own_book);
+
+        // The resized table is kept when the position changes
+        let num_slots = engine.position.board.hash.num_slots();
+        parse_position_command(&mut engine, "position startpos moves e2e4").unwrap();
+        assert_eq!(engine.position.board.hash.num_slots(), num_slots);
+    }
+
+    #[test]
+    fn test_setoption_skill_level() {
+        let mut engine = Engine::default();
+
+        parse_setoption_command(&mut engine, "setoption name Skill Level value 0").unwrap();
+        assert!(matches!(engine.difficulty, Some(Difficulty::Beginner)));
+
+        parse_go_command(&mut engine, "go wtime 1000 btime 1000");
+        assert_eq!(engine.search_settings.max_depth, 1);
+
+        parse_setoption_command(&mut engine, "setoption name Skill Level value 20").unwrap();
+        assert!(engine.difficulty.is_none());
+    }
+
+    #[test]
+    fn test_setoption_rejects_invalid_input() {
+        let mut engine = Engine::default();
+
+        assert!(parse_setoption_command(&mut engine, "setoption name Unknown value 1").is_err());
+        assert!(parse_setoption_command(&mut engine, "setoption name Threads value many").is_err());
+        assert!(parse_setoption_command(&mut engine, "setoption name Threads").is_err());
+        assert!(
+            parse_setoption_command(&mut engine, "setoption name BookFile value missing.bin")
+                .is_err()
+        );
+        assert!(
+            parse_setoption_command(&mut engine, "setoption name EvalFile value missing.nnue")
+                .is_err()
+        );
+        assert!(!engine.uses_network_eval());
+        assert!(
+            parse_setoption_command(
+                &mut engine,
+                "setoption name EvalParamsFile value missing.txt"
+            )
+            .is_err()
+        );
+        assert!(
+            parse_setoption_command(&mut engine, "setoption name SyzygyPath value missing/dir")
+                .is_err()
+        );
+        assert!(engine.position.tablebases().is_none());
+    }
+
+    #[test]
+    fn test_ponderhit_and_stop_end_ponder_search() {
+        let mut engine = Engine::default();
+        let input =
+            "go ponder wtime 1000 btime 1000\nponderhit\nisready\ngo ponder depth 1\nstop\nquit\n";
+
+        // Returns only if `bestmove` is released by `ponderhit` and then by `stop`
+        run_uci_loop(&mut engine, input.as_bytes());
+
+        assert!(!engine.ponder_signal().load(Ordering::Relaxed));
+        assert_eq!(engine.position.ply, 0);
+    }
+
+    #[test]
+    fn test_go_ponder_raises_ponder_signal() {
+        let mut engine = Engine::default();
+
+        parse_go_command(&mut engine, "go ponder wtime 60000 btime 60000");
+        assert!(engine.ponder_signal().load(Ordering::Relaxed));
+        assert_eq!(engine.search_settings.wtime, 60000);
+
+        parse_go_command(&mut engine, "go wtime 60000 btime 60000");
+        assert!(!engine.ponder_signal().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_go_searchmoves_mate_and_movestogo() {
+        let mut engine = Engine::default();
+
+        parse_go_command(
+            &mut engine,
+            "go wtime 60000 btime 60000 movestogo 40 searchmoves e2e4 d2d4 e2e5 mate 3",
+        );
+
+        let settings = &engine.search_settings;
+        assert_eq!(settings.movestogo, Some(40));
+        assert_eq!(settings.mate, Some(3));
+        assert!(!settings.infinite, "Clock limits apply to the mate search");
+
+        // The illegal e2e5 is dropped
+        let search_moves: Vec<String> = settings
+            .search_moves
+            .iter()
+            .map(|m| Board::move_to_uci_string(m.from, m.to, m.promote, false))
+            .collect();
+        assert_eq!(search_moves, ["e2e4", "d2d4"]);
+
+        // Limits don't carry over to the next search
+        parse_go_command(&mut engine, "go mate 2");
+        assert!(engine.search_settings.search_moves.is_empty());
+        assert_eq!(engine.search_settings.movestogo, None);
+        assert!(engine.search_settings.infinite);
+    }
 }
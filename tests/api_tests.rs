@@ -75,3 +75,54 @@ fn get_top_moves_scores_are_sorted() {
         assert!(w[0].score >= w[1].score, "Moves should be sorted best-first");
     }
 }
+
+#[test]
+fn get_top_moves_are_distinct_legal_moves() {
+    let moves = api::get_top_moves(START_FEN, 4).unwrap();
+    let legal_moves = api::get_legal_moves(START_FEN).unwrap();
+
+    for (i, m) in moves.iter().enumerate() {
+        assert!(legal_moves.contains(&m.mv), "{} should be legal", m.mv);
+        assert!(!moves[..i].iter().any(|other| other.mv == m.mv), "{} listed twice", m.mv);
+    }
+}
+
+#[test]
+fn get_top_moves_finds_mate_first() {
+    // Scholar's mate: Qxf7# is the only mating move
+    let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 0 1";
+    let moves = api::get_top_moves(fen, 3).unwrap();
+    assert_eq!(moves[0].mv, "h5f7");
+    assert_eq!(moves[0].mate, Some(1));
+    assert_eq!(moves[0].to_string(), "h5f7 M1");
+    assert!(moves[0].score > moves[1].score);
+    assert_eq!(moves[1].mate, None);
+}
+
+#[test]
+fn get_top_moves_invalid_fen() {
+    assert!(api::get_top_moves("garbage", 3).is_err());
+}
+
+#[test]
+fn static_exchange_evaluation_of_defended_pawn() {
+    let fen = "4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1";
+    assert_eq!(api::static_exchange_evaluation(fen, "e1e5").unwrap(), -800);
+}
+
+#[test]
+fn static_exchange_evaluation_illegal_move() {
+    assert!(api::static_exchange_evaluation(START_FEN, "e2e5").is_err());
+}
+
+#[test]
+fn evaluate_with_trace_starting_position() {
+    let trace = api::evaluate_with_trace(START_FEN).unwrap();
+    assert_eq!(trace.total(), 0);
+    assert_eq!(trace.phase, 24);
+}
+
+#[test]
+fn evaluate_with_trace_invalid_fen() {
+    assert!(api::evaluate_with_trace("garbage").is_err());
+}
@@ -5,9 +5,11 @@
 ///
 /// 1. **Material Balance**: Piece values (Queen > Rook > Bishop ≈ Knight > Pawn)
 /// 2. **Piece Positioning**: Central control, piece activity, piece-square tables
-/// 3. **Pawn Structure**: Passed pawns, isolated pawns, pawn chains, doubled pawns
+/// 3. **Pawn Structure**: Passed pawns, isolated pawns, pawn chains, doubled and backward pawns
 /// 4. **Rook Placement**: Open files, semi-open files, file control
-/// 5. **King Safety**: Pawn shields when queens are on board, centralization in endgames
+/// 5. **King Safety**: Pawn shields, attacks on the king zone, open files, pawn storms, safe checks,
+///    centralization in endgames
+/// 6. **Piece Activity**: Mobility, bishop pair, knight outposts, rooks on the 7th, connected rooks
 ///
 /// The evaluation function returns a score from white's perspective:
 /// - Positive scores favor white
@@ -47,6 +49,19 @@ fn evaluate(position: &Position) -> i32 {
     position.evaluate()
 }
 
+/// Tapering rounds each evaluation toward zero, so comparing two changes in evaluation can be off
+/// by this much even when nothing else differs
+const TAPER_ROUNDING: i32 = 1;
+
+/// How much adding a piece changes the evaluation, for White to move. Comparing this for the
+/// same piece in two surroundings leaves out its piece-square score.
+fn value_of_adding(position: &Position, side: Side, piece: Piece, square: Square) -> i32 {
+    let mut with_piece = position.clone();
+    with_piece.board.add_piece(side, piece, square);
+
+    evaluate(&with_piece) - evaluate(position)
+}
+
 #[cfg(test)]
 mod material_evaluation {
     use super::*;
@@ -392,16 +407,42 @@ mod pawn_structure {
         spread.board.add_piece(Side::White, Piece::Pawn, Square::D3);
         let spread_score = evaluate(&spread);
 
-        // Spread pawns should generally be better than doubled
-        // (though evaluation may not heavily penalize this directly)
         assert!(
-            spread_score >= doubled_score,
-            "Spread pawns should score at least as well as doubled: {} vs {}",
+            spread_score > doubled_score,
+            "Spread pawns should score higher than doubled: {} vs {}",
             spread_score,
             doubled_score
         );
     }
 
+    #[test]
+    fn test_doubled_pawn_penalty() {
+        // e3 is isolated and can't become passed either way; only e5 in front of it differs.
+        // c4 already stops d6 from being passed, so e3 doesn't change d6's score.
+        let mut own_pawn_ahead = position_with_kings(Side::White);
+        own_pawn_ahead
+            .board
+            .add_piece(Side::White, Piece::Pawn, Square::E5);
+
+        let mut enemy_pawn_nearby = position_with_kings(Side::White);
+        enemy_pawn_nearby
+            .board
+            .add_piece(Side::White, Piece::Pawn, Square::C4);
+        enemy_pawn_nearby
+            .board
+            .add_piece(Side::Black, Piece::Pawn, Square::D6);
+
+        let doubled_pawn = value_of_adding(&own_pawn_ahead, Side::White, Piece::Pawn, Square::E3);
+        let single_pawn = value_of_adding(&enemy_pawn_nearby, Side::White, Piece::Pawn, Square::E3);
+
+        assert!(
+            single_pawn > doubled_pawn,
+            "A doubled pawn should be worth less: {} vs {}",
+            single_pawn,
+            doubled_pawn
+        );
+    }
+
     #[test]
     fn test_pawn_chain() {
         let mut chain = position_with_kings(Side::White);
@@ -429,6 +470,32 @@ mod pawn_structure {
             isolated_score
         );
     }
+
+    #[test]
+    fn test_pawn_hash_table() {
+        let mut position = position_from_fen(
+            "r1bqk2r/pp3ppp/2n1pn2/2pp4/1bPP4/2N1PN2/PP3PPP/R1BQKB1R w KQkq - 0 7",
+        );
+        let pawn_key = position.board.hash.pawn_key;
+
+        let score = evaluate(&position);
+        assert_eq!(position.pawn_hash_hits.get(), 0);
+
+        assert_eq!(evaluate(&position), score, "A cached entry should score the same");
+        assert_eq!(position.pawn_hash_hits.get(), 1);
+
+        // Pieces moving keep the pawn key; pawns moving change it
+        assert!(position.make_move(Square::F1, Square::D3, None));
+        assert_eq!(position.board.hash.pawn_key, pawn_key);
+        assert!(position.make_move(Square::D5, Square::C4, None));
+        assert_ne!(position.board.hash.pawn_key, pawn_key);
+
+        position.take_back_move();
+        position.take_back_move();
+        assert_eq!(position.board.hash.pawn_key, pawn_key);
+        assert_eq!(evaluate(&position), score);
+        assert_eq!(position.pawn_hash_hits.get(), 2);
+    }
 }
 
 #[cfg(test)]
@@ -527,6 +594,20 @@ mod rook_evaluation {
 mod king_evaluation {
     use super::*;
 
+    /// Enough material for the midgame, where the king's shelter matters
+    fn add_queens_and_rooks(position: &mut Position) {
+        for (side, queen, rooks) in [
+            (Side::White, Square::D1, [Square::A1, Square::H1]),
+            (Side::Black, Square::D8, [Square::A8, Square::H8]),
+        ] {
+            position.board.add_piece(side, Piece::Queen, queen);
+
+            for rook in rooks {
+                position.board.add_piece(side, Piece::Rook, rook);
+            }
+        }
+    }
+
     #[test]
     fn test_king_safety_with_queens_on_board() {
         // King with pawn shield (kingside castle)
@@ -557,9 +638,7 @@ mod king_evaluation {
         safe_king
             .board
             .add_piece(Side::Black, Piece::Pawn, Square::C7);
-        safe_king
-            .board
-            .add_piece(Side::Black, Piece::Queen, Square::D8);
+        add_queens_and_rooks(&mut safe_king);
 
         let safe_score = evaluate(&safe_king);
 
@@ -571,9 +650,7 @@ mod king_evaluation {
         exposed_king
             .board
             .add_piece(Side::White, Piece::King, Square::E4);
-        exposed_king
-            .board
-            .add_piece(Side::Black, Piece::Queen, Square::D8);
+        add_queens_and_rooks(&mut exposed_king);
 
         let exposed_score = evaluate(&exposed_king);
 
@@ -715,6 +792,95 @@ mod king_evaluation {
             no_shield_score
         );
     }
+
+    #[test]
+    fn test_attackers_gang_up_on_king() {
+        // The knight hits f1 and g2. With the queen already hitting f2 and h2, that's two
+        // pieces in the king zone; with the queen on a5, the knight attacks alone.
+        let queen_attacking = position_from_fen("r3k2r/4p3/8/8/7q/8/5PPP/R2Q1RK1 w - - 0 1");
+        let queen_elsewhere = position_from_fen("r3k2r/4p3/8/q7/8/8/5PPP/R2Q1RK1 w - - 0 1");
+
+        let knight_joining =
+            value_of_adding(&queen_attacking, Side::Black, Piece::Knight, Square::E3);
+        let knight_alone =
+            value_of_adding(&queen_elsewhere, Side::Black, Piece::Knight, Square::E3);
+
+        assert!(
+            knight_joining + TAPER_ROUNDING < knight_alone,
+            "A second attacker near the king should be worth more to Black: {} vs {}",
+            knight_joining,
+            knight_alone
+        );
+    }
+
+    #[test]
+    fn test_open_file_in_front_of_king() {
+        // The same pawn closes the open g-file in front of the king on g1, but not on b1
+        let king_on_g_file = position_from_fen("q3k3/8/8/8/8/8/8/R2Q1RK1 w - - 0 1");
+        let king_on_b_file = position_from_fen("q3k3/8/8/8/8/8/8/RK1Q1R2 w - - 0 1");
+
+        let closing_pawn = value_of_adding(&king_on_g_file, Side::White, Piece::Pawn, Square::G4);
+        let distant_pawn = value_of_adding(&king_on_b_file, Side::White, Piece::Pawn, Square::G4);
+
+        assert!(
+            closing_pawn > distant_pawn + TAPER_ROUNDING,
+            "Closing the file in front of the king should be worth more: {} vs {}",
+            closing_pawn,
+            distant_pawn
+        );
+    }
+
+    #[test]
+    fn test_pawn_storm() {
+        let storm_on_king = position_from_fen("r2qk2r/8/8/5ppp/8/8/PPP2PPP/R2Q1RK1 w - - 0 1");
+        let storm_away_from_king =
+            position_from_fen("r2qk2r/8/8/5ppp/8/8/PPP2PPP/RK1Q1R2 w - - 0 1");
+
+        // Advance the storming pawns from the 5th rank to the 4th
+        let advance = |position: &Position| {
+            let mut advanced = position.clone();
+
+            for (from, to) in [
+                (Square::F5, Square::F4),
+                (Square::G5, Square::G4),
+                (Square::H5, Square::H4),
+            ] {
+                advanced.board.remove_piece(Side::Black, Piece::Pawn, from);
+                advanced.board.add_piece(Side::Black, Piece::Pawn, to);
+            }
+
+            evaluate(&advanced) - evaluate(position)
+        };
+
+        let storm_on_king_cost = advance(&storm_on_king);
+        let storm_away_cost = advance(&storm_away_from_king);
+
+        assert!(
+            storm_on_king_cost + TAPER_ROUNDING < storm_away_cost,
+            "Pawns storming the king should be worth more to Black: {} vs {}",
+            storm_on_king_cost,
+            storm_away_cost
+        );
+    }
+
+    #[test]
+    fn test_safe_check() {
+        // From g5 the knight can check on h3. The bishop on f1 covers h3; the one on b1 doesn't.
+        let h3_uncovered = position_from_fen("r2qk2r/8/8/8/8/8/5P1P/RB1Q2KR w - - 0 1");
+        let h3_covered = position_from_fen("r2qk2r/8/8/8/8/8/5P1P/R2Q1BKR w - - 0 1");
+
+        let knight_with_check =
+            value_of_adding(&h3_uncovered, Side::Black, Piece::Knight, Square::G5);
+        let knight_without_check =
+            value_of_adding(&h3_covered, Side::Black, Piece::Knight, Square::G5);
+
+        assert!(
+            knight_with_check + TAPER_ROUNDING < knight_without_check,
+            "A knight with a safe check should be worth more to Black: {} vs {}",
+            knight_with_check,
+            knight_without_check
+        );
+    }
 }
 
 #[cfg(test)]
@@ -1199,3 +1365,293 @@ mod evaluation_perspective_fix_tests {
         );
     }
 }
+
+#[cfg(test)]
+mod tapered_evaluation {
+    use super::*;
+
+    #[test]
+    fn test_game_phase_falls_as_pieces_come_off() {
+        let start = Position::new(TimeManager::default());
+        let queens_traded =
+            position_from_fen("rnb1kbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 0 3");
+        let pawn_endgame = position_from_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1");
+
+        assert!(start.game_phase() > queens_traded.game_phase());
+        assert!(queens_traded.game_phase() > 0);
+        assert_eq!(pawn_endgame.game_phase(), 0);
+    }
+
+    #[test]
+    fn test_promotions_do_not_raise_phase_past_the_opening() {
+        let start = Position::new(TimeManager::default());
+        let extra_queens =
+            position_from_fen("rnbqkbnr/pppppppp/8/8/8/8/QQQQQQQQ/RNBQKBNR w KQkq - 0 1");
+
+        assert_eq!(extra_queens.game_phase(), start.game_phase());
+    }
+
+    #[test]
+    fn test_queen_trade_does_not_jump_evaluation() {
+        // White has castled behind its pawns, Black's king is still in the centre. Trading
+        // queens used to switch both kings to the endgame table at once, swinging the
+        // evaluation from one side to the other.
+        let with_queens = position_from_fen(
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQ1RK1 b kq - 0 6",
+        );
+        let without_queens = position_from_fen(
+            "r1b1k2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1B2RK1 b kq - 0 6",
+        );
+
+        let with_queens_score = evaluate(&with_queens);
+        let without_queens_score = evaluate(&without_queens);
+
+        assert_eq!(with_queens_score.signum(), without_queens_score.signum());
+        assert!(
+            (with_queens_score - without_queens_score).abs() <= 20,
+            "Trading queens moved the evaluation: {} vs {}",
+            with_queens_score,
+            without_queens_score
+        );
+    }
+
+    #[test]
+    fn test_king_shelter_fades_in_endgame() {
+        // The same king move costs less as material comes off
+        let middlegame_shelter =
+            position_from_fen("r2qk2r/ppp2ppp/2n2n2/8/8/2N2N2/PPP2PPP/R2Q1RK1 w kq - 0 1");
+        let middlegame_exposed =
+            position_from_fen("r2qk2r/ppp2ppp/2n2n2/8/8/2N2NK1/PPP2PPP/R2Q1R2 w kq - 0 1");
+        let endgame_shelter = position_from_fen("4k3/ppp2ppp/8/8/8/8/PPP2PPP/6K1 w - - 0 1");
+        let endgame_exposed = position_from_fen("4k3/ppp2ppp/8/8/8/6K1/PPP2PPP/8 w - - 0 1");
+
+        let middlegame_cost = evaluate(&middlegame_shelter) - evaluate(&middlegame_exposed);
+        let endgame_cost = evaluate(&endgame_shelter) - evaluate(&endgame_exposed);
+
+        assert!(middlegame_cost > 0);
+        assert!(
+            endgame_cost < middlegame_cost,
+            "Leaving the shelter cost {} in the endgame and {} in the middlegame",
+            endgame_cost,
+            middlegame_cost
+        );
+    }
+}
+
+#[cfg(test)]
+mod piece_activity {
+    use super::*;
+
+    #[test]
+    fn test_boxed_in_rook_is_worth_less() {
+        let mut boxed = position_with_kings(Side::White);
+        boxed
+            .board
+            .add_piece(Side::White, Piece::Knight, Square::B1);
+        boxed.board.add_piece(Side::White, Piece::Pawn, Square::A2);
+
+        let mut free = position_with_kings(Side::White);
+        free.board.add_piece(Side::White, Piece::Knight, Square::B3);
+        free.board.add_piece(Side::White, Piece::Pawn, Square::A4);
+
+        let boxed_rook = value_of_adding(&boxed, Side::White, Piece::Rook, Square::A1);
+        let free_rook = value_of_adding(&free, Side::White, Piece::Rook, Square::A1);
+
+        assert!(
+            free_rook > boxed_rook,
+            "A rook with open lines should be worth more than a boxed-in one: {} vs {}",
+            free_rook,
+            boxed_rook
+        );
+    }
+
+    #[test]
+    fn test_bishop_pair_bonus() {
+        // The bishops' diagonals don't cross, so the second bishop is only worth more than the
+        // first because of the pair
+        let kings_only = position_with_kings(Side::White);
+        let mut one_bishop = position_with_kings(Side::White);
+        one_bishop
+            .board
+            .add_piece(Side::White, Piece::Bishop, Square::C1);
+
+        let first_bishop = value_of_adding(&kings_only, Side::White, Piece::Bishop, Square::F1);
+        let second_bishop = value_of_adding(&one_bishop, Side::White, Piece::Bishop, Square::F1);
+
+        assert!(
+            second_bishop > first_bishop,
+            "The second bishop should complete the pair: {} vs {}",
+            second_bishop,
+            first_bishop
+        );
+    }
+
+    #[test]
+    fn test_knight_outpost() {
+        let mut outpost = position_with_kings(Side::White);
+        outpost
+            .board
+            .add_piece(Side::White, Piece::Pawn, Square::D4);
+
+        // The d6 pawn can chase the knight away with ...d5 later. It attacks c5 and e5, none of
+        // which are squares the knight moves to.
+        let mut no_outpost = position_with_kings(Side::White);
+        no_outpost
+            .board
+            .add_piece(Side::White, Piece::Pawn, Square::D4);
+        no_outpost
+            .board
+            .add_piece(Side::Black, Piece::Pawn, Square::D6);
+
+        let outpost_knight = value_of_adding(&outpost, Side::White, Piece::Knight, Square::E5);
+        let chased_knight = value_of_adding(&no_outpost, Side::White, Piece::Knight, Square::E5);
+
+        assert!(
+            outpost_knight > chased_knight,
+            "A knight enemy pawns can't reach should be worth more: {} vs {}",
+            outpost_knight,
+            chased_knight
+        );
+    }
+
+    #[test]
+    fn test_rook_on_seventh_traps_king() {
+        let trapped_king = position_from_fen("6k1/8/8/8/8/8/8/4K3 w - - 0 1");
+        let free_king = position_from_fen("8/8/6k1/8/8/8/8/4K3 w - - 0 1");
+
+        let rook_vs_trapped = value_of_adding(&trapped_king, Side::White, Piece::Rook, Square::D7);
+        let rook_vs_free = value_of_adding(&free_king, Side::White, Piece::Rook, Square::D7);
+
+        assert!(
+            rook_vs_trapped > rook_vs_free,
+            "A rook on the 7th should be worth more with the king stuck on the 8th: {} vs {}",
+            rook_vs_trapped,
+            rook_vs_free
+        );
+    }
+
+    #[test]
+    fn test_connected_rooks() {
+        let mut connected = position_with_kings(Side::White);
+        connected
+            .board
+            .add_piece(Side::White, Piece::Rook, Square::A1);
+
+        // An enemy knight stands between the rooks. Each rook still attacks it, so both rooks
+        // reach as many squares as before.
+        let mut blocked = position_with_kings(Side::White);
+        blocked
+            .board
+            .add_piece(Side::White, Piece::Rook, Square::A1);
+        blocked
+            .board
+            .add_piece(Side::Black, Piece::Knight, Square::C1);
+
+        let connecting_rook = value_of_adding(&connected, Side::White, Piece::Rook, Square::D1);
+        let blocked_rook = value_of_adding(&blocked, Side::White, Piece::Rook, Square::D1);
+
+        assert!(
+            connecting_rook > blocked_rook,
+            "Rooks that defend each other should be worth more: {} vs {}",
+            connecting_rook,
+            blocked_rook
+        );
+    }
+
+    #[test]
+    fn test_backward_pawn_penalty() {
+        // d3 has fallen behind c4 and e4. Black's c5 pawn guards d4, so it can't catch up.
+        let mut guarded = position_with_kings(Side::White);
+        guarded
+            .board
+            .add_piece(Side::White, Piece::Pawn, Square::C4);
+        guarded
+            .board
+            .add_piece(Side::White, Piece::Pawn, Square::E4);
+        guarded
+            .board
+            .add_piece(Side::Black, Piece::Pawn, Square::C5);
+
+        // c6 doesn't guard d4, and still stops d3 from being a passed pawn
+        let mut unguarded = position_with_kings(Side::White);
+        unguarded
+            .board
+            .add_piece(Side::White, Piece::Pawn, Square::C4);
+        unguarded
+            .board
+            .add_piece(Side::White, Piece::Pawn, Square::E4);
+        unguarded
+            .board
+            .add_piece(Side::Black, Piece::Pawn, Square::C6);
+
+        let backward_pawn = value_of_adding(&guarded, Side::White, Piece::Pawn, Square::D3);
+        let free_pawn = value_of_adding(&unguarded, Side::White, Piece::Pawn, Square::D3);
+
+        assert!(
+            free_pawn > backward_pawn,
+            "A backward pawn should be worth less: {} vs {}",
+            free_pawn,
+            backward_pawn
+        );
+    }
+}
+
+#[cfg(test)]
+mod evaluation_trace {
+    use super::*;
+    use chess_engine::types::{EvalTerm, TaperedScore};
+
+    #[test]
+    fn test_trace_adds_up_to_evaluation() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R w KQ - 0 8",
+            "8/5pk1/6p1/3R4/p7/6P1/5PKP/r7 b - - 0 40",
+        ] {
+            let position = position_from_fen(fen);
+            let trace = position.evaluate_with_trace();
+
+            let expected = match position.side {
+                Side::White => position.evaluate(),
+                Side::Black => -position.evaluate(),
+            };
+
+            assert_eq!(trace.total(), expected, "{}", fen);
+            assert_eq!(trace.side_to_move, position.side);
+            assert_eq!(trace.phase, position.game_phase());
+        }
+    }
+
+    #[test]
+    fn test_trace_reports_terms_for_each_side() {
+        // White: bishop pair, rook on an open file, isolated d-pawn. Black: doubled f-pawns.
+        let position = position_from_fen("4k3/p4pp1/5p2/8/3P4/8/P7/2BBKR2 w - - 0 1");
+        let trace = position.evaluate_with_trace();
+
+        assert_eq!(
+            trace.get(EvalTerm::BishopPair, Side::White),
+            TaperedScore::new(30, 50)
+        );
+        assert_eq!(
+            trace.get(EvalTerm::BishopPair, Side::Black),
+            TaperedScore::default()
+        );
+        assert!(trace.get(EvalTerm::RookFiles, Side::White).midgame > 0);
+        assert!(trace.get(EvalTerm::IsolatedPawns, Side::White).midgame < 0);
+        assert!(trace.get(EvalTerm::DoubledPawns, Side::Black).midgame < 0);
+        assert_eq!(
+            trace.get(EvalTerm::DoubledPawns, Side::White),
+            TaperedScore::default()
+        );
+
+        // Material counts each side's own pieces
+        assert!(
+            trace.get(EvalTerm::Material, Side::White).midgame
+                > trace.get(EvalTerm::Material, Side::Black).midgame
+        );
+
+        let table = trace.to_string();
+        assert!(table.contains("Bishop pair"));
+        assert!(table.contains("Evaluation: "));
+    }
+}
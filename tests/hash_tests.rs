@@ -2,8 +2,8 @@
 mod test_utils;
 
 use chess_engine::{
-    hash::Hash,
-    types::{Piece, Side, Square},
+    hash::{Hash, HashFlag, PawnEntry, score_from_hash, score_to_hash},
+    types::{Piece, Side, Square, TaperedScore},
 };
 use test_utils::*;
 
@@ -23,7 +23,7 @@ fn hash_store_and_probe_move() {
 
     // Set a hash key and store a move
     hash.current_key = 12345;
-    hash.store_move(test_move, 5, 100);
+    hash.store_move(test_move, 5, 100, HashFlag::Exact);
 
     // Should retrieve the same move
     let entry = hash.probe();
@@ -44,7 +44,7 @@ fn hash_probe_returns_none_for_different_key() {
 
     // Store move at one hash key
     hash.current_key = 12345;
-    hash.store_move(test_move, 5, 100);
+    hash.store_move(test_move, 5, 100, HashFlag::Exact);
 
     // Change hash key
     hash.current_key = 54321;
@@ -65,12 +65,12 @@ fn hash_store_overwrites_previous_entry() {
     hash.current_key = 12345;
 
     // Store first move
-    hash.store_move(move1, 5, 100);
+    hash.store_move(move1, 5, 100, HashFlag::Exact);
     let entry = hash.probe().unwrap();
     assert_eq!(entry.best_move.unwrap().from, Square::E2);
 
     // Store second move with same hash and greater depth (overwrites)
-    hash.store_move(move2, 6, 200);
+    hash.store_move(move2, 6, 200, HashFlag::Exact);
     let entry = hash.probe().unwrap();
     assert_eq!(entry.best_move.unwrap().from, Square::D2);
     assert_eq!(entry.best_move.unwrap().to, Square::D4);
@@ -410,12 +410,12 @@ fn hash_table_collision_handling() {
 
     // Store move at key1
     hash.current_key = 1000;
-    hash.store_move(move1, 5, 100);
+    hash.store_move(move1, 5, 100, HashFlag::Exact);
 
     // Change to different key that might collide in table
     // (Same index after modulo NUM_HASH_SLOTS but different key)
     hash.current_key = 1001;
-    hash.store_move(move2, 5, 200);
+    hash.store_move(move2, 5, 200, HashFlag::Exact);
 
     // Should retrieve move2, not move1 (collision detected by hash_key check)
     let entry = hash.probe().unwrap();
@@ -423,6 +423,168 @@ fn hash_table_collision_handling() {
     assert_eq!(entry.best_move.unwrap().to, Square::D4);
 }
 
+#[test]
+fn hash_store_records_bound_flag() {
+    let mut hash = Hash::new();
+    let test_move = create_test_move(Square::E2, Square::E4);
+
+    hash.current_key = 12345;
+    hash.store_move(test_move, 4, 150, HashFlag::LowerBound);
+
+    let entry = hash.probe().unwrap();
+    assert_eq!(entry.flag, HashFlag::LowerBound);
+}
+
+#[test]
+fn hash_entry_cutoff_respects_depth() {
+    let mut hash = Hash::new();
+    hash.current_key = 12345;
+    hash.store_move(
+        create_test_move(Square::E2, Square::E4),
+        4,
+        50,
+        HashFlag::Exact,
+    );
+
+    let entry = hash.probe().unwrap();
+    assert_eq!(entry.cutoff_score(4, -100, 100), Some(50));
+    assert_eq!(
+        entry.cutoff_score(5, -100, 100),
+        None,
+        "A shallower entry must not produce a cutoff"
+    );
+}
+
+#[test]
+fn hash_entry_cutoff_respects_bounds() {
+    let mut hash = Hash::new();
+    let test_move = create_test_move(Square::E2, Square::E4);
+
+    hash.current_key = 1;
+    hash.store_move(test_move, 4, 200, HashFlag::LowerBound);
+    let entry = hash.probe().unwrap();
+    assert_eq!(
+        entry.cutoff_score(4, 0, 100),
+        Some(200),
+        "Fails high above beta"
+    );
+    assert_eq!(
+        entry.cutoff_score(4, 0, 300),
+        None,
+        "Lower bound inside window"
+    );
+
+    hash.current_key = 2;
+    hash.store_move(test_move, 4, -200, HashFlag::UpperBound);
+    let entry = hash.probe().unwrap();
+    assert_eq!(
+        entry.cutoff_score(4, -100, 0),
+        Some(-200),
+        "Fails low below alpha"
+    );
+    assert_eq!(
+        entry.cutoff_score(4, -300, 0),
+        None,
+        "Upper bound inside window"
+    );
+}
+
+#[test]
+fn hash_mate_scores_are_adjusted_by_ply() {
+    let mate_in_3_from_root = 10_000 - 5;
+
+    // Stored relative to the node at ply 2, then read back at ply 4 (a transposition)
+    let stored = score_to_hash(mate_in_3_from_root, 2);
+    assert_eq!(stored, 10_000 - 3);
+    assert_eq!(score_from_hash(stored, 4), 10_000 - 7);
+
+    // Being mated is adjusted in the opposite direction
+    let stored = score_to_hash(-mate_in_3_from_root, 2);
+    assert_eq!(score_from_hash(stored, 2), -mate_in_3_from_root);
+
+    // Regular scores are untouched
+    assert_eq!(score_to_hash(123, 10), 123);
+    assert_eq!(score_from_hash(-123, 10), -123);
+}
+
+#[test]
+fn hash_resize_table_sets_size_and_clears_entries() {
+    let mut hash = Hash::new();
+    hash.current_key = 12345;
+    hash.store_move(create_test_move(Square::E2, Square::E4), 5, 100, HashFlag::Exact);
+
+    hash.resize_table(1);
+
+    assert!(hash.num_slots() > 0 && hash.num_slots() < 1024 * 1024);
+    assert!(hash.probe().is_none(), "Resizing should empty the table");
+
+    // Entries work as usual after resizing
+    hash.store_move(create_test_move(Square::D2, Square::D4), 3, 50, HashFlag::Exact);
+    assert_eq!(hash.probe().unwrap().depth, 3);
+}
+
+#[test]
+fn hash_clear_table_keeps_size() {
+    let mut hash = Hash::new();
+    let num_slots = hash.num_slots();
+    hash.current_key = 12345;
+    hash.store_move(create_test_move(Square::E2, Square::E4), 5, 100, HashFlag::Exact);
+
+    hash.clear_table();
+
+    assert!(hash.probe().is_none(), "Clearing should empty the table");
+    assert_eq!(hash.num_slots(), num_slots);
+}
+
+#[test]
+fn hash_entry_round_trips_promotion_and_negative_score() {
+    let mut hash = Hash::new();
+    let mut test_move = create_test_move(Square::B7, Square::A8);
+    test_move.promote = Some(Piece::Knight);
+
+    hash.current_key = u64::MAX;
+    hash.store_move(test_move, 200, -9_990, HashFlag::UpperBound);
+
+    let entry = hash.probe().unwrap();
+    let best_move = entry.best_move.unwrap();
+    assert_eq!(best_move.from, Square::B7);
+    assert_eq!(best_move.to, Square::A8);
+    assert_eq!(best_move.promote, Some(Piece::Knight));
+    assert_eq!(entry.depth, 200);
+    assert_eq!(entry.score, -9_990);
+    assert_eq!(entry.flag, HashFlag::UpperBound);
+}
+
+#[test]
+fn hash_clones_share_table() {
+    let mut hash = Hash::new();
+    let mut clone = hash.clone();
+    assert!(hash.shares_table_with(&clone));
+
+    clone.current_key = 12345;
+    clone.store_move(create_test_move(Square::E2, Square::E4), 5, 100, HashFlag::Exact);
+
+    hash.current_key = 12345;
+    assert_eq!(hash.probe().unwrap().best_move.unwrap().to, Square::E4);
+
+    // Resizing gives this hash its own table
+    hash.resize_table(1);
+    assert!(!hash.shares_table_with(&clone));
+}
+
+#[test]
+fn hash_take_back_double_push_at_root_restores_key() {
+    // Kiwipete: a2a4 leaves an en passant square that b4 can capture
+    let mut position =
+        position_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let original_key = position.board.hash.current_key;
+
+    assert!(position.make_move(Square::A2, Square::A4, None));
+    position.take_back_move();
+
+    assert_eq!(position.board.hash.current_key, original_key);
+}
+
 #[test]
 fn hash_all_pieces_affect_hash_uniquely() {
     let pieces = valid_pieces();
@@ -563,3 +725,48 @@ fn hash_en_passant_affects_uniqueness() {
         "En passant should affect hash"
     );
 }
+
+#[test]
+fn hash_pawn_key_only_tracks_pawns() {
+    let mut hash = Hash::new();
+
+    hash.toggle_piece(Side::White, Piece::Knight, Square::G1);
+    hash.toggle_side_to_move();
+    assert_eq!(hash.pawn_key, 0, "Only pawns should change the pawn key");
+
+    hash.toggle_piece(Side::White, Piece::Pawn, Square::E4);
+    assert_ne!(hash.pawn_key, 0);
+
+    hash.toggle_piece(Side::White, Piece::Pawn, Square::E4);
+    assert_eq!(hash.pawn_key, 0, "Toggling a pawn twice should cancel");
+}
+
+#[test]
+fn hash_store_and_probe_pawns() {
+    let mut hash = Hash::new();
+    hash.toggle_piece(Side::White, Piece::Pawn, Square::E4);
+    hash.toggle_piece(Side::Black, Piece::Pawn, Square::D5);
+
+    let entry = PawnEntry {
+        score: [TaperedScore::new(-12, 30), TaperedScore::new(5, -40)],
+        kingside_shield: [25, -3],
+        queenside_shield: [0, 18],
+    };
+
+    assert!(hash.probe_pawns().is_none());
+    hash.store_pawns(&entry);
+    assert_eq!(hash.probe_pawns(), Some(entry));
+
+    // Different pawns
+    hash.toggle_piece(Side::Black, Piece::Pawn, Square::D5);
+    assert!(hash.probe_pawns().is_none());
+    hash.toggle_piece(Side::Black, Piece::Pawn, Square::D5);
+
+    // Values that don't fit aren't stored
+    hash.clear_pawn_table();
+    hash.store_pawns(&PawnEntry {
+        kingside_shield: [100_000, 0],
+        ..entry
+    });
+    assert!(hash.probe_pawns().is_none());
+}
@@ -1302,6 +1302,92 @@ fn generate_captures_pawn_promotions() {
 // EDGE CASES AND COMPLEX SCENARIOS
 // ============================================================================
 
+#[test]
+fn generate_captures_orders_losing_captures_last() {
+    // Rxd5 wins a knight but loses the rook to exd5; Bxa7 wins a pawn for free
+    let mut position = position_from_fen("4k3/p7/4p3/3n4/8/8/8/3RK1B1 w - - 0 1");
+
+    reset_move_state(&mut position);
+    position.generate_captures(Side::White);
+
+    let score_of = |from: Square, to: Square| {
+        position.move_list[..position.first_move[1] as usize]
+            .iter()
+            .flatten()
+            .find(|mv| mv.from == from && mv.to == to)
+            .map(|mv| mv.score)
+            .unwrap()
+    };
+
+    let winning = score_of(Square::G1, Square::A7);
+    let losing = score_of(Square::D1, Square::D5);
+
+    assert!(
+        losing < 0,
+        "Losing capture should be scored below quiet moves"
+    );
+    assert!(winning > losing);
+}
+
+#[test]
+fn static_exchange_evaluation_undefended_piece() {
+    let position = position_from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1");
+    assert_eq!(
+        position.static_exchange_evaluation(Square::E1, Square::E5, None),
+        100
+    );
+}
+
+#[test]
+fn static_exchange_evaluation_includes_x_ray_attackers() {
+    // Nxe5 Nxe5 Rxe5 Bxe5 Qxe5 Qxe5: the queens join the exchange from behind the rook and bishop
+    let position = position_from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1");
+    assert_eq!(
+        position.static_exchange_evaluation(Square::D3, Square::E5, None),
+        -200
+    );
+
+    // The second rook backs up the first, so taking the defended pawn wins it
+    let position = position_from_fen("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1");
+    assert_eq!(
+        position.static_exchange_evaluation(Square::E2, Square::E5, None),
+        100
+    );
+}
+
+#[test]
+fn static_exchange_evaluation_stops_when_recapture_loses() {
+    // After QxP, black's pawn recaptures. White's queen never gets back its value.
+    let position = position_from_fen("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1");
+    assert_eq!(
+        position.static_exchange_evaluation(Square::E1, Square::E5, None),
+        -800
+    );
+
+    // Kxe5 puts the king en prise to the rook, so it counts as losing the king
+    let position = position_from_fen("8/8/3k4/4P3/8/8/8/4RK2 b - - 0 1");
+    assert_eq!(
+        position.static_exchange_evaluation(Square::D6, Square::E5, None),
+        -9900
+    );
+}
+
+#[test]
+fn static_exchange_evaluation_en_passant_and_promotion() {
+    let position = position_from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    assert_eq!(
+        position.static_exchange_evaluation(Square::E5, Square::D6, None),
+        100
+    );
+
+    // e8=Q Rxe8 Rxe8: the new queen is traded for the rook. White's rook was behind the pawn.
+    let position = position_from_fen("r5k1/4P3/8/8/8/8/8/4R1K1 w - - 0 1");
+    assert_eq!(
+        position.static_exchange_evaluation(Square::E7, Square::E8, Some(Piece::Queen)),
+        400
+    );
+}
+
 #[test]
 fn multiple_pieces_same_type_generate_independently() {
     let mut position = empty_position_with_kings(Side::White);
@@ -0,0 +1,157 @@
+/// Tests for the NNUE evaluation and its incrementally updated accumulator
+mod test_utils;
+
+use std::sync::Arc;
+
+use chess_engine::{
+    engine::Engine,
+    nnue::{NUM_FEATURES, Network},
+    position::Position,
+    types::{Piece, Square},
+};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use test_utils::*;
+
+const HIDDEN_SIZE: usize = 32;
+
+/// A network file with small random weights, in the format `Network::load` reads
+fn random_network_bytes(seed: u64) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(b"CENN");
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(&(HIDDEN_SIZE as u32).to_le_bytes());
+
+    for _ in 0..NUM_FEATURES * HIDDEN_SIZE {
+        bytes.extend_from_slice(&rng.gen_range(-64i16..64).to_le_bytes());
+    }
+    for _ in 0..HIDDEN_SIZE {
+        bytes.extend_from_slice(&rng.gen_range(0i16..128).to_le_bytes());
+    }
+    for _ in 0..2 * HIDDEN_SIZE {
+        bytes.extend_from_slice(&rng.gen_range(-64i16..64).to_le_bytes());
+    }
+    bytes.extend_from_slice(&rng.gen_range(-1000i32..1000).to_le_bytes());
+
+    bytes
+}
+
+fn random_network(seed: u64) -> Arc<Network> {
+    Arc::new(Network::from_bytes(&random_network_bytes(seed)).unwrap())
+}
+
+/// The evaluation from an accumulator rebuilt from scratch for the current board
+fn refreshed_evaluation(position: &Position) -> i32 {
+    let mut position = position.clone();
+    position.board.set_network(position.board.network());
+    position.evaluate()
+}
+
+#[test]
+fn network_loads_from_file() {
+    let path = std::env::temp_dir().join("chess_engine_nnue_tests_load.nnue");
+    std::fs::write(&path, random_network_bytes(1)).unwrap();
+
+    let network = Network::load(path.to_str().unwrap()).unwrap();
+    assert_eq!(network.hidden_size(), HIDDEN_SIZE);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn network_rejects_invalid_files() {
+    let bytes = random_network_bytes(1);
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'X';
+    assert!(Network::from_bytes(&bad_magic).is_err());
+
+    let mut bad_version = bytes.clone();
+    bad_version[4] = 2;
+    assert!(Network::from_bytes(&bad_version).is_err());
+
+    let mut bad_hidden_size = bytes.clone();
+    bad_hidden_size[8] = 17;
+    assert!(Network::from_bytes(&bad_hidden_size).is_err());
+
+    assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Network::from_bytes(&[]).is_err());
+    assert!(Network::load("missing.nnue").is_err());
+}
+
+#[test]
+fn accumulator_matches_refresh_after_each_move() {
+    let mut position = position_from_fen("r3k2r/1P6/8/3pP3/8/8/6p1/R3K2R w KQkq d6 0 1");
+    position.board.set_network(Some(random_network(2)));
+
+    let start = position.evaluate();
+    assert_eq!(start, refreshed_evaluation(&position));
+
+    let moves = [
+        (Square::E5, Square::D6, None),                // En passant
+        (Square::E8, Square::G8, None),                // Castling
+        (Square::B7, Square::A8, Some(Piece::Queen)),  // Capturing promotion
+        (Square::G2, Square::H1, Some(Piece::Knight)), // Capturing underpromotion
+        (Square::E1, Square::C1, None),                // Castling
+        (Square::F8, Square::A8, None),                // Capture
+    ];
+
+    for (from, to, promote) in moves {
+        assert!(position.make_move(from, to, promote));
+        assert_eq!(position.evaluate(), refreshed_evaluation(&position));
+    }
+
+    for _ in moves {
+        position.take_back_move();
+        assert_eq!(position.evaluate(), refreshed_evaluation(&position));
+    }
+
+    assert_eq!(position.evaluate(), start);
+}
+
+#[test]
+fn network_evaluation_is_colour_symmetric() {
+    let network = random_network(3);
+
+    let mut position =
+        position_from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let mut mirrored =
+        position_from_fen("rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 3");
+
+    position.board.set_network(Some(Arc::clone(&network)));
+    mirrored.board.set_network(Some(network));
+
+    assert_eq!(position.evaluate(), mirrored.evaluate());
+}
+
+#[test]
+fn engine_switches_between_network_and_handcrafted_evaluation() {
+    let path = std::env::temp_dir().join("chess_engine_nnue_tests_engine.nnue");
+    std::fs::write(&path, random_network_bytes(4)).unwrap();
+
+    let mut engine = Engine::default();
+    let handcrafted = engine.position.evaluate();
+    assert!(!engine.uses_network_eval());
+
+    engine.load_eval_file(path.to_str().unwrap()).unwrap();
+    assert!(engine.uses_network_eval());
+    assert_ne!(engine.position.evaluate(), handcrafted);
+
+    // A new position keeps the network
+    engine.new_game();
+    assert!(engine.uses_network_eval());
+    assert_eq!(
+        engine.position.evaluate(),
+        refreshed_evaluation(&engine.position)
+    );
+
+    engine.use_handcrafted_eval();
+    assert!(!engine.uses_network_eval());
+    assert_eq!(engine.position.evaluate(), handcrafted);
+
+    assert!(engine.load_eval_file("missing.nnue").is_err());
+    assert!(!engine.uses_network_eval());
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -1,10 +1,13 @@
 mod test_utils;
-use chess_engine::{engine::Engine, position::Position};
+use chess_engine::{
+    engine::{Engine, SearchLine},
+    position::Position,
+};
 
 #[test]
 fn test_pv_collection() {
     let mut engine = Engine::new(None, None, None, None, None, Some(5), None, None, None);
-    let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+    let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
     // Verify we have a PV
     assert!(
@@ -34,7 +37,7 @@ fn test_pv_mate_in_one() {
 
     // Set up a mate in one position via new_game + moves
     // For now, just test with starting position
-    let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+    let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
     // Verify we have a PV
     assert!(
@@ -53,7 +56,7 @@ fn test_pv_depth_increases() {
     let mut engine = Engine::new(None, None, None, None, None, Some(6), None, None, None);
 
     // Starting position is already loaded
-    let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+    let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
     // With depth 6, we should get a reasonable PV
     // (it might be shorter due to repetitions, exchanges, or quiet positions)
@@ -16,8 +16,9 @@
 mod test_utils;
 
 use chess_engine::{
+    engine::SearchLine,
     position::Position,
-    types::{Piece, Square},
+    types::{Board, MoveData, Piece, Score, ScoreBound, Square},
 };
 use test_utils::*;
 
@@ -25,7 +26,7 @@ use test_utils::*;
 fn search_position(fen: &str, depth: u16) -> Option<(Square, Square)> {
     let mut engine = engine_from_fen(fen, depth);
 
-    let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+    let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
     match (result.best_move_from, result.best_move_to) {
         (Some(from), Some(to)) => Some((from, to)),
@@ -49,7 +50,7 @@ mod basic_search {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 3);
 
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should complete and find a move
         assert!(
@@ -63,7 +64,7 @@ mod basic_search {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 2);
 
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         if let (Some(from), Some(to)) = (result.best_move_from, result.best_move_to) {
             // Generate legal moves and verify the returned move is legal
@@ -99,7 +100,7 @@ mod tactical_search {
         // Back rank mate setup: white queen can deliver mate
         let fen = "6k1/5ppp/8/8/8/8/5PPP/4Q1K1 w - - 0 1";
         let mut engine = engine_from_fen(fen, 3);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // The search should find a mating move
         assert!(
@@ -113,7 +114,7 @@ mod tactical_search {
         // Opening position where white should develop pieces
         let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should find a reasonable developing move
         assert!(result.best_move_from.is_some(), "Should find a move");
@@ -133,7 +134,7 @@ mod tactical_search {
         // Position from the Italian Game
         let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should find a move
         assert!(
@@ -151,7 +152,7 @@ mod quiescence_search {
         // Standard Sicilian Defense position with tactical possibilities
         let fen = "rnbqkb1r/pp1ppppp/5n2/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
         let mut engine = engine_from_fen(fen, 2);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should consider the position
         assert!(result.best_move_from.is_some(), "Should find a move");
@@ -162,7 +163,7 @@ mod quiescence_search {
         // Quiet position - standard starting position
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 1);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should complete without hanging
         assert!(
@@ -177,7 +178,7 @@ mod quiescence_search {
         // Kiwipete position with many tactical possibilities
         let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 3);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should find a legal move
         assert!(result.best_move_from.is_some(), "Should find a move");
@@ -195,7 +196,7 @@ mod check_extensions {
         let mut engine = engine_from_fen(fen, 4);
 
         let nodes_before = engine.position.nodes;
-        engine.think(None::<fn(u16, i32, &mut Position)>);
+        engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
         let nodes_after = engine.position.nodes;
 
         // Should search some nodes
@@ -207,7 +208,7 @@ mod check_extensions {
         // Complex position for testing en passant and promotion
         let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should not crash or infinite loop
         assert!(
@@ -227,7 +228,7 @@ mod move_ordering {
         let mut engine = engine_from_fen(fen, 4);
 
         // First search to populate hash
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         let first_move = result.best_move_from;
 
@@ -282,6 +283,60 @@ mod move_ordering {
             assert!(cap > quiet, "Captures should score higher than quiet moves");
         }
     }
+
+    #[test]
+    fn test_beta_cutoffs_record_killers_and_countermoves() {
+        // A quiet opening, where many cutoffs come from quiet moves
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let mut engine = engine_from_fen(fen, 5);
+
+        engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        let position = &engine.position;
+        assert!(
+            position
+                .killer_moves
+                .iter()
+                .any(|killers| killers[0].is_some())
+        );
+        assert!(
+            position
+                .countermoves
+                .iter()
+                .flatten()
+                .any(|reply| reply.is_some())
+        );
+
+        // The older killer is only filled by a different cutoff move
+        for killers in &position.killer_moves {
+            if let [Some(newest), Some(older)] = killers {
+                assert_ne!(newest, older);
+            }
+        }
+    }
+
+    #[test]
+    fn test_think_clears_killers_and_countermoves() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 1);
+
+        let stale = Some(MoveData {
+            from: Square::A2,
+            to: Square::A3,
+            promote: None,
+        });
+        let last_ply = engine.position.killer_moves.len() - 1;
+        engine.position.killer_moves[last_ply] = [stale, stale];
+        engine.position.countermoves[Square::H7 as usize][Square::H6 as usize] = stale;
+
+        engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(engine.position.killer_moves[last_ply], [None, None]);
+        assert_eq!(
+            engine.position.countermoves[Square::H7 as usize][Square::H6 as usize],
+            None
+        );
+    }
 }
 
 mod depth_and_reduction {
@@ -291,7 +346,7 @@ mod depth_and_reduction {
     fn test_search_respects_depth_limit() {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 2);
-        engine.think(None::<fn(u16, i32, &mut Position)>);
+        engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should complete quickly with low depth
         assert!(
@@ -305,11 +360,11 @@ mod depth_and_reduction {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
         let mut engine1 = engine_from_fen(fen, 2);
-        engine1.think(None::<fn(u16, i32, &mut Position)>);
+        engine1.think(None::<fn(u16, &[SearchLine], &mut Position)>);
         let nodes_depth2 = engine1.position.nodes;
 
         let mut engine2 = engine_from_fen(fen, 3);
-        engine2.think(None::<fn(u16, i32, &mut Position)>);
+        engine2.think(None::<fn(u16, &[SearchLine], &mut Position)>);
         let nodes_depth3 = engine2.position.nodes;
 
         assert!(
@@ -318,13 +373,38 @@ mod depth_and_reduction {
         );
     }
 
+    #[test]
+    fn test_reduced_quiet_key_move_still_mates() {
+        // 1. Kb6 (or Kc7) forces 2. Rh8# (or Ra1#). The quiet king move is ordered late
+        // and reduced, so the re-search must find it.
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let mut engine = engine_from_fen(fen, 6);
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.evaluation, Score::Mate(2));
+        assert_eq!(result.best_move_from, Some(Square::C6));
+    }
+
+    #[test]
+    fn test_late_move_pruning_keeps_checks() {
+        // Mate in 2 with a rook ladder: the mating check comes late among many quiet rook
+        // moves in a shallow null-window node
+        let fen = "7k/8/8/8/8/8/R7/1R4K1 w - - 0 1";
+        let mut engine = engine_from_fen(fen, 4);
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.evaluation, Score::Mate(2));
+    }
+
     #[test]
     #[ignore] // TODO: Too slow
     fn test_search_with_reductions() {
         // Kiwipete position - standard perft testing position
         let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 4);
-        engine.think(None::<fn(u16, i32, &mut Position)>);
+        engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should complete in reasonable time
         assert!(
@@ -353,7 +433,7 @@ mod search_stability {
         // Position 5 from perft - complex middlegame
         let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         assert!(
             result.best_move_from.is_some(),
@@ -367,7 +447,7 @@ mod search_stability {
         // Position 3 from perft - endgame-like position
         let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         assert!(result.best_move_from.is_some(), "Should handle endgames");
     }
@@ -378,7 +458,7 @@ mod search_stability {
         // Position 6 from perft - middlegame position
         let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         assert!(
             result.best_move_from.is_some(),
@@ -395,7 +475,7 @@ mod edge_cases {
         // Position with castling rights on both sides
         let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 3);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         assert!(
             result.best_move_from.is_some(),
@@ -407,7 +487,7 @@ mod edge_cases {
     fn test_search_with_castling_rights() {
         let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Should consider castling
         assert!(
@@ -420,7 +500,7 @@ mod edge_cases {
     fn test_search_in_opening() {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 3);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         assert!(
             result.best_move_from.is_some(),
@@ -433,7 +513,7 @@ mod edge_cases {
         // After 1.e4
         let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         assert!(
             result.best_move_from.is_some(),
@@ -452,7 +532,7 @@ mod performance {
 
         let start = std::time::Instant::now();
 
-        engine.think(None::<fn(u16, i32, &mut Position)>);
+        engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         let duration = start.elapsed();
 
@@ -468,7 +548,7 @@ mod performance {
     fn test_node_count_is_reasonable() {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 3);
-        engine.think(None::<fn(u16, i32, &mut Position)>);
+        engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Depth 3 should visit reasonable number of nodes
         assert!(
@@ -482,6 +562,465 @@ mod performance {
     }
 }
 
+mod search_termination {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn test_node_limit_returns_move_and_restores_position() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 30);
+        engine.search_settings.max_nodes = Some(20_000);
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert!(
+            result.best_move_from.is_some(),
+            "Should return the best move from the last completed depth"
+        );
+        assert!(result.depth < 30, "Node limit should end the search early");
+        assert_eq!(engine.position.ply, 0, "Aborted line should be unwound");
+        assert_eq!(engine.position.to_fen(), fen);
+    }
+
+    #[test]
+    fn test_stop_signal_ends_search_from_another_thread() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 30);
+        let stop_signal = engine.stop_signal();
+
+        let handle = thread::spawn(move || {
+            let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+            (engine, result)
+        });
+
+        thread::sleep(Duration::from_millis(200));
+        stop_signal.store(true, std::sync::atomic::Ordering::Relaxed);
+
+        let (mut engine, result) = handle.join().expect("Search thread should not panic");
+
+        assert!(result.best_move_from.is_some(), "Should return a move");
+        assert!(result.depth < 30, "Stop should end the search early");
+        assert_eq!(engine.position.to_fen(), fen);
+
+        // The signal is cleared, so the next search runs normally
+        engine.search_settings.max_depth = 2;
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+        assert_eq!(result.depth, 2);
+    }
+}
+
+mod pondering {
+    use super::*;
+    use std::{sync::atomic::Ordering, thread, time::Duration};
+
+    #[test]
+    fn test_ponder_move_is_second_pv_move() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut engine = engine_from_fen(fen, 4);
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert!(result.principal_variation.len() >= 2);
+        assert_eq!(result.ponder_move, Some(result.principal_variation[1]));
+    }
+
+    #[test]
+    fn test_ponder_signal_holds_clock_until_ponderhit() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 30);
+        engine.search_settings.movetime = Some(50);
+        let ponder_signal = engine.ponder_signal();
+        ponder_signal.store(true, Ordering::Relaxed);
+
+        let handle =
+            thread::spawn(move || engine.think(None::<fn(u16, &[SearchLine], &mut Position)>));
+
+        thread::sleep(Duration::from_millis(300));
+        assert!(!handle.is_finished(), "Movetime is ignored while pondering");
+
+        ponder_signal.store(false, Ordering::Relaxed);
+        let result = handle.join().expect("Search thread should not panic");
+
+        assert!(result.best_move_from.is_some());
+        assert!(result.time_ms < 300, "The clock starts at ponderhit");
+    }
+}
+
+mod null_move_pruning {
+    use super::*;
+
+    #[test]
+    fn test_null_move_passes_turn_and_clears_en_passant() {
+        // Black could capture en passant on e3
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let mut position = position_from_fen(fen);
+        let original_hash = position.board.hash.current_key;
+
+        position.make_null_move();
+
+        let expected =
+            position_from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(position.side, expected.side);
+        assert_eq!(
+            position.board.hash.current_key,
+            expected.board.hash.current_key
+        );
+
+        position.take_back_null_move();
+
+        assert_eq!(position.board.hash.current_key, original_hash);
+        assert_eq!(position.ply, 0);
+        assert_eq!(position.to_fen(), fen);
+    }
+
+    #[test]
+    fn test_aborted_search_unwinds_null_moves() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+        // Abort at many different points, some inside null-move searches
+        for max_nodes in (5_000..50_000).step_by(2_500) {
+            let mut engine = engine_from_fen(fen, 30);
+            let original_hash = engine.position.board.hash.current_key;
+            engine.search_settings.max_nodes = Some(max_nodes);
+
+            engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+            assert_eq!(engine.position.ply, 0);
+            assert_eq!(engine.position.to_fen(), fen);
+            assert_eq!(engine.position.board.hash.current_key, original_hash);
+        }
+    }
+}
+
+mod aspiration_windows {
+    use super::*;
+
+    #[test]
+    fn test_failed_window_is_reported_as_bound() {
+        // The mate is found after several iterations scoring around +6.5, so it fails high
+        let fen = "k7/8/8/3K4/8/8/8/7R w - - 0 1";
+        let mut engine = engine_from_fen(fen, 8);
+        let mut reports = Vec::new();
+
+        let result = engine.think(Some(
+            |depth, lines: &[SearchLine], _position: &mut Position| {
+                let line = lines.last().unwrap();
+                reports.push((depth, line.score, line.bound));
+            },
+        ));
+
+        let fail_high = reports
+            .iter()
+            .position(|&(_, _, bound)| bound == ScoreBound::Lower)
+            .expect("Jump to a mate score should fail high");
+
+        // The same depth is then searched again with a wider window
+        let (depth, _, _) = reports[fail_high];
+        assert!(reports[fail_high + 1..].contains(&(depth, Score::Mate(3), ScoreBound::Exact)));
+
+        assert_eq!(result.evaluation, Score::Mate(3));
+        assert_eq!(result.lines[0].bound, ScoreBound::Exact);
+    }
+
+    #[test]
+    fn test_multi_pv_lines_are_exact() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 6);
+        engine.search_settings.multi_pv = 3;
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.lines.len(), 3);
+        assert!(
+            result
+                .lines
+                .iter()
+                .all(|line| line.bound == ScoreBound::Exact)
+        );
+        assert!(
+            result
+                .lines
+                .windows(2)
+                .all(|pair| pair[0].score >= pair[1].score)
+        );
+    }
+}
+
+mod frontier_pruning {
+    use super::*;
+    use chess_engine::engine::Engine;
+
+    #[test]
+    fn test_quiet_mate_survives_frontier_pruning() {
+        // 1. Kb6 forces 2. Rh8#. Black's caged queen and knight put White far behind on the
+        // static evaluation, so only a search that doesn't trust it at the frontier sees the mate.
+        let fen = "k7/8/2K5/8/8/8/pp6/qn5R w - - 0 1";
+        let mut engine = engine_from_fen(fen, 5);
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.evaluation, Score::Mate(2));
+        assert_eq!(result.best_move_from, Some(Square::C6));
+        assert_eq!(result.best_move_to, Some(Square::B6));
+    }
+
+    #[test]
+    fn test_bench_node_count_is_deterministic() {
+        let first = Engine::bench(3);
+        let second = Engine::bench(3);
+
+        assert!(first.nodes > 0);
+        assert_eq!(first.nodes, second.nodes);
+    }
+}
+
+mod lazy_smp {
+    use super::*;
+
+    #[test]
+    fn test_helper_threads_add_nodes_and_keep_position() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 4);
+        engine.search_settings.threads = 4;
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.depth, 4);
+        assert!(engine.position.helper_nodes > 0, "Helpers should search");
+        assert_eq!(
+            result.nodes,
+            engine.position.nodes + engine.position.helper_nodes,
+            "Node counts aggregate across threads"
+        );
+        assert_eq!(engine.position.to_fen(), fen);
+
+        let best_move = Board::move_to_uci_string(
+            result.best_move_from.unwrap(),
+            result.best_move_to.unwrap(),
+            result.best_move_promote,
+            false,
+        );
+        assert!(engine.position.get_legal_moves().contains(&best_move));
+
+        // Helpers are stopped through the shared signal, which is left clear
+        assert!(
+            !engine
+                .stop_signal()
+                .load(std::sync::atomic::Ordering::Relaxed)
+        );
+    }
+
+    #[test]
+    fn test_node_limit_counts_all_threads() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 20);
+        engine.search_settings.threads = 4;
+        engine.search_settings.max_nodes = Some(20_000);
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert!(result.nodes <= 20_000, "{} nodes searched", result.nodes);
+        assert!(result.best_move_from.is_some());
+    }
+
+    #[test]
+    fn test_threads_find_same_mate() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 3);
+        engine.search_settings.threads = 3;
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.best_move_from, Some(Square::H5));
+        assert_eq!(result.best_move_to, Some(Square::F7));
+        assert_eq!(result.evaluation, Score::Mate(1));
+    }
+}
+
+mod go_limits {
+    use super::*;
+    use chess_engine::{time::TimeManager, types::MoveData};
+
+    #[test]
+    fn test_search_moves_restrict_root() {
+        // Qxf7# is available but only the listed moves may be played
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 3);
+        let allowed = [
+            MoveData {
+                from: Square::A2,
+                to: Square::A3,
+                promote: None,
+            },
+            MoveData {
+                from: Square::B2,
+                to: Square::B3,
+                promote: None,
+            },
+        ];
+        engine.search_settings.search_moves = allowed.to_vec();
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        let best_move = result.lines[0].best_move().unwrap();
+        assert!(allowed.contains(&best_move), "Played {:?}", best_move);
+        assert!(engine.position.root_search_moves.is_empty());
+    }
+
+    #[test]
+    fn test_mate_search_proves_mate_in_two() {
+        // Rook ladder: 1. Ra7 Kg8 2. Rb8#
+        let fen = "7k/8/8/8/8/8/R7/1R4K1 w - - 0 1";
+        let mut engine = engine_from_fen(fen, 1);
+        engine.search_settings.max_depth = 100;
+        engine.search_settings.mate = Some(2);
+        engine.search_settings.infinite = true;
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.evaluation, Score::Mate(2));
+        assert!(result.depth <= 4, "A mate in 2 is found within 4 plies");
+    }
+
+    #[test]
+    fn test_mate_search_stops_when_refuted() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 1);
+        engine.search_settings.max_depth = 100;
+        engine.search_settings.mate = Some(2);
+        engine.search_settings.infinite = true;
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.depth, 4);
+        assert!(!result.evaluation.is_mate());
+    }
+
+    #[test]
+    fn test_moves_to_go_spreads_remaining_time() {
+        let sudden_death = TimeManager::new(90_000, 90_000, 0, 0, None, None, true);
+        let few_moves_left = TimeManager::new(90_000, 90_000, 0, 0, Some(5), None, true);
+        let many_moves_left = TimeManager::new(90_000, 90_000, 0, 0, Some(40), None, true);
+
+        assert!(few_moves_left.hard_limit > sudden_death.hard_limit);
+        assert!(many_moves_left.hard_limit < sudden_death.hard_limit);
+        assert_eq!(many_moves_left.hard_limit.as_millis(), 90_000 / 40);
+    }
+}
+
+mod multi_pv {
+    use super::*;
+
+    #[test]
+    fn test_multi_pv_returns_ranked_distinct_lines() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut engine = engine_from_fen(fen, 3);
+        engine.search_settings.multi_pv = 3;
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(
+            result.principal_variation,
+            result.lines[0].principal_variation
+        );
+        assert_eq!(result.evaluation, result.lines[0].score);
+
+        for (i, line) in result.lines.iter().enumerate() {
+            assert_eq!(line.depth, 3);
+            assert!(!line.principal_variation.is_empty());
+
+            if i > 0 {
+                assert!(
+                    result.lines[i - 1].score >= line.score,
+                    "Lines should be best first"
+                );
+                assert!(
+                    !result.lines[..i]
+                        .iter()
+                        .any(|other| other.best_move() == line.best_move()),
+                    "Each line should start with a different move"
+                );
+            }
+        }
+
+        assert!(engine.position.excluded_root_moves.is_empty());
+    }
+
+    #[test]
+    fn test_multi_pv_is_limited_by_legal_moves() {
+        // Black king in the corner has only two legal moves
+        let fen = "7k/8/5K2/8/8/8/8/1R6 b - - 0 1";
+        let mut engine = engine_from_fen(fen, 2);
+        engine.search_settings.multi_pv = 5;
+
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.lines.len(), engine.position.get_legal_moves().len());
+    }
+}
+
+mod mate_scores {
+    use super::*;
+
+    #[test]
+    fn test_mate_in_one_is_reported_as_mate() {
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/4Q1K1 w - - 0 1";
+        let mut engine = engine_from_fen(fen, 3);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+        assert_eq!(result.evaluation, Score::Mate(1));
+        assert_eq!(result.evaluation.to_uci_string(), "mate 1");
+        assert_eq!(result.evaluation.to_string(), "M1");
+    }
+
+    #[test]
+    fn test_score_round_trips_through_search_score() {
+        for score in [
+            Score::Centipawns(0),
+            Score::Centipawns(-250),
+            Score::Mate(1),
+            Score::Mate(7),
+            Score::Mate(-1),
+            Score::Mate(-7),
+        ] {
+            assert_eq!(Score::from_search_score(score.to_search_score()), score);
+        }
+    }
+
+    #[test]
+    fn test_score_ordering() {
+        let ordered = [
+            Score::Mate(-1),
+            Score::Mate(-5),
+            Score::Centipawns(-900),
+            Score::Centipawns(0),
+            Score::Centipawns(900),
+            Score::Mate(5),
+            Score::Mate(1),
+        ];
+
+        for pair in ordered.windows(2) {
+            assert!(
+                pair[0] < pair[1],
+                "{:?} should be worse than {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_score_formatting() {
+        assert_eq!(Score::Centipawns(35).to_uci_string(), "cp 35");
+        assert_eq!(Score::Mate(-3).to_uci_string(), "mate -3");
+        assert_eq!(Score::Centipawns(-35).to_string(), "-35");
+        assert_eq!(Score::Mate(3).to_string(), "M3");
+        assert_eq!(Score::Mate(-3).to_string(), "-M3");
+    }
+}
+
 mod principal_variation {
     use super::*;
 
@@ -490,7 +1029,7 @@ mod principal_variation {
     fn test_pv_extracted_from_hash() {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 4);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // PV should be stored in hash table
         assert!(
@@ -507,7 +1046,7 @@ mod principal_variation {
     fn test_pv_is_legal_sequence() {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let mut engine = engine_from_fen(fen, 3);
-        let result = engine.think(None::<fn(u16, i32, &mut Position)>);
+        let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
 
         // Try to make the PV moves
         if let (Some(from), Some(to)) = (result.best_move_from, result.best_move_to) {
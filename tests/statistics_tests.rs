@@ -1,4 +1,4 @@
-use chess_engine::engine::Engine;
+use chess_engine::engine::{Engine, SearchLine};
 use chess_engine::position::Position;
 
 #[test]
@@ -10,7 +10,7 @@ fn test_statistics_tracking() {
         Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
 
     // Perform a search
-    let result = engine.think::<fn(u16, i32, &mut Position)>(None);
+    let result = engine.think::<fn(u16, &[SearchLine], &mut Position)>(None);
 
     // Verify statistics are being tracked
     assert!(engine.position.nodes > 0, "Nodes should be tracked");
@@ -51,7 +51,7 @@ fn test_statistics_reset_between_searches() {
     // First search
     engine.position =
         Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
-    engine.think::<fn(u16, i32, &mut Position)>(None);
+    engine.think::<fn(u16, &[SearchLine], &mut Position)>(None);
 
     let first_nodes = engine.position.nodes;
     let first_qnodes = engine.position.qnodes;
@@ -59,7 +59,7 @@ fn test_statistics_reset_between_searches() {
     // Second search
     engine.position =
         Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
-    engine.think::<fn(u16, i32, &mut Position)>(None);
+    engine.think::<fn(u16, &[SearchLine], &mut Position)>(None);
 
     // Verify statistics were reset and new values are different
     assert!(
@@ -83,7 +83,7 @@ fn test_nps_calculation() {
     engine.position =
         Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
 
-    engine.think::<fn(u16, i32, &mut Position)>(None);
+    engine.think::<fn(u16, &[SearchLine], &mut Position)>(None);
 
     let elapsed_ms = engine.position.time_manager.elapsed().as_millis();
     let nodes = engine.position.nodes;
@@ -110,7 +110,7 @@ fn test_max_depth_reached_exceeds_nominal_depth() {
         Position::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
             .unwrap();
 
-    let result = engine.think::<fn(u16, i32, &mut Position)>(None);
+    let result = engine.think::<fn(u16, &[SearchLine], &mut Position)>(None);
 
     // In tactical positions, selective depth should be greater than nominal depth
     // due to quiescence search and check extensions
@@ -131,7 +131,7 @@ fn test_beta_cutoff_percentage() {
     engine.position =
         Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
 
-    engine.think::<fn(u16, i32, &mut Position)>(None);
+    engine.think::<fn(u16, &[SearchLine], &mut Position)>(None);
 
     let total_nodes = engine.position.nodes;
     let qnodes = engine.position.qnodes;
@@ -150,3 +150,41 @@ fn test_beta_cutoff_percentage() {
         assert!(cutoff_rate <= 100, "Cutoff rate should be <= 100%");
     }
 }
+
+#[test]
+fn test_hash_statistics_tracking() {
+    let mut engine = Engine::new(None, None, None, None, None, Some(5), None, None, None);
+
+    engine.position =
+        Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+
+    engine.think::<fn(u16, &[SearchLine], &mut Position)>(None);
+
+    assert!(
+        engine.position.hash_stores > 0,
+        "Search should store positions in the hash table"
+    );
+    assert!(
+        engine.position.hash_hits > 0,
+        "Iterative deepening should hit entries stored by earlier iterations"
+    );
+}
+
+#[test]
+fn test_pawn_hash_statistics_tracking() {
+    let mut engine = Engine::new(None, None, None, None, None, Some(5), None, None, None);
+
+    engine.position =
+        Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+
+    engine.think::<fn(u16, &[SearchLine], &mut Position)>(None);
+
+    let probes = engine.position.pawn_hash_probes.get();
+    let hits = engine.position.pawn_hash_hits.get();
+
+    assert!(probes > 0, "Search should evaluate positions");
+    assert!(
+        hits > probes / 2,
+        "Most evaluations should find their pawn structure in the pawn hash table"
+    );
+}
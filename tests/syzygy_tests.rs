@@ -0,0 +1,681 @@
+/// Tests for Syzygy tablebase probing, on small synthetic table files
+mod test_utils;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chess_engine::{
+    api,
+    engine::{Engine, SearchLine},
+    position::Position,
+    syzygy::{DtzProbe, Tablebases, Wdl},
+    types::{Board, GameState, Piece, Score, Side, Square},
+};
+
+/// DTZ stored for every white-to-move position in the synthetic KRvK.rtbz, in moves
+const KRVK_DTZ_MOVES: u8 = 5;
+
+/// White to move can save the attacked rook by moving it out of the king's reach
+const ROOK_ATTACKED_FEN: &str = "8/8/8/8/8/1k6/2R5/7K w - - 0 1";
+const ROOK_SAVING_MOVES: [&str; 10] = [
+    "c2c1", "c2c5", "c2c6", "c2c7", "c2c8", "c2d2", "c2e2", "c2f2", "c2g2", "c2h2",
+];
+
+/// A KRvK WDL file where every position is won for the side with the rook: one single-value
+/// table per side to move
+fn krvk_wdl_bytes() -> Vec<u8> {
+    let mut bytes = vec![0x71, 0xE8, 0x23, 0x5D];
+    bytes.push(1); // A table for each side to move
+    bytes.push(0x00); // All three pieces in the first group
+    bytes.extend_from_slice(&[0x66, 0x44, 0xEE]); // King, rook, king for both tables
+    bytes.push(0); // Word alignment
+    bytes.extend_from_slice(&[128, 4]); // White to move: single value, win (WDL + 2)
+    bytes.extend_from_slice(&[128, 0]); // Black to move: single value, loss
+    bytes
+}
+
+/// A KRvK DTZ file storing `KRVK_DTZ_MOVES` for every white-to-move position
+fn krvk_dtz_bytes() -> Vec<u8> {
+    let mut bytes = vec![0xD7, 0x66, 0x0C, 0xA5];
+    bytes.push(0);
+    bytes.push(0x00);
+    bytes.extend_from_slice(&[0x66, 0x44, 0xEE]);
+    bytes.push(0);
+    bytes.extend_from_slice(&[128, KRVK_DTZ_MOVES]); // White to move, single value
+    bytes
+}
+
+/// The compressed fixtures' Huffman code: a symbol for each value below `NUM_VALUES` (the
+/// symbol is the value), and pair symbols for runs of values. Symbols 0-495 have 9-bit codes,
+/// 496-501 8-bit codes and 502 a 7-bit code, numbered canonically from the longest codes.
+const NUM_VALUES: u16 = 500;
+const PAIR_7_7: u16 = 500;
+const PAIR_8_9: u16 = 501;
+const TRIPLE_7: u16 = 502; // `PAIR_7_7` then 7
+const NUM_SYMBOLS: u16 = 503;
+
+/// 512-byte blocks (read in more than one chunk), and a sparse index entry every 64 values
+const BLOCK_SIZE_BITS: u8 = 9;
+const SPAN_BITS: u8 = 6;
+
+fn symbol_code(symbol: u16) -> (u32, u32) {
+    match symbol {
+        0..496 => (symbol as u32, 9),
+        496..502 => (248 + symbol as u32 - 496, 8),
+        _ => (127, 7),
+    }
+}
+
+/// One compressed table of a file, in the sections the file keeps apart
+struct CompressedTable {
+    sizes: Vec<u8>, // Code description and index array sizes
+    sparse_index: Vec<u8>,
+    block_lengths: Vec<u8>,
+    data: Vec<u8>,
+}
+
+fn compress(values: &[u16]) -> CompressedTable {
+    // Split the values into symbols, longest runs first
+    let mut symbols = Vec::new();
+    let mut i = 0;
+    while i < values.len() {
+        let rest = &values[i..];
+        let (symbol, len) = if rest.starts_with(&[7, 7, 7]) {
+            (TRIPLE_7, 3)
+        } else if rest.starts_with(&[7, 7]) {
+            (PAIR_7_7, 2)
+        } else if rest.starts_with(&[8, 9]) {
+            (PAIR_8_9, 2)
+        } else {
+            (rest[0], 1)
+        };
+        symbols.push((symbol, len));
+        i += len;
+    }
+
+    // Fill the blocks with whole symbols, first bit first
+    let block_bits = 8 << BLOCK_SIZE_BITS;
+    let mut blocks: Vec<(Vec<bool>, usize)> = vec![(Vec::new(), 0)];
+    for (symbol, len) in symbols {
+        let (code, code_len) = symbol_code(symbol);
+        if blocks.last().unwrap().0.len() + code_len as usize > block_bits {
+            blocks.push((Vec::new(), 0));
+        }
+        let (bits, block_values) = blocks.last_mut().unwrap();
+        bits.extend((0..code_len).rev().map(|bit| code >> bit & 1 == 1));
+        *block_values += len;
+    }
+
+    let mut data = Vec::new();
+    let mut block_lengths = Vec::new();
+    let mut block_starts = Vec::new();
+    let mut start = 0;
+    for (bits, block_values) in &blocks {
+        let mut bytes = vec![0u8; 1 << BLOCK_SIZE_BITS];
+        for (i, &bit) in bits.iter().enumerate() {
+            bytes[i / 8] |= (bit as u8) << (7 - i % 8);
+        }
+        data.extend(bytes);
+        block_lengths.extend((*block_values as u16 - 1).to_le_bytes());
+        block_starts.push(start);
+        start += block_values;
+    }
+
+    // Each entry points at the middle of its span
+    let span = 1 << SPAN_BITS;
+    let mut sparse_index = Vec::new();
+    for chunk in 0..values.len().div_ceil(span) {
+        let middle = chunk * span + span / 2;
+        let block = block_starts
+            .iter()
+            .rposition(|&start| start <= middle)
+            .unwrap();
+        sparse_index.extend((block as u32).to_le_bytes());
+        sparse_index.extend(((middle - block_starts[block]) as u16).to_le_bytes());
+    }
+
+    let mut sizes = vec![0, BLOCK_SIZE_BITS, SPAN_BITS, 0]; // Flags, sizes, padding
+    sizes.extend((blocks.len() as u32).to_le_bytes());
+    sizes.extend([9, 7]); // Longest and shortest codes
+    for lowest_symbol in [502u16, 496, 0] {
+        sizes.extend(lowest_symbol.to_le_bytes());
+    }
+    sizes.extend(NUM_SYMBOLS.to_le_bytes());
+    for symbol in 0..NUM_SYMBOLS {
+        let (left, right) = match symbol {
+            PAIR_7_7 => (7, 7),
+            PAIR_8_9 => (8, 9),
+            TRIPLE_7 => (PAIR_7_7, 7),
+            value => (value, 0xFFF), // A single value
+        };
+        sizes.extend([
+            left as u8,
+            (left >> 8) as u8 | (right << 4) as u8,
+            (right >> 4) as u8,
+        ]);
+    }
+    sizes.push(0); // Word alignment after an odd number of symbols
+
+    CompressedTable {
+        sizes,
+        sparse_index,
+        block_lengths,
+        data,
+    }
+}
+
+/// A DTZ file of white-to-move tables: one for pieces, or one per leading pawn file (a-d).
+/// `pieces` are the table piece codes in index order.
+fn compressed_dtz_bytes(pieces: &[u8], tables: &[CompressedTable]) -> Vec<u8> {
+    let mut bytes = vec![0xD7, 0x66, 0x0C, 0xA5];
+    bytes.push(if tables.len() == 4 { 2 } else { 0 }); // Whether there are pawns
+    for _ in tables {
+        bytes.push(0x00); // Leading group first
+        bytes.extend(pieces.iter().map(|&piece| piece | piece << 4));
+    }
+    bytes.resize(bytes.len().next_multiple_of(2), 0);
+
+    for table in tables {
+        bytes.extend(&table.sizes);
+    }
+    for table in tables {
+        bytes.extend(&table.sparse_index);
+    }
+    for table in tables {
+        bytes.extend(&table.block_lengths);
+    }
+    for table in tables {
+        bytes.resize(bytes.len().next_multiple_of(64), 0);
+        bytes.extend(&table.data);
+    }
+    bytes
+}
+
+/// Value stored at `index` in the compressed fixtures: runs of three, so every symbol is used
+fn compressed_value(index: usize, tb_file: usize) -> u16 {
+    ((index / 3 + 125 * tb_file) % NUM_VALUES as usize) as u16
+}
+
+fn rank(square: usize) -> usize {
+    square >> 3
+}
+
+fn file(square: usize) -> usize {
+    square & 7
+}
+
+/// Rank minus file: 0 on the a1-h8 diagonal, negative below it
+fn diagonal(square: usize) -> i32 {
+    rank(square) as i32 - file(square) as i32
+}
+
+/// Index of a KRvK position with white to move in the Syzygy format, written from the format's
+/// description rather than shared with the prober: the white king is moved to the a1-d1-d4
+/// triangle, then the white king, rook and black king are indexed together
+fn reference_krvk_index(white_king: usize, rook: usize, black_king: usize) -> usize {
+    let mut squares = [white_king, rook, black_king];
+    if file(squares[0]) > 3 {
+        squares = squares.map(|square| square ^ 7);
+    }
+    if rank(squares[0]) > 3 {
+        squares = squares.map(|square| square ^ 56);
+    }
+    if let Some(&first) = squares.iter().find(|&&square| diagonal(square) != 0)
+        && diagonal(first) > 0
+    {
+        squares = squares.map(|square| file(square) * 8 + rank(square));
+    }
+
+    // Squares below the diagonal: b1-d1-d3 (then a1-d4 on it), and b1-h1-h7
+    let below_diagonal = |square: usize| (0..square).filter(|&other| diagonal(other) < 0).count();
+    let triangle = |square: usize| {
+        let triangle = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+        triangle.iter().position(|&other| other == square).unwrap()
+    };
+
+    let [king, rook, other_king] = squares;
+    let rook_adjust = (rook > king) as usize;
+    let other_king_adjust = (other_king > king) as usize + (other_king > rook) as usize;
+
+    if diagonal(king) != 0 {
+        (triangle(king) * 63 + rook - rook_adjust) * 62 + other_king - other_king_adjust
+    } else if diagonal(rook) != 0 {
+        (6 * 63 + rank(king) * 28 + below_diagonal(rook)) * 62 + other_king - other_king_adjust
+    } else if diagonal(other_king) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank(king) * 7 * 28
+            + (rank(rook) - rook_adjust) * 28
+            + below_diagonal(other_king)
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank(king) * 7 * 6
+            + (rank(rook) - rook_adjust) * 6
+            + rank(other_king)
+            - other_king_adjust
+    }
+}
+
+/// Index of a KPvK position with white to move, and the table (the pawn's file, mirrored to
+/// a-d) it's in. The pawn's rank comes first, then the kings on the squares left over.
+fn reference_kpvk_index(pawn: usize, white_king: usize, black_king: usize) -> (usize, usize) {
+    let mut squares = [pawn, white_king, black_king];
+    if file(pawn) > 3 {
+        squares = squares.map(|square| square ^ 7);
+    }
+
+    let [pawn, king, other_king] = squares;
+    let king = king - (pawn < king) as usize;
+    let other_king = other_king - (pawn < other_king) as usize - (squares[1] < other_king) as usize;
+
+    (
+        (rank(pawn) - 1) + 6 * king + 6 * 63 * other_king,
+        file(pawn),
+    )
+}
+
+/// A directory holding the synthetic KRvK files (the DTZ file only if `with_dtz`)
+fn tablebase_dir(name: &str, with_dtz: bool) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("chess_engine_syzygy_tests_{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(dir.join("KRvK.rtbw"), krvk_wdl_bytes()).unwrap();
+    if with_dtz {
+        std::fs::write(dir.join("KRvK.rtbz"), krvk_dtz_bytes()).unwrap();
+    }
+    std::fs::write(dir.join("README.txt"), "Not a table").unwrap();
+
+    dir
+}
+
+fn position_with_tablebases(fen: &str, dir: &Path) -> Position {
+    let mut position = Position::from_fen(fen).unwrap();
+    let tablebases = Tablebases::open(dir.to_str().unwrap()).unwrap();
+    position.set_tablebases(Some(Arc::new(tablebases)));
+    position
+}
+
+fn best_moves(position: &mut Position) -> Vec<String> {
+    let ranked_moves = position.tablebase_root_moves().unwrap();
+    let best_rank = ranked_moves.iter().map(|&(_, rank)| rank).max();
+
+    let mut moves: Vec<String> = ranked_moves
+        .iter()
+        .filter(|&&(_, rank)| Some(rank) == best_rank)
+        .map(|(mv, _)| Board::move_to_uci_string(mv.from, mv.to, mv.promote, false))
+        .collect();
+    moves.sort();
+    moves
+}
+
+#[test]
+fn tablebases_find_table_files() {
+    let dir = tablebase_dir("find", true);
+
+    let tablebases = Tablebases::open(dir.to_str().unwrap()).unwrap();
+    assert_eq!(tablebases.num_tables(), 2);
+    assert_eq!(tablebases.max_pieces(), 3);
+
+    assert!(Tablebases::open("missing/syzygy/dir").is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn probe_wdl_reads_both_sides_to_move() {
+    let dir = tablebase_dir("wdl", false);
+
+    let mut position = position_with_tablebases(ROOK_ATTACKED_FEN, &dir);
+    assert_eq!(position.probe_wdl(), Some(Wdl::Win));
+
+    // Black to move takes the rook
+    let mut position = position_with_tablebases("8/8/8/8/8/1k6/2R5/7K b - - 0 1", &dir);
+    assert_eq!(position.probe_wdl(), Some(Wdl::Draw));
+
+    let mut position = position_with_tablebases("8/8/8/8/8/k7/8/2R4K b - - 0 1", &dir);
+    assert_eq!(position.probe_wdl(), Some(Wdl::Loss));
+
+    // Black has the rook, so the table is read with the colors swapped
+    let mut position = position_with_tablebases("7k/2r5/1K6/8/8/8/8/8 b - - 0 1", &dir);
+    assert_eq!(position.probe_wdl(), Some(Wdl::Win));
+
+    // No table for this material
+    let mut position = position_with_tablebases("8/8/8/8/8/1k6/8/2Q4K w - - 0 1", &dir);
+    assert_eq!(position.probe_wdl(), None);
+
+    // Too many pieces
+    let mut position = position_with_tablebases("8/8/8/8/8/1k6/2RR4/7K w - - 0 1", &dir);
+    assert_eq!(position.probe_wdl(), None);
+
+    // Castling rights aren't in the tables
+    let mut position = position_with_tablebases("8/8/8/8/8/1k6/8/R3K3 w Q - 0 1", &dir);
+    assert_eq!(position.probe_wdl(), None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn probe_dtz_counts_plies_to_zeroing() {
+    let dir = tablebase_dir("dtz", true);
+    let moves = KRVK_DTZ_MOVES as i32;
+
+    let mut position = position_with_tablebases(ROOK_ATTACKED_FEN, &dir);
+    assert_eq!(position.probe_dtz(), Some(2 * moves + 1));
+
+    // Only white to move is stored, so black's value comes from the replies
+    let mut position = position_with_tablebases("8/8/8/8/8/k7/8/2R4K b - - 0 1", &dir);
+    assert_eq!(position.probe_dtz(), Some(-(2 * moves + 2)));
+
+    let mut position = position_with_tablebases("8/8/8/8/8/1k6/2R5/7K b - - 0 1", &dir);
+    assert_eq!(position.probe_dtz(), Some(0));
+
+    // Without the DTZ file
+    let dir_without_dtz = tablebase_dir("dtz_missing", false);
+    let mut position = position_with_tablebases(ROOK_ATTACKED_FEN, &dir_without_dtz);
+    assert_eq!(position.probe_dtz(), None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+    std::fs::remove_dir_all(dir_without_dtz).unwrap();
+}
+
+#[test]
+fn unreadable_tables_are_reported_once() {
+    let dir = tablebase_dir("unreadable", false);
+    std::fs::write(dir.join("KQvK.rtbw"), b"Not a table").unwrap();
+
+    let mut position = position_with_tablebases("8/8/8/8/8/1k6/8/2Q4K w - - 0 1", &dir);
+    let tablebases = position.tablebases().unwrap();
+
+    assert_eq!(position.probe_wdl(), None);
+    assert_eq!(position.probe_wdl(), None);
+    let errors = tablebases.take_errors();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(errors[0].contains("KQvK.rtbw"));
+    assert!(tablebases.take_errors().is_empty());
+
+    // Other tables are still probed
+    let mut position = position_with_tablebases(ROOK_ATTACKED_FEN, &dir);
+    assert_eq!(position.probe_wdl(), Some(Wdl::Win));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Put these pieces (square indexes, a1 = 0) on an otherwise empty board
+fn set_pieces(position: &mut Position, side_to_move: Side, pieces: &[(Side, Piece, usize)]) {
+    for square in Square::iter() {
+        let piece = position.board.value[square as usize];
+        let side = if position.board.bit_units[Side::White as usize].is_bit_set(square) {
+            Side::White
+        } else {
+            Side::Black
+        };
+        position.board.remove_piece(side, piece, square);
+    }
+    for &(side, piece, square) in pieces {
+        let square = Square::try_from(square as i32).unwrap();
+        position.board.add_piece(side, piece, square);
+    }
+    position.side = side_to_move;
+}
+
+#[test]
+fn compressed_tables_match_the_reference_index() {
+    let dir = tablebase_dir("compressed", false);
+
+    let values: Vec<u16> = (0..31332).map(|index| compressed_value(index, 0)).collect();
+    let krvk = compressed_dtz_bytes(&[6, 4, 14], &[compress(&values)]); // King, rook, king
+    std::fs::write(dir.join("KRvK.rtbz"), krvk).unwrap();
+
+    let tables: Vec<CompressedTable> = (0..4)
+        .map(|tb_file| {
+            let values: Vec<u16> = (0..23436)
+                .map(|index| compressed_value(index, tb_file))
+                .collect();
+            compress(&values)
+        })
+        .collect();
+    let kpvk = compressed_dtz_bytes(&[1, 6, 14], &tables); // Pawn, king, king
+    std::fs::write(dir.join("KPvK.rtbz"), kpvk).unwrap();
+
+    let mut position = position_with_tablebases(ROOK_ATTACKED_FEN, &dir);
+    let tablebases = position.tablebases().unwrap();
+    let mut probes = 0;
+
+    // Probed for a sample of positions, and for every position stored in a pair symbol. Each
+    // position is also probed with the colors swapped and the board flipped.
+    let mut probe = |piece: Piece, first: usize, white_king: usize, black_king: usize| {
+        let (index, tb_file) = match piece {
+            Piece::Rook => (reference_krvk_index(white_king, first, black_king), 0),
+            _ => reference_kpvk_index(first, white_king, black_king),
+        };
+        let value = compressed_value(index, tb_file);
+        if !(first * 4096 + white_king * 64 + black_king).is_multiple_of(41)
+            && !(7..=9).contains(&value)
+        {
+            return;
+        }
+        let expected = Some(DtzProbe::Value(2 * value as i32 + 1));
+
+        set_pieces(
+            &mut position,
+            Side::White,
+            &[
+                (Side::White, piece, first),
+                (Side::White, Piece::King, white_king),
+                (Side::Black, Piece::King, black_king),
+            ],
+        );
+        assert_eq!(
+            tablebases.probe_dtz_table(&position, Wdl::Win),
+            expected,
+            "{:?} {}, kings {} and {}",
+            piece,
+            first,
+            white_king,
+            black_king
+        );
+
+        set_pieces(
+            &mut position,
+            Side::Black,
+            &[
+                (Side::Black, piece, first ^ 56),
+                (Side::Black, Piece::King, white_king ^ 56),
+                (Side::White, Piece::King, black_king ^ 56),
+            ],
+        );
+        assert_eq!(tablebases.probe_dtz_table(&position, Wdl::Win), expected);
+        probes += 1;
+    };
+
+    for white_king in 0..64 {
+        for black_king in (0..64).filter(|&square| square != white_king) {
+            for other in (0..64).filter(|&square| square != white_king && square != black_king) {
+                probe(Piece::Rook, other, white_king, black_king);
+                if (8..56).contains(&other) {
+                    probe(Piece::Pawn, other, white_king, black_king);
+                }
+            }
+        }
+    }
+    assert!(probes > 10_000, "{} probes", probes);
+    assert!(tablebases.take_errors().is_empty());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Against real tables, from the directory in `SYZYGY_PATH` with KRvK and KPvK. Checks some
+/// known results, and that DTZ-optimal play from a KRvK position mates in time. Run with
+/// `SYZYGY_PATH=<dir> cargo test --test syzygy_tests -- --ignored`.
+#[test]
+#[ignore] // Needs real table files, which aren't in the repository
+fn real_tables_give_known_results() {
+    let path = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH should name the tables' directory");
+    let tablebases = Arc::new(Tablebases::open(&path).unwrap());
+    let probe_wdl = |fen: &str| {
+        let mut position = Position::from_fen(fen).unwrap();
+        position.set_tablebases(Some(Arc::clone(&tablebases)));
+        position.probe_wdl()
+    };
+
+    assert_eq!(probe_wdl(ROOK_ATTACKED_FEN), Some(Wdl::Win));
+    assert_eq!(probe_wdl("8/8/8/8/8/1k6/2R5/7K b - - 0 1"), Some(Wdl::Draw));
+    assert_eq!(probe_wdl("8/8/8/8/8/k7/8/2R4K b - - 0 1"), Some(Wdl::Loss));
+    assert_eq!(probe_wdl("7k/2r5/1K6/8/8/8/8/8 b - - 0 1"), Some(Wdl::Win));
+
+    // The king in front of its pawn on the sixth rank wins; a rook pawn with the other king
+    // in the corner doesn't
+    assert_eq!(probe_wdl("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+    assert_eq!(
+        probe_wdl("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"),
+        Some(Wdl::Loss)
+    );
+    assert_eq!(
+        probe_wdl("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1"),
+        Some(Wdl::Loss)
+    );
+    assert_eq!(probe_wdl("k7/8/8/8/8/8/P7/K7 w - - 0 1"), Some(Wdl::Draw));
+    assert_eq!(probe_wdl("7k/8/8/8/8/8/7P/7K b - - 0 1"), Some(Wdl::Draw));
+
+    let mut position = Position::from_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+    position.set_tablebases(Some(Arc::clone(&tablebases)));
+    let dtz = position.probe_dtz().unwrap();
+    assert!(dtz > 0);
+
+    for ply in 0.. {
+        let ranked_moves = position.tablebase_root_moves().unwrap();
+        let Some(&(best_move, _)) = ranked_moves.iter().max_by_key(|&&(_, rank)| rank) else {
+            assert_eq!(position.get_game_state(), GameState::Checkmate(Side::White));
+            break;
+        };
+        assert!(ply <= dtz + 1, "No mate within {} plies", dtz); // DTZ can be rounded
+
+        position.make_move(best_move.from, best_move.to, best_move.promote);
+        position.ply = 0;
+        let wdl = position.probe_wdl().unwrap();
+        assert_eq!(wdl, if ply % 2 == 0 { Wdl::Loss } else { Wdl::Win });
+    }
+}
+
+#[test]
+fn root_moves_keep_the_win() {
+    // Ranked by DTZ, and by WDL alone when the DTZ file is missing
+    for with_dtz in [true, false] {
+        let dir = tablebase_dir(&format!("root_{}", with_dtz), with_dtz);
+
+        let mut position = position_with_tablebases(ROOK_ATTACKED_FEN, &dir);
+        assert_eq!(best_moves(&mut position), ROOK_SAVING_MOVES);
+
+        let mut position = position_with_tablebases("7k/2r5/1K6/8/8/8/8/8 b - - 0 1", &dir);
+        assert_eq!(
+            best_moves(&mut position),
+            [
+                "c7c1", "c7c2", "c7c3", "c7c4", "c7c8", "c7d7", "c7e7", "c7f7", "c7g7", "c7h7"
+            ]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn engine_searches_only_winning_root_moves() {
+    let dir = tablebase_dir("engine", true);
+
+    let mut engine = Engine::default();
+    assert_eq!(engine.set_syzygy_path(dir.to_str().unwrap()), Ok(2));
+    engine.set_position(Position::from_fen(ROOK_ATTACKED_FEN).unwrap());
+    engine.search_settings.max_depth = 2;
+
+    let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+    let best_move = Board::move_to_uci_string(
+        result.best_move_from.unwrap(),
+        result.best_move_to.unwrap(),
+        None,
+        false,
+    );
+    assert!(ROOK_SAVING_MOVES.contains(&best_move.as_str()));
+
+    // "searchmoves" is narrowed to its winning moves
+    engine.search_settings.search_moves = ["c2c3", "c2c5"]
+        .iter()
+        .map(|mv| Board::move_from_uci_string(mv).unwrap())
+        .collect();
+    let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+    assert_eq!(
+        result.principal_variation[0],
+        engine.search_settings.search_moves[1]
+    );
+
+    // An empty path turns probing off
+    assert_eq!(engine.set_syzygy_path(""), Ok(0));
+    assert!(engine.position.tablebases().is_none());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn search_scores_captures_into_tablebase_wins() {
+    let dir = tablebase_dir("search", false);
+
+    let mut engine = Engine::default();
+    engine.set_syzygy_path(dir.to_str().unwrap()).unwrap();
+    engine.set_position(Position::from_fen("k7/8/2n5/8/8/8/2R5/7K w - - 0 1").unwrap());
+    engine.search_settings.max_depth = 3;
+
+    let result = engine.think(None::<fn(u16, &[SearchLine], &mut Position)>);
+
+    assert_eq!(
+        Board::move_to_uci_string(
+            result.best_move_from.unwrap(),
+            result.best_move_to.unwrap(),
+            None,
+            false
+        ),
+        "c2c6"
+    );
+    assert!(matches!(result.evaluation, Score::Centipawns(score) if score > 5000));
+    assert!(engine.position.tb_hits > 0);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn api_probes_tablebases() {
+    assert!(
+        api::probe_tablebase(ROOK_ATTACKED_FEN).is_err(),
+        "No path set"
+    );
+
+    let dir = tablebase_dir("api", true);
+    assert_eq!(api::set_syzygy_path(dir.to_str().unwrap()), Ok(2));
+
+    let probe = api::probe_tablebase(ROOK_ATTACKED_FEN).unwrap();
+    assert_eq!(probe.wdl, Wdl::Win);
+    assert_eq!(probe.dtz, Some(2 * KRVK_DTZ_MOVES as i32 + 1));
+    assert_eq!(probe.best_moves, ROOK_SAVING_MOVES);
+
+    let probe = api::probe_tablebase("8/8/8/8/8/1k6/2R5/7K b - - 0 1").unwrap();
+    assert_eq!(probe.wdl, Wdl::Draw);
+    assert_eq!(probe.dtz, Some(0));
+    assert_eq!(probe.best_moves, ["b3c2"]);
+
+    assert!(api::probe_tablebase("8/8/8/8/8/1k6/8/2Q4K w - - 0 1").is_err());
+    assert!(api::probe_tablebase("garbage").is_err());
+
+    // A table that fails to load is reported rather than missing
+    std::fs::write(dir.join("KQvK.rtbw"), b"Not a table").unwrap();
+    assert_eq!(api::set_syzygy_path(dir.to_str().unwrap()), Ok(3));
+    let error = api::probe_tablebase("8/8/8/8/8/1k6/8/2Q4K w - - 0 1").unwrap_err();
+    assert!(error.contains("KQvK.rtbw"), "{}", error);
+
+    assert_eq!(api::set_syzygy_path(""), Ok(0));
+    assert!(api::probe_tablebase(ROOK_ATTACKED_FEN).is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,150 @@
+/// Tests for the evaluation parameters and Texel tuning
+mod test_utils;
+
+use std::sync::Arc;
+
+use chess_engine::{
+    engine::Engine,
+    params::EvalParams,
+    tuning::{Tuner, TuningPosition},
+    types::{Piece, TaperedScore},
+};
+use test_utils::*;
+
+/// White has the bishop pair, Black a knight and a bishop
+const BISHOP_PAIR_FEN: &str = "4k3/pppn1ppp/4b3/8/8/2B1B3/PPP2PPP/4K3 w - - 0 1";
+
+#[test]
+fn default_params_round_trip_through_file_format() {
+    let params = EvalParams::default();
+    assert_eq!(EvalParams::parse(&params.to_string()).unwrap(), params);
+
+    let values = params.values();
+    assert_eq!(EvalParams::from_values(&values), params);
+}
+
+#[test]
+fn parse_overrides_only_listed_params() {
+    let params = EvalParams::parse(
+        "# Comments and blank lines are ignored
+
+        bishop_pair 45 70 # Midgame, endgame
+        king_open_file_units 6",
+    )
+    .unwrap();
+
+    let expected = EvalParams {
+        bishop_pair: TaperedScore::new(45, 70),
+        king_open_file_units: 6,
+        ..EvalParams::default()
+    };
+
+    assert_eq!(params, expected);
+}
+
+#[test]
+fn parse_rejects_invalid_files() {
+    assert!(EvalParams::parse("unknown_param 1").is_err());
+    assert!(EvalParams::parse("bishop_pair 45").is_err());
+    assert!(EvalParams::parse("bishop_pair 45 70 10").is_err());
+    assert!(EvalParams::parse("bishop_pair 45 seventy").is_err());
+    assert!(EvalParams::load("missing.txt").is_err());
+}
+
+#[test]
+fn unused_weights_are_not_params() {
+    // The king is always on the board, and only knights to queens score mobility
+    assert!(EvalParams::parse("piece_value_midgame 100 320 330 500 900").is_ok());
+    assert!(EvalParams::parse("piece_value_midgame 100 320 330 500 900 0").is_err());
+    assert!(EvalParams::parse("mobility_endgame 4 5 4 2").is_ok());
+    assert!(EvalParams::parse("mobility_endgame 0 4 5 4 2 0").is_err());
+}
+
+#[test]
+fn eval_params_change_evaluation() {
+    let mut position = position_from_fen(BISHOP_PAIR_FEN);
+    let default_eval = position.evaluate();
+
+    let params = EvalParams {
+        bishop_pair: EvalParams::default().bishop_pair + TaperedScore::new(100, 100),
+        ..EvalParams::default()
+    };
+    position.set_eval_params(Arc::new(params));
+    assert_eq!(position.evaluate(), default_eval + 100);
+
+    // White has one more bishop
+    let mut params = EvalParams::default();
+    params.piece_value[Piece::Bishop as usize] += TaperedScore::new(50, 50);
+    position.set_eval_params(Arc::new(params));
+    assert_eq!(position.evaluate(), default_eval + 50);
+}
+
+#[test]
+fn engine_loads_eval_params_file() {
+    let path = std::env::temp_dir().join("chess_engine_tuning_tests_params.txt");
+    std::fs::write(&path, "bishop_pair 130 150").unwrap();
+
+    let mut engine = Engine::from_fen(BISHOP_PAIR_FEN);
+    let default_eval = engine.position.evaluate();
+
+    engine.load_eval_params(path.to_str().unwrap()).unwrap();
+    assert_eq!(engine.position.evaluate(), default_eval + 100);
+
+    // A new position keeps the parameters
+    engine.set_position(position_from_fen(BISHOP_PAIR_FEN));
+    assert_eq!(engine.position.evaluate(), default_eval + 100);
+
+    engine.use_default_eval_params();
+    assert_eq!(engine.position.evaluate(), default_eval);
+
+    assert!(engine.load_eval_params("missing.txt").is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn tuning_positions_parse_results() {
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 w - -";
+
+    for (line, result) in [
+        (format!("{} 0 1 [1.0]", fen), 1.0),
+        (format!("{} 0 1 [0.5]", fen), 0.5),
+        (format!("{} 0 1 0.0", fen), 0.0),
+        (format!("{} c9 \"1-0\";", fen), 1.0),
+        (format!("{} c9 \"1/2-1/2\";", fen), 0.5),
+        (format!("{} c9 \"0-1\";", fen), 0.0),
+    ] {
+        assert_eq!(TuningPosition::parse(&line).unwrap().result, result);
+    }
+
+    assert!(TuningPosition::parse(&format!("{} [2.0]", fen)).is_err());
+    assert!(TuningPosition::parse(&format!("{} c9 \"*\";", fen)).is_err());
+    assert!(TuningPosition::parse("4k3/8/8/8/8/8/4P3/4K3 w [1.0]").is_ok());
+    assert!(TuningPosition::parse("4k3/8/8/8/8/8/8/8 w - - [1.0]").is_err()); // No white king
+    assert!(TuningPosition::parse("4k3/8/8/8/8/8/4P3/4K2 w - - [1.0]").is_err());
+    assert!(TuningPosition::parse("4k3/8/8/8/8/8/4P3/4K3 x - - [1.0]").is_err());
+}
+
+#[test]
+fn local_search_lowers_error() {
+    let positions = [
+        "4k3/pppn1ppp/4b3/8/8/2B1B3/PPP2PPP/4K3 w - - [1.0]",
+        "4k3/pppb1ppp/4n3/8/8/2B1N3/PPP2PPP/4K3 b - - [0.5]",
+        "4k3/pp3ppp/8/8/8/8/PPP2PPP/4K3 w - - [1.0]",
+        "4k3/ppp2ppp/8/8/8/8/PP3PPP/4K3 b - - [0.0]",
+        "r3k3/ppp2ppp/8/8/8/8/PPP2PPP/4K2R w - - [0.5]",
+    ]
+    .map(|line| TuningPosition::parse(line).unwrap());
+
+    let mut tuner = Tuner::new(positions.into(), 2);
+    let mut params = EvalParams::default();
+
+    let k = tuner.fit_k(&params);
+    assert!(k > 0.0);
+
+    let error = tuner.error(&params);
+    let new_error = tuner.local_search_pass(&mut params, error, 5);
+
+    assert!(new_error < error);
+    assert!((tuner.error(&params) - new_error).abs() < 1e-12);
+    assert_ne!(params, EvalParams::default());
+}